@@ -0,0 +1,320 @@
+//! Stack frame and binary size budget checks of `anchor build`.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use solana_sbpf::{
+    aligned_memory::AlignedMemory,
+    ebpf::{self, HOST_ALIGN, INSN_SIZE},
+    elf_parser::Elf64,
+};
+
+use crate::config::ProgramBudget;
+
+/// Stack frame size of a function, in bytes, keyed by the function's (mangled) name.
+pub type StackFrames = BTreeMap<String, u64>;
+
+/// Collect the stack frame sizes of the functions of a program binary.
+///
+/// Functions are named after their symbols, and after their address if the binary is stripped.
+pub fn read_stack_frames(path: &Path) -> Result<StackFrames> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("Failed to read `{}`: {e}", path.display()))?;
+    let bytes = AlignedMemory::<HOST_ALIGN>::from_slice(&bytes);
+    let elf = Elf64::parse(bytes.as_slice())
+        .map_err(|e| anyhow!("Failed to parse `{}`: {e}", path.display()))?;
+
+    let text = elf
+        .section_header_table()
+        .iter()
+        .find(|section| {
+            elf.section_name(section.sh_name)
+                .is_ok_and(|name| name == b".text")
+        })
+        .ok_or_else(|| anyhow!("`{}` has no .text section", path.display()))?;
+    let code = text
+        .file_range()
+        .and_then(|range| bytes.as_slice().get(range))
+        .ok_or_else(|| anyhow!("Invalid .text section in `{}`", path.display()))?;
+
+    // Function entries, as instruction indexes into the .text section
+    let symbols = elf
+        .symbol_table()
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .iter()
+        .map(|symbol| (symbol, elf.symbol_name(symbol.st_name)))
+        .chain(
+            elf.dynamic_symbol_table()
+                .unwrap_or_default()
+                .iter()
+                .map(|symbol| (symbol, elf.dynamic_symbol_name(symbol.st_name))),
+        );
+    let mut functions = BTreeMap::new();
+    for (symbol, name) in symbols {
+        let (Ok(name), true) = (name, symbol.is_function()) else {
+            continue;
+        };
+        if let Some(offset) = symbol
+            .st_value
+            .checked_sub(text.sh_addr)
+            .filter(|offset| *offset < text.sh_size)
+        {
+            functions
+                .entry(offset as usize / INSN_SIZE)
+                .or_insert_with(|| String::from_utf8_lossy(name).into_owned());
+        }
+    }
+
+    Ok(stack_frames(
+        code,
+        text.sh_addr,
+        functions,
+        elf.file_header().e_flags,
+    ))
+}
+
+/// Compute the stack frame size of every function of the `.text` section of a program.
+///
+/// `functions` are the known function entries, keyed by instruction index, which are completed
+/// with the targets of relative calls. Each function ends at the next entry.
+///
+/// From SBPFv1, functions reserve their frame by moving the frame pointer, while SBPFv0 functions
+/// have a fixed frame, and only their accesses relative to the frame pointer show its used size.
+fn stack_frames(
+    code: &[u8],
+    text_addr: u64,
+    mut functions: BTreeMap<usize, String>,
+    sbpf_version: u32,
+) -> StackFrames {
+    let insn_count = code.len() / INSN_SIZE;
+    for pc in 0..insn_count {
+        let insn = ebpf::get_insn_unchecked(code, pc);
+        if insn.opc == ebpf::CALL_IMM && insn.src == 1 {
+            let target = pc as i64 + 1 + insn.imm;
+            if (0..insn_count as i64).contains(&target) {
+                functions.entry(target as usize).or_insert_with(|| {
+                    format!(
+                        "function at {:#x}",
+                        text_addr + (target as usize * INSN_SIZE) as u64
+                    )
+                });
+            }
+        }
+    }
+
+    let frame_ptr = ebpf::FRAME_PTR_REG as u8;
+    let mut frames = StackFrames::new();
+    let ends = functions.keys().skip(1).copied().chain([insn_count]);
+    for ((start, name), end) in functions.iter().zip(ends) {
+        let mut frame = 0;
+        // Registers holding the frame pointer
+        let mut frame_ptr_copies = [false; 16];
+        for pc in *start..end {
+            let insn = ebpf::get_insn_unchecked(code, pc);
+            let class = insn.opc & ebpf::BPF_CLS_MASK;
+            let offset = match insn.opc {
+                ebpf::ADD64_IMM if sbpf_version != 0 && insn.dst == frame_ptr => -insn.imm,
+                _ if sbpf_version != 0 => continue,
+                ebpf::MOV64_REG if insn.src == frame_ptr => {
+                    frame_ptr_copies[insn.dst as usize] = true;
+                    continue;
+                }
+                ebpf::ADD64_IMM if frame_ptr_copies[insn.dst as usize] => {
+                    frame_ptr_copies[insn.dst as usize] = false;
+                    -insn.imm
+                }
+                ebpf::CALL_IMM | ebpf::CALL_REG => {
+                    frame_ptr_copies[..=5].fill(false);
+                    continue;
+                }
+                _ if class == ebpf::BPF_LDX && insn.opc & 0xe0 == ebpf::BPF_MEM => {
+                    frame_ptr_copies[insn.dst as usize] = false;
+                    match insn.src == frame_ptr {
+                        true => -i64::from(insn.off),
+                        false => continue,
+                    }
+                }
+                _ if matches!(class, ebpf::BPF_ST | ebpf::BPF_STX)
+                    && insn.opc & 0xe0 == ebpf::BPF_MEM
+                    && insn.dst == frame_ptr =>
+                {
+                    -i64::from(insn.off)
+                }
+                _ => {
+                    // Any other write to a register overwrites its copy of the frame pointer
+                    if matches!(
+                        class,
+                        ebpf::BPF_LD | ebpf::BPF_ALU32_LOAD | ebpf::BPF_ALU64_STORE
+                    ) {
+                        frame_ptr_copies[insn.dst as usize] = false;
+                    }
+                    continue;
+                }
+            };
+            frame = frame.max(offset.max(0) as u64);
+        }
+
+        let size = frames.entry(name.clone()).or_default();
+        *size = (*size).max(frame);
+    }
+
+    frames
+}
+
+/// Report the stack frames and binary size of a program against its budget.
+///
+/// Stack frames are only checked if the budget has a stack frame limit.
+///
+/// Errors if the budget is exceeded.
+pub fn check(
+    lib_name: &str,
+    frames: &StackFrames,
+    binary_path: &Path,
+    budget: &ProgramBudget,
+) -> Result<()> {
+    let binary_size = fs::metadata(binary_path)
+        .map_err(|e| anyhow!("Failed to read `{}`: {e}", binary_path.display()))?
+        .len();
+
+    println!("\nBudget of `{lib_name}`:");
+    match budget.binary_size {
+        Some(max) => println!("  Binary size: {binary_size} / {max} bytes"),
+        None => println!("  Binary size: {binary_size} bytes"),
+    }
+
+    let mut errors = vec![];
+    if let Some(max) = budget.binary_size.filter(|max| binary_size > *max) {
+        errors.push(format!(
+            "binary size of {binary_size} bytes exceeds the budget of {max} bytes"
+        ));
+    }
+    if let Some(stack_budget) = budget.stack_frame {
+        let overflows = frames
+            .iter()
+            .filter(|(_, size)| **size > stack_budget)
+            .collect::<Vec<_>>();
+        if overflows.is_empty() {
+            // Only report the largest stack frame of the program when it fits
+            if let Some((function, size)) = frames.iter().max_by_key(|(_, size)| **size) {
+                println!("  Largest stack frame: {size} / {stack_budget} bytes in {function}");
+            }
+        } else {
+            for (function, size) in &overflows {
+                println!("  Stack frame: {size} / {stack_budget} bytes in {function}");
+            }
+            errors.push(format!(
+                "{} function(s) exceed the stack frame budget of {stack_budget} bytes",
+                overflows.len()
+            ));
+        }
+    }
+    if !errors.is_empty() {
+        bail!("Budget of `{lib_name}` exceeded: {}", errors.join(", "));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an instruction.
+    fn insn(opc: u8, dst: u8, src: u8, off: i16, imm: i32) -> [u8; INSN_SIZE] {
+        let [off_0, off_1] = off.to_le_bytes();
+        let [imm_0, imm_1, imm_2, imm_3] = imm.to_le_bytes();
+        [
+            opc,
+            src << 4 | dst,
+            off_0,
+            off_1,
+            imm_0,
+            imm_1,
+            imm_2,
+            imm_3,
+        ]
+    }
+
+    fn functions(entries: &[(usize, &str)]) -> BTreeMap<usize, String> {
+        entries
+            .iter()
+            .map(|(pc, name)| (*pc, name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_stack_frames_sbpf_v0() {
+        let code = [
+            // entrypoint
+            insn(ebpf::ST_DW_REG, 10, 6, -256, 0),
+            insn(ebpf::MOV64_REG, 1, 10, 0, 0),
+            insn(ebpf::ADD64_IMM, 1, 0, 0, -4160),
+            insn(ebpf::CALL_IMM, 0, 1, 0, 1),
+            insn(ebpf::EXIT, 0, 0, 0, 0),
+            // unnamed function
+            insn(ebpf::LD_DW_REG, 0, 10, -24, 0),
+            insn(ebpf::MOV64_REG, 2, 10, 0, 0),
+            insn(ebpf::MOV64_IMM, 2, 0, 0, 0),
+            insn(ebpf::ADD64_IMM, 2, 0, 0, -8192),
+            insn(ebpf::EXIT, 0, 0, 0, 0),
+            // leaf
+            insn(ebpf::ADD64_IMM, 0, 0, 0, 1),
+            insn(ebpf::EXIT, 0, 0, 0, 0),
+        ]
+        .concat();
+
+        let frames = stack_frames(
+            &code,
+            0x120,
+            functions(&[(0, "entrypoint"), (10, "leaf")]),
+            0,
+        );
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames["entrypoint"], 4160);
+        assert_eq!(frames["function at 0x148"], 24);
+        assert_eq!(frames["leaf"], 0);
+    }
+
+    #[test]
+    fn test_stack_frames_dynamic() {
+        let code = [
+            // entrypoint
+            insn(ebpf::ADD64_IMM, 10, 0, 0, -256),
+            insn(ebpf::MOV64_REG, 1, 10, 0, 0),
+            insn(ebpf::CALL_IMM, 0, 1, 0, 1),
+            insn(ebpf::RETURN, 0, 0, 0, 0),
+            // unnamed function
+            insn(ebpf::ADD64_IMM, 10, 0, 0, 0),
+            insn(ebpf::RETURN, 0, 0, 0, 0),
+        ]
+        .concat();
+
+        let frames = stack_frames(&code, 0, functions(&[(0, "entrypoint")]), 3);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames["entrypoint"], 256);
+        assert_eq!(frames["function at 0x20"], 0);
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary_path = dir.path().join("counter.so");
+        fs::write(&binary_path, [0; 100]).unwrap();
+
+        let frames = StackFrames::from([("large".to_owned(), 4160), ("small".to_owned(), 512)]);
+        let budget = |stack_frame, binary_size| ProgramBudget {
+            stack_frame,
+            binary_size,
+        };
+
+        assert!(check("counter", &frames, &binary_path, &budget(None, Some(100))).is_ok());
+        assert!(check("counter", &frames, &binary_path, &budget(None, Some(99))).is_err());
+        assert!(check("counter", &frames, &binary_path, &budget(Some(4096), None)).is_err());
+        assert!(check("counter", &frames, &binary_path, &budget(Some(1024), None)).is_err());
+
+        let frames = StackFrames::from([("small".to_owned(), 512)]);
+        assert!(check("counter", &frames, &binary_path, &budget(Some(512), None)).is_ok());
+        assert!(check("counter", &frames, &binary_path, &budget(Some(256), None)).is_err());
+    }
+}
@@ -326,6 +326,7 @@ pub struct Config {
     pub scripts: ScriptsConfig,
     pub hooks: HooksConfig,
     pub workspace: WorkspaceConfig,
+    pub budget: Option<BudgetConfig>,
     // Separate entry next to test_config because
     // "anchor localnet" only has access to the Anchor.toml,
     // not the Test.toml files
@@ -444,6 +445,76 @@ pub struct HooksConfig {
     post_deploy: Option<Hook>,
 }
 
+/// Resource budgets checked by `anchor build`.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetConfig {
+    /// Maximum stack frame size of a function in bytes, not checked if unset.
+    ///
+    /// Larger frames cause undefined behavior, so this can't exceed
+    /// [`BudgetConfig::MAX_STACK_FRAME`].
+    #[serde(alias = "stack-frame")]
+    pub stack_frame: Option<u64>,
+    /// Maximum size of a program binary in bytes
+    #[serde(alias = "binary-size")]
+    pub binary_size: Option<u64>,
+    /// Per program overrides, keyed by the program's lib name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub programs: BTreeMap<String, ProgramBudget>,
+}
+
+impl BudgetConfig {
+    /// Maximum stack frame size enforced by the runtime.
+    pub const MAX_STACK_FRAME: u64 = 4096;
+
+    /// Check that the stack frame budgets are within the runtime limit.
+    fn validate(self) -> Result<Self> {
+        let stack_frames = self.programs.iter().filter_map(|(lib_name, budget)| {
+            budget
+                .stack_frame
+                .map(|stack_frame| (format!("budget.programs.{lib_name}"), stack_frame))
+        });
+        for (section, stack_frame) in self
+            .stack_frame
+            .map(|stack_frame| ("budget".to_owned(), stack_frame))
+            .into_iter()
+            .chain(stack_frames)
+        {
+            if stack_frame > Self::MAX_STACK_FRAME {
+                return Err(anyhow!(
+                    "`{section}.stack-frame` of {stack_frame} bytes exceeds the runtime limit of \
+                    {} bytes, above which stack frames cause undefined behavior",
+                    Self::MAX_STACK_FRAME
+                ));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Get the budget of a program, with the program's overrides applied.
+    pub fn program(&self, lib_name: &str) -> ProgramBudget {
+        let overrides = self.programs.get(lib_name);
+        ProgramBudget {
+            stack_frame: overrides
+                .and_then(|budget| budget.stack_frame)
+                .or(self.stack_frame),
+            binary_size: overrides
+                .and_then(|budget| budget.binary_size)
+                .or(self.binary_size),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProgramBudget {
+    #[serde(alias = "stack-frame")]
+    pub stack_frame: Option<u64>,
+    #[serde(alias = "binary-size")]
+    pub binary_size: Option<u64>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum Hook {
@@ -500,6 +571,22 @@ impl ProgramArch {
             Self::Sbf => &["build-sbf", "--tools-version", "v1.52"],
         }
     }
+
+    /// Argument of the build subcommand setting the directory of the program binaries
+    pub fn out_dir_flag(&self) -> &'static str {
+        match self {
+            Self::Bpf => "--bpf-out-dir",
+            Self::Sbf => "--sbf-out-dir",
+        }
+    }
+
+    /// Target triple the programs are compiled to
+    pub fn target_triple(&self) -> &'static str {
+        match self {
+            Self::Bpf => "bpfel-unknown-unknown",
+            Self::Sbf => "sbf-solana-solana",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -627,6 +714,7 @@ struct _Config {
     workspace: Option<WorkspaceConfig>,
     scripts: Option<ScriptsConfig>,
     hooks: Option<HooksConfig>,
+    budget: Option<BudgetConfig>,
     test: Option<_TestValidator>,
     surfpool: Option<_SurfpoolConfig>,
 }
@@ -727,6 +815,7 @@ impl fmt::Display for Config {
                 false => Some(self.scripts.clone()),
             },
             hooks: Some(self.hooks.clone()),
+            budget: self.budget.clone(),
             programs,
             workspace: (!self.workspace.members.is_empty() || !self.workspace.exclude.is_empty())
                 .then(|| self.workspace.clone()),
@@ -754,6 +843,7 @@ impl FromStr for Config {
             },
             scripts: cfg.scripts.unwrap_or_default(),
             hooks: cfg.hooks.unwrap_or_default(),
+            budget: cfg.budget.map(BudgetConfig::validate).transpose()?,
             validator: None, // Will be set based on CLI flags
             test_validator: cfg.test.map(Into::into),
             test_config: None,
@@ -1688,4 +1778,44 @@ mod tests {
         let config = Config::from_str(&string).unwrap();
         assert!(!config.features.skip_lint);
     }
+
+    #[test]
+    fn parse_budget() {
+        let string = BASE_CONFIG.to_owned()
+            + "
+        [budget]
+        stack-frame = 3072
+        binary-size = 400000
+
+        [budget.programs.counter]
+        binary-size = 200000
+        ";
+        let config = Config::from_str(&string).unwrap();
+        let budget = config.budget.unwrap();
+        assert_eq!(
+            budget.program("counter"),
+            ProgramBudget {
+                stack_frame: Some(3072),
+                binary_size: Some(200000),
+            }
+        );
+        assert_eq!(budget.program("other").binary_size, Some(400000));
+    }
+
+    #[test]
+    fn parse_budget_above_stack_limit() {
+        for budget in [
+            "[budget]\nstack-frame = 8192",
+            "[budget.programs.counter]\nstack-frame = 8192",
+        ] {
+            let string = BASE_CONFIG.to_owned() + budget;
+            assert!(Config::from_str(&string).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_budget_no_section() {
+        let config = Config::from_str(BASE_CONFIG).unwrap();
+        assert!(config.budget.is_none());
+    }
 }
@@ -7,9 +7,9 @@ use crate::config::{
 use anchor_client::Cluster;
 use anchor_lang::prelude::UpgradeableLoaderState;
 use anchor_lang::solana_program::bpf_loader_upgradeable;
-use anchor_lang::AnchorDeserialize;
-use anchor_lang_idl::convert::convert_idl;
-use anchor_lang_idl::types::{Idl, IdlArrayLen, IdlDefinedFields, IdlType, IdlTypeDefTy};
+use anchor_lang_idl::coder::IdlCoder;
+use anchor_lang_idl::convert::{convert_idl, pin_idl};
+use anchor_lang_idl::types::{Idl, IdlType};
 use anyhow::{anyhow, bail, Context, Result};
 use checks::{check_anchor_version, check_deps, check_idl_build_feature, check_overflow};
 use clap::{CommandFactory, Parser};
@@ -18,7 +18,6 @@ use heck::{ToKebabCase, ToLowerCamelCase, ToPascalCase, ToSnakeCase};
 use regex::{Regex, RegexBuilder};
 use rust_template::{ProgramTemplate, TestTemplate};
 use semver::{Version, VersionReq};
-use serde_json::{json, Map, Value as JsonValue};
 use solana_cli_config::Config as SolanaCliConfig;
 use solana_commitment_config::CommitmentConfig;
 use solana_compute_budget_interface::ComputeBudgetInstruction;
@@ -43,9 +42,11 @@ use std::string::ToString;
 use std::sync::LazyLock;
 
 mod account;
+mod budget;
 mod checks;
 pub mod config;
 mod keygen;
+mod profile;
 mod program;
 pub mod rust_template;
 
@@ -231,6 +232,8 @@ pub enum Command {
         /// Environment variables to pass into the docker container
         #[clap(short, long, required = false)]
         env: Vec<String>,
+        #[clap(flatten)]
+        profile_cu: profile::ProfileCuArgs,
         /// Arguments to pass to the underlying `cargo build-sbf` command.
         #[clap(required = false, last = true)]
         cargo_args: Vec<String>,
@@ -682,6 +685,10 @@ pub enum IdlCommand {
         /// Fetch non-canonical metadata account (third-party metadata)
         #[clap(long)]
         non_canonical: bool,
+        /// Save a pinned copy of the IDL for `declare_program!` (`idls/<name>.json` in the
+        /// workspace root if `out` is not specified)
+        #[clap(long)]
+        for_declare: bool,
     },
     /// Convert legacy IDLs (pre Anchor 0.30) to the new IDL spec
     Convert {
@@ -1252,6 +1259,7 @@ fn process_command(opts: Opts) -> Result<()> {
             cargo_args,
             skip_lint,
             arch,
+            profile_cu,
         } => test(
             &opts.cfg_override,
             program_name,
@@ -1267,6 +1275,7 @@ fn process_command(opts: Opts) -> Result<()> {
             env,
             cargo_args,
             arch,
+            profile_cu,
         ),
         Command::Airdrop { amount, pubkey } => airdrop(&opts.cfg_override, amount, pubkey),
         Command::Cluster { subcmd } => cluster(subcmd),
@@ -2230,6 +2239,42 @@ fn docker_exec(container_name: &str, args: &[&str]) -> Result<()> {
     }
 }
 
+/// Get the directory the program binaries are written to, and the cargo target directory of the
+/// program in the current directory.
+fn build_dirs(arch: &ProgramArch, cargo_args: &[String]) -> Result<(PathBuf, PathBuf)> {
+    let output = std::process::Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("Failed to run `cargo metadata`: {e}"))?;
+    if !output.status.success() {
+        bail!("Failed to run `cargo metadata`");
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let target_dir = metadata["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("`cargo metadata` has no target directory"))?;
+
+    // Binaries are written to the `deploy` directory of the target directory by default
+    let out_dir_flag = arch.out_dir_flag();
+    let mut args = cargo_args.iter();
+    let mut out_dir = None;
+    while let Some(arg) = args.next() {
+        if arg == out_dir_flag {
+            out_dir = args.next().map(PathBuf::from);
+        } else if let Some(dir) = arg
+            .strip_prefix(out_dir_flag)
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            out_dir = Some(PathBuf::from(dir));
+        }
+    }
+    let deploy_dir = out_dir.unwrap_or_else(|| target_dir.join("deploy"));
+
+    Ok((deploy_dir, target_dir))
+}
+
 #[allow(clippy::too_many_arguments)]
 fn _build_rust_cwd(
     cfg: &WithPath<Config>,
@@ -2252,6 +2297,31 @@ fn _build_rust_cwd(
         std::process::exit(exit.status.code().unwrap_or(1));
     }
 
+    if let Some(budget) = &cfg.budget {
+        let lib_name = Manifest::from_path("Cargo.toml")?.lib_name()?;
+        let program_budget = budget.program(&lib_name);
+        let (deploy_dir, target_dir) = build_dirs(arch, &cargo_args)?;
+        let binary_path = deploy_dir.join(&lib_name).with_extension("so");
+
+        // Stack frames are read from the binary, so that they are also checked when the program
+        // isn't recompiled. The unstripped binary names the functions.
+        let frames = match program_budget.stack_frame {
+            Some(_) => {
+                let unstripped_path = target_dir
+                    .join(arch.target_triple())
+                    .join("release")
+                    .join(&lib_name)
+                    .with_extension("so");
+                budget::read_stack_frames(match unstripped_path.exists() {
+                    true => &unstripped_path,
+                    false => &binary_path,
+                })?
+            }
+            None => Default::default(),
+        };
+        budget::check(&lib_name, &frames, &binary_path, &program_budget)?;
+    }
+
     // Generate IDL
     if !no_idl {
         let idl = generate_idl(cfg, skip_lint, no_docs, &cargo_args)?;
@@ -2413,7 +2483,11 @@ fn idl(cfg_override: &ConfigOverride, subcmd: IdlCommand) -> Result<()> {
             address,
             out,
             non_canonical,
-        } => idl_fetch(cfg_override, address, out, non_canonical),
+            for_declare,
+        } => match for_declare {
+            true => idl_fetch_for_declare(cfg_override, address, out, non_canonical),
+            false => idl_fetch(cfg_override, address, out, non_canonical),
+        },
         IdlCommand::Convert {
             path,
             out,
@@ -2664,6 +2738,46 @@ fn idl_fetch(
     Ok(())
 }
 
+/// Fetch the IDL and save it with its hash embedded, so that `declare_program!` fails to compile
+/// if the IDL file is changed without re-pinning.
+fn idl_fetch_for_declare(
+    cfg_override: &ConfigOverride,
+    address: Pubkey,
+    out: Option<String>,
+    non_canonical: bool,
+) -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let tmp_path = tmp_dir.path().join("idl.json");
+    idl_fetch(
+        cfg_override,
+        address,
+        Some(tmp_path.display().to_string()),
+        non_canonical,
+    )?;
+    let idl = convert_idl(&fs::read(&tmp_path)?)?;
+
+    let out = match out {
+        Some(out) => PathBuf::from(out),
+        None => {
+            let root = match Config::discover(cfg_override)? {
+                Some(cfg) => cfg
+                    .path()
+                    .parent()
+                    .expect("Invalid Anchor.toml")
+                    .to_path_buf(),
+                None => std::env::current_dir()?,
+            };
+            let idl_dir = root.join("idls");
+            fs::create_dir_all(&idl_dir)?;
+            idl_dir.join(&idl.metadata.name).with_extension("json")
+        }
+    };
+    fs::write(&out, pin_idl(&idl)?)?;
+    println!("Pinned IDL written to {}", out.display());
+
+    Ok(())
+}
+
 fn idl_convert(path: String, out: Option<String>, program_id: Option<Pubkey>) -> Result<()> {
     let idl = fs::read(path)?;
 
@@ -2954,8 +3068,11 @@ fn account(
         .ok_or_else(|| anyhow!("Account `{account_type_name}` not found in IDL"))?;
     let mut data_view = &data[disc_len..];
 
-    let deserialized_json =
-        deserialize_idl_defined_type_to_json(&idl, account_type_name, &mut data_view)?;
+    let ty = IdlType::Defined {
+        name: account_type_name.into(),
+        generics: vec![],
+    };
+    let deserialized_json = IdlCoder::new(&idl.types).decode(&ty, &mut data_view)?;
 
     println!(
         "{}",
@@ -2965,190 +3082,6 @@ fn account(
     Ok(())
 }
 
-// Deserializes user defined IDL types by munching the account data(recursively).
-fn deserialize_idl_defined_type_to_json(
-    idl: &Idl,
-    defined_type_name: &str,
-    data: &mut &[u8],
-) -> Result<JsonValue, anyhow::Error> {
-    let defined_type = &idl
-        .accounts
-        .iter()
-        .find(|acc| acc.name == defined_type_name)
-        .and_then(|acc| idl.types.iter().find(|ty| ty.name == acc.name))
-        .or_else(|| idl.types.iter().find(|ty| ty.name == defined_type_name))
-        .ok_or_else(|| anyhow!("Type `{}` not found in IDL.", defined_type_name))?
-        .ty;
-
-    let mut deserialized_fields = Map::new();
-
-    match defined_type {
-        IdlTypeDefTy::Struct { fields } => {
-            if let Some(fields) = fields {
-                match fields {
-                    IdlDefinedFields::Named(fields) => {
-                        for field in fields {
-                            deserialized_fields.insert(
-                                field.name.clone(),
-                                deserialize_idl_type_to_json(&field.ty, data, idl)?,
-                            );
-                        }
-                    }
-                    IdlDefinedFields::Tuple(fields) => {
-                        let mut values = Vec::new();
-                        for field in fields {
-                            values.push(deserialize_idl_type_to_json(field, data, idl)?);
-                        }
-                        deserialized_fields
-                            .insert(defined_type_name.to_owned(), JsonValue::Array(values));
-                    }
-                }
-            }
-        }
-        IdlTypeDefTy::Enum { variants } => {
-            let repr = <u8 as AnchorDeserialize>::deserialize(data)?;
-
-            let variant = variants
-                .get(repr as usize)
-                .ok_or_else(|| anyhow!("Error while deserializing enum variant {repr}"))?;
-
-            let mut value = json!({});
-
-            if let Some(enum_field) = &variant.fields {
-                match enum_field {
-                    IdlDefinedFields::Named(fields) => {
-                        let mut values = Map::new();
-                        for field in fields {
-                            values.insert(
-                                field.name.clone(),
-                                deserialize_idl_type_to_json(&field.ty, data, idl)?,
-                            );
-                        }
-                        value = JsonValue::Object(values);
-                    }
-                    IdlDefinedFields::Tuple(fields) => {
-                        let mut values = Vec::new();
-                        for field in fields {
-                            values.push(deserialize_idl_type_to_json(field, data, idl)?);
-                        }
-                        value = JsonValue::Array(values);
-                    }
-                }
-            }
-
-            deserialized_fields.insert(variant.name.clone(), value);
-        }
-        IdlTypeDefTy::Type { alias } => {
-            return deserialize_idl_type_to_json(alias, data, idl);
-        }
-    }
-
-    Ok(JsonValue::Object(deserialized_fields))
-}
-
-// Deserializes a primitive type using AnchorDeserialize
-fn deserialize_idl_type_to_json(
-    idl_type: &IdlType,
-    data: &mut &[u8],
-    parent_idl: &Idl,
-) -> Result<JsonValue, anyhow::Error> {
-    if data.is_empty() {
-        return Err(anyhow::anyhow!("Unable to parse from empty bytes"));
-    }
-
-    Ok(match idl_type {
-        IdlType::Bool => json!(<bool as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U8 => {
-            json!(<u8 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I8 => {
-            json!(<i8 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::U16 => {
-            json!(<u16 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I16 => {
-            json!(<i16 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::U32 => {
-            json!(<u32 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I32 => {
-            json!(<i32 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::F32 => json!(<f32 as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U64 => {
-            json!(<u64 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I64 => {
-            json!(<i64 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::F64 => json!(<f64 as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U128 => {
-            json!(<u128 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I128 => {
-            json!(<i128 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::U256 => todo!("Upon completion of u256 IDL standard"),
-        IdlType::I256 => todo!("Upon completion of i256 IDL standard"),
-        IdlType::Bytes => JsonValue::Array(
-            <Vec<u8> as AnchorDeserialize>::deserialize(data)?
-                .iter()
-                .map(|i| json!(*i))
-                .collect(),
-        ),
-        IdlType::String => json!(<String as AnchorDeserialize>::deserialize(data)?),
-        IdlType::Pubkey => {
-            json!(<Pubkey as AnchorDeserialize>::deserialize(data)?.to_string())
-        }
-        IdlType::Array(ty, size) => match size {
-            IdlArrayLen::Value(size) => {
-                let mut array_data: Vec<JsonValue> = Vec::with_capacity(*size);
-
-                for _ in 0..*size {
-                    array_data.push(deserialize_idl_type_to_json(ty, data, parent_idl)?);
-                }
-
-                JsonValue::Array(array_data)
-            }
-            // TODO:
-            IdlArrayLen::Generic(_) => unimplemented!("Generic array length is not yet supported"),
-        },
-        IdlType::Option(ty) => {
-            let is_present = <u8 as AnchorDeserialize>::deserialize(data)?;
-
-            if is_present == 0 {
-                JsonValue::String("None".to_string())
-            } else {
-                deserialize_idl_type_to_json(ty, data, parent_idl)?
-            }
-        }
-        IdlType::Vec(ty) => {
-            let size: usize = <u32 as AnchorDeserialize>::deserialize(data)?
-                .try_into()
-                .unwrap();
-
-            let mut vec_data: Vec<JsonValue> = Vec::with_capacity(size);
-
-            for _ in 0..size {
-                vec_data.push(deserialize_idl_type_to_json(ty, data, parent_idl)?);
-            }
-
-            JsonValue::Array(vec_data)
-        }
-        IdlType::Defined {
-            name,
-            generics: _generics,
-        } => {
-            // TODO: Generics
-            deserialize_idl_defined_type_to_json(parent_idl, name, data)?
-        }
-        IdlType::Generic(generic) => json!(generic),
-        _ => unimplemented!("{idl_type:?}"),
-    })
-}
-
 enum OutFile {
     Stdout,
     File(PathBuf),
@@ -3171,6 +3104,7 @@ fn test(
     env_vars: Vec<String>,
     cargo_args: Vec<String>,
     arch: ProgramArch,
+    profile_cu: profile::ProfileCuArgs,
 ) -> Result<()> {
     let test_paths = tests_to_run
         .iter()
@@ -3224,6 +3158,7 @@ fn test(
 
         cfg.run_hooks(HookType::PreTest)?;
 
+        let mut cu_profile = profile_cu.profile_cu.then(profile::CuProfile::default);
         let mut is_first_suite = true;
         if let Some(test_script) = cfg.scripts.get_mut("test") {
             is_first_suite = false;
@@ -3264,6 +3199,7 @@ fn test(
                 &cfg.scripts,
                 &extra_args,
                 &cfg.surfpool_config,
+                cu_profile.as_mut(),
             )?;
         }
         if let Some(test_config) = &cfg.test_config {
@@ -3293,9 +3229,13 @@ fn test(
                     &test_suite.1.scripts,
                     &extra_args,
                     &cfg.surfpool_config,
+                    cu_profile.as_mut(),
                 )?;
             }
         }
+        if let Some(cu_profile) = &cu_profile {
+            profile::report(cu_profile, &profile_cu)?;
+        }
         cfg.run_hooks(HookType::PostTest)?;
         Ok(())
     })?
@@ -3314,6 +3254,7 @@ fn run_test_suite(
     scripts: &ScriptsConfig,
     extra_args: &[String],
     surfpool_config: &Option<SurfpoolConfig>,
+    cu_profile: Option<&mut profile::CuProfile>,
 ) -> Result<()> {
     println!("\nRunning test suite: {:#?}\n", test_suite_path.as_ref());
     let mut validator_handle = None;
@@ -3362,7 +3303,13 @@ fn run_test_suite(
     );
 
     // Setup log reader - kept alive until end of scope
-    let log_streams = match stream_logs(cfg, &url) {
+    //
+    // Profiling compute units requires the program logs regardless of the validator.
+    let log_streams = match if cu_profile.is_some() {
+        stream_solana_logs(cfg, &url)
+    } else {
+        stream_logs(cfg, &url)
+    } {
         Ok(streams) => Some(streams),
         Err(e) => {
             eprintln!("Warning: Failed to setup program log streaming: {:#}", e);
@@ -3407,11 +3354,20 @@ fn run_test_suite(
 
     // Explicitly shutdown log streams - closes WebSocket subscriptions
     if let Some(log_streams) = log_streams {
-        for handle in log_streams {
-            handle.shutdown();
+        let writers: Vec<_> = log_streams
+            .into_iter()
+            .map(LogStreamHandle::shutdown)
+            .collect();
+        if cu_profile.is_some() {
+            // The profile is read from the log files, so every received log must be written
+            join_log_writers(writers);
         }
     }
 
+    if let Some(cu_profile) = cu_profile {
+        cu_profile.record_log_dir(&Path::new(".anchor").join("program-logs"))?;
+    }
+
     // Must exist *after* shutting down the validator and log streams.
     match test_result {
         Ok(exit) => {
@@ -3722,22 +3678,42 @@ fn surfpool_flags(
 /// Call `shutdown()` to cleanly stop the thread.
 struct LogStreamHandle {
     subscription: PubsubClientSubscription<RpcResponse<RpcLogsResponse>>,
+    writer: std::thread::JoinHandle<()>,
 }
 
+/// Maximum time to wait for the log writers to finish once the log streams are shut down.
+const LOG_WRITER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 impl LogStreamHandle {
-    /// Explicitly shutdown the log stream
-    fn shutdown(self) {
+    /// Explicitly shutdown the log stream, returning the thread writing its logs.
+    fn shutdown(self) -> std::thread::JoinHandle<()> {
         // Send unsubscribe in a background thread to avoid blocking
         // PubsubClientSubscription::send_unsubscribe() can block indefinitely if WebSocket is stuck
         // The receiver threads will exit when the subscription closes
+        let subscription = self.subscription;
         std::thread::spawn(move || {
-            let _ = self.subscription.send_unsubscribe();
+            let _ = subscription.send_unsubscribe();
         });
+        self.writer
+    }
+}
+
+/// Wait for the log writers to write every log they received, unless their connection is stuck.
+fn join_log_writers(writers: Vec<std::thread::JoinHandle<()>>) {
+    let deadline = std::time::Instant::now() + LOG_WRITER_TIMEOUT;
+    for writer in writers {
+        while !writer.is_finished() {
+            if std::time::Instant::now() >= deadline {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let _ = writer.join();
     }
 }
 
 /// Spawns a thread to receive logs from a subscription and write them to a file
-fn spawn_log_receiver_thread<R>(receiver: R, log_file_path: PathBuf)
+fn spawn_log_receiver_thread<R>(receiver: R, log_file_path: PathBuf) -> std::thread::JoinHandle<()>
 where
     R: IntoIterator<Item = RpcResponse<RpcLogsResponse>> + Send + 'static,
 {
@@ -3769,7 +3745,7 @@ where
         } else {
             eprintln!("Failed to create log file: {:?}", log_file_path);
         }
-    });
+    })
 }
 
 fn stream_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<LogStreamHandle>> {
@@ -3859,10 +3835,11 @@ fn stream_solana_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<Lo
         };
 
         // Spawn thread to write logs to file
-        spawn_log_receiver_thread(receiver, log_file_path);
+        let writer = spawn_log_receiver_thread(receiver, log_file_path);
 
         handles.push(LogStreamHandle {
             subscription: client,
+            writer,
         });
     }
 
@@ -3892,10 +3869,11 @@ fn stream_solana_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<Lo
                 };
 
                 // Spawn thread to write logs to file
-                spawn_log_receiver_thread(receiver, log_file_path);
+                let writer = spawn_log_receiver_thread(receiver, log_file_path);
 
                 handles.push(LogStreamHandle {
                     subscription: client,
+                    writer,
                 });
             }
         }
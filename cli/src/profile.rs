@@ -0,0 +1,451 @@
+//! Compute unit profiling of `anchor test` runs.
+//!
+//! Program logs streamed during the tests are parsed for the compute units consumed by each
+//! instruction of the workspace programs, and the result is compared against a saved baseline.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Parser)]
+pub struct ProfileCuArgs {
+    /// Profile the compute units consumed by each instruction during the tests
+    #[clap(long)]
+    pub profile_cu: bool,
+    /// Baseline to compare the compute units against, relative to the workspace root
+    #[clap(long, default_value = "cu-baseline.json", requires = "profile_cu")]
+    pub cu_baseline: PathBuf,
+    /// Fail if an instruction uses more compute units than the baseline by this percentage
+    #[clap(long, default_value_t = 5.0, requires = "profile_cu")]
+    pub cu_threshold: f64,
+    /// Overwrite the baseline with the results of this run
+    #[clap(long, requires = "profile_cu")]
+    pub update_cu_baseline: bool,
+}
+
+/// Name used for invocations that don't log an Anchor instruction name.
+const UNKNOWN_INSTRUCTION: &str = "unknown";
+
+/// Compute units consumed, keyed by program name and instruction name.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CuProfile {
+    pub programs: BTreeMap<String, BTreeMap<String, CuStats>>,
+}
+
+/// Compute unit usage of a single instruction across every invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "CuStatsReport", from = "CuStatsReport")]
+pub struct CuStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub total: u64,
+}
+
+impl CuStats {
+    fn new(units: u64) -> Self {
+        Self {
+            count: 1,
+            min: units,
+            max: units,
+            total: units,
+        }
+    }
+
+    fn add(&mut self, units: u64) {
+        self.count += 1;
+        self.min = self.min.min(units);
+        self.max = self.max.max(units);
+        self.total += units;
+    }
+
+    /// Average compute units per invocation, rounded down.
+    pub fn avg(&self) -> u64 {
+        self.total.checked_div(self.count).unwrap_or_default()
+    }
+}
+
+/// Serialized form of [`CuStats`], which includes the average.
+#[derive(Serialize, Deserialize)]
+struct CuStatsReport {
+    count: u64,
+    min: u64,
+    max: u64,
+    avg: u64,
+    /// Missing from the reports written before the total was stored
+    #[serde(default)]
+    total: Option<u64>,
+}
+
+impl From<CuStats> for CuStatsReport {
+    fn from(stats: CuStats) -> Self {
+        Self {
+            count: stats.count,
+            min: stats.min,
+            max: stats.max,
+            avg: stats.avg(),
+            total: Some(stats.total),
+        }
+    }
+}
+
+impl From<CuStatsReport> for CuStats {
+    fn from(report: CuStatsReport) -> Self {
+        Self {
+            count: report.count,
+            min: report.min,
+            max: report.max,
+            total: report.total.unwrap_or(report.avg * report.count),
+        }
+    }
+}
+
+impl CuProfile {
+    /// Record an invocation of `program`'s `instruction`.
+    pub fn record(&mut self, program: &str, instruction: &str, units: u64) {
+        self.programs
+            .entry(program.to_owned())
+            .or_default()
+            .entry(instruction.to_owned())
+            .and_modify(|stats| stats.add(units))
+            .or_insert_with(|| CuStats::new(units));
+    }
+
+    /// Parse the program logs of a transaction, recording every invocation of `program_id`.
+    pub fn record_logs<S: AsRef<str>>(&mut self, program: &str, program_id: &str, logs: &[S]) {
+        // Invoked program ids, with the instruction name once it has been logged
+        let mut stack: Vec<(&str, Option<&str>)> = vec![];
+        for log in logs {
+            let Some(log) = log.as_ref().trim().strip_prefix("Program ") else {
+                continue;
+            };
+
+            if let Some(name) = log.strip_prefix("log: Instruction: ") {
+                if let Some((_, ix_name @ None)) = stack.last_mut() {
+                    *ix_name = Some(name.trim());
+                }
+            } else if let Some((id, rest)) = log.split_once(' ') {
+                if rest.starts_with("invoke [") {
+                    stack.push((id, None));
+                } else if rest == "success" || rest.starts_with("failed") {
+                    stack.pop();
+                } else if let Some(units) = parse_consumed(rest) {
+                    match stack.last() {
+                        Some((invoked, name)) if *invoked == id && id == program_id => {
+                            self.record(program, name.unwrap_or(UNKNOWN_INSTRUCTION), units)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parse the log files written while streaming the program logs of a test run.
+    ///
+    /// Files are named `<program_id>.<program_name>.log`.
+    pub fn record_log_dir(&mut self, dir: &Path) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some((program_id, program)) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split_once('.'))
+            else {
+                continue;
+            };
+
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read `{}`", path.display()))?;
+            for tx in content.split("Log Messages:").skip(1) {
+                let logs = tx
+                    .lines()
+                    .skip(1)
+                    .take_while(|line| !line.trim().is_empty())
+                    .collect::<Vec<_>>();
+                self.record_logs(program, program_id, &logs);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare against `baseline`, using the maximum compute units of each instruction.
+    pub fn diff(&self, baseline: &CuProfile) -> Vec<CuDiff> {
+        self.programs
+            .iter()
+            .flat_map(|(program, instructions)| {
+                instructions.iter().map(move |(instruction, stats)| {
+                    let baseline = baseline
+                        .programs
+                        .get(program)
+                        .and_then(|instructions| instructions.get(instruction))
+                        .map(|stats| stats.max);
+                    CuDiff {
+                        program: program.to_owned(),
+                        instruction: instruction.to_owned(),
+                        baseline,
+                        current: stats.max,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read `{}`", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse `{}`", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write `{}`", path.display()))
+    }
+}
+
+/// Change in compute units of an instruction compared to the baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CuDiff {
+    pub program: String,
+    pub instruction: String,
+    /// `None` if the instruction is not in the baseline
+    pub baseline: Option<u64>,
+    pub current: u64,
+}
+
+impl CuDiff {
+    /// Change in percent, `None` if there is nothing to compare against.
+    pub fn change(&self) -> Option<f64> {
+        self.baseline
+            .filter(|baseline| *baseline != 0)
+            .map(|baseline| (self.current as f64 - baseline as f64) * 100.0 / baseline as f64)
+    }
+
+    /// Whether the instruction regressed by more than `threshold` percent.
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        self.change().is_some_and(|change| change > threshold)
+    }
+}
+
+/// Write the report of a profiled test run and compare it against the baseline.
+///
+/// Errors if any instruction regressed by more than the threshold.
+pub fn report(profile: &CuProfile, args: &ProfileCuArgs) -> Result<()> {
+    let report_path = Path::new(".anchor").join("cu-profile.json");
+    profile.write(&report_path)?;
+    println!("\nCompute units report: {}", report_path.display());
+
+    if args.update_cu_baseline {
+        profile.write(&args.cu_baseline)?;
+        println!(
+            "Compute units baseline updated: {}",
+            args.cu_baseline.display()
+        );
+        return Ok(());
+    }
+    if !args.cu_baseline.exists() {
+        println!(
+            "No compute units baseline found at `{}`. Run with `--update-cu-baseline` to create it.",
+            args.cu_baseline.display()
+        );
+        return Ok(());
+    }
+
+    let diffs = profile.diff(&CuProfile::read(&args.cu_baseline)?);
+    let diff_path = Path::new(".anchor").join("cu-diff.json");
+    fs::write(&diff_path, serde_json::to_string_pretty(&diffs)? + "\n")
+        .with_context(|| format!("Failed to write `{}`", diff_path.display()))?;
+
+    let regressions = print_diff(&diffs, args.cu_threshold);
+    if !regressions.is_empty() {
+        bail!(
+            "{} instruction(s) exceeded the compute units threshold of {}%",
+            regressions.len(),
+            args.cu_threshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the diff as a table, returning the instructions that exceed `threshold`.
+fn print_diff(diffs: &[CuDiff], threshold: f64) -> Vec<&CuDiff> {
+    let name = |diff: &CuDiff| format!("{}::{}", diff.program, diff.instruction);
+    let width = diffs
+        .iter()
+        .map(|diff| name(diff).len())
+        .max()
+        .unwrap_or_default()
+        .max("Instruction".len());
+
+    println!(
+        "\n{:<width$}  {:>10}  {:>10}  {:>9}",
+        "Instruction", "Baseline", "Current", "Change"
+    );
+    for diff in diffs {
+        let baseline = diff
+            .baseline
+            .map(|units| units.to_string())
+            .unwrap_or_else(|| "-".into());
+        let change = diff
+            .change()
+            .map(|change| format!("{change:+.2}%"))
+            .unwrap_or_else(|| "new".into());
+        let marker = if diff.exceeds(threshold) { " !" } else { "" };
+        println!(
+            "{:<width$}  {:>10}  {:>10}  {:>9}{marker}",
+            name(diff),
+            baseline,
+            diff.current,
+            change
+        );
+    }
+
+    diffs
+        .iter()
+        .filter(|diff| diff.exceeds(threshold))
+        .collect()
+}
+
+fn parse_consumed(log: &str) -> Option<u64> {
+    log.strip_prefix("consumed ")?
+        .split_once(' ')?
+        .0
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+    fn logs() -> Vec<String> {
+        [
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]",
+            "Program log: Instruction: Initialize",
+            "Program 11111111111111111111111111111111 invoke [2]",
+            "Program 11111111111111111111111111111111 success",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 5000 of 200000 compute units",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]",
+            "Program log: Instruction: Initialize",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 7000 of 195000 compute units",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success",
+            "Program Other111111111111111111111111111111111111 invoke [1]",
+            "Program log: Instruction: Transfer",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [2]",
+            "Program log: Instruction: Update",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 1200 of 150000 compute units",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success",
+            "Program Other111111111111111111111111111111111111 consumed 3000 of 180000 compute units",
+            "Program Other111111111111111111111111111111111111 success",
+        ]
+        .map(Into::into)
+        .to_vec()
+    }
+
+    #[test]
+    fn test_record_logs() {
+        let mut profile = CuProfile::default();
+        profile.record_logs("counter", PROGRAM_ID, &logs());
+
+        let instructions = &profile.programs["counter"];
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions["Initialize"],
+            CuStats {
+                count: 2,
+                min: 5000,
+                max: 7000,
+                total: 12000
+            }
+        );
+        assert_eq!(instructions["Initialize"].avg(), 6000);
+        assert_eq!(instructions["Update"], CuStats::new(1200));
+    }
+
+    #[test]
+    fn test_stats_avg() {
+        let mut stats = CuStats::new(1);
+        stats.add(2);
+        stats.add(3);
+        // Rebuilding the total from the truncated average would give `(1 * 2 + 3) / 3 == 1`
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.avg(), 2);
+
+        let json = serde_json::to_value(stats).unwrap();
+        assert_eq!(json["avg"], 2);
+        assert_eq!(serde_json::from_value::<CuStats>(json).unwrap(), stats);
+    }
+
+    #[test]
+    fn test_record_log_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut content = String::new();
+        for _ in 0..2 {
+            content.push_str("Transaction executed in slot 1:\n  Status: Ok\n  Log Messages:\n");
+            for log in logs() {
+                content.push_str(&format!("    {log}\n"));
+            }
+            content.push('\n');
+        }
+        fs::write(
+            dir.path().join(format!("{PROGRAM_ID}.counter.log")),
+            content,
+        )
+        .unwrap();
+
+        let mut profile = CuProfile::default();
+        profile.record_log_dir(dir.path()).unwrap();
+        assert_eq!(profile.programs["counter"]["Initialize"].count, 4);
+        assert_eq!(profile.programs["counter"]["Update"].count, 2);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut baseline = CuProfile::default();
+        baseline.record("counter", "Initialize", 5000);
+        baseline.record("counter", "Update", 1000);
+
+        let mut profile = CuProfile::default();
+        profile.record("counter", "Initialize", 5100);
+        profile.record("counter", "Update", 1200);
+        profile.record("counter", "Close", 800);
+
+        let diffs = profile.diff(&baseline);
+        let change = |ix: &str| {
+            diffs
+                .iter()
+                .find(|diff| diff.instruction == ix)
+                .unwrap()
+                .change()
+        };
+        assert_eq!(change("Initialize"), Some(2.0));
+        assert_eq!(change("Update"), Some(20.0));
+        assert_eq!(change("Close"), None);
+
+        let exceeding = diffs
+            .iter()
+            .filter(|diff| diff.exceeds(5.0))
+            .map(|diff| diff.instruction.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(exceeding, ["Update"]);
+    }
+}
@@ -1,22 +1,24 @@
 use crate::{
-    ClientError, Config, EventContext, EventUnsubscriber, Program, ProgramAccountsIterator,
-    RequestBuilder,
+    AccountContext, AddressLookupTableAccount, ClientError, Config, DynamicProgram, EventContext,
+    EventNotification, EventUnsubscriber, Program, ProgramAccountsIterator, ReconnectConfig,
+    ReplayConfig, ReplayedEvents, RequestBuilder, RpcTransport, SendOutcome, SendPolicy,
+    Simulation, Transport,
 };
-use anchor_lang::{prelude::Pubkey, AccountDeserialize, Discriminator};
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, AnchorDeserialize, Discriminator};
+use anchor_lang_idl::types::Idl;
+use serde_json::Value;
 use solana_commitment_config::CommitmentConfig;
+use solana_instruction::Instruction;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
 #[cfg(not(feature = "mock"))]
 use solana_rpc_client::rpc_client::RpcClient;
 use solana_rpc_client_api::{config::RpcSendTransactionConfig, filter::RpcFilterType};
 use solana_signature::Signature;
 use solana_signer::Signer;
-use solana_transaction::Transaction;
+use solana_transaction::{versioned::VersionedTransaction, Transaction};
 
 use std::{marker::PhantomData, ops::Deref, sync::Arc};
-use tokio::{
-    runtime::{Builder, Handle},
-    sync::RwLock,
-};
+use tokio::runtime::{Builder, Handle};
 
 impl EventUnsubscriber<'_> {
     /// Unsubscribe gracefully.
@@ -31,8 +33,6 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         cfg: Config<C>,
         #[cfg(feature = "mock")] rpc_client: AsyncRpcClient,
     ) -> Result<Self, ClientError> {
-        let rt: tokio::runtime::Runtime = Builder::new_multi_thread().enable_all().build()?;
-
         #[cfg(not(feature = "mock"))]
         let rpc_client = {
             let comm_config = cfg.options.unwrap_or_default();
@@ -40,11 +40,22 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
             AsyncRpcClient::new_with_commitment(cluster_url.clone(), comm_config)
         };
 
+        let transport = RpcTransport::new(rpc_client, cfg.cluster.ws_url());
+        Self::new_with_transport(program_id, cfg, Arc::new(transport))
+    }
+
+    /// Create a program client that makes its requests through the given transport.
+    pub fn new_with_transport(
+        program_id: Pubkey,
+        cfg: Config<C>,
+        transport: Arc<dyn Transport>,
+    ) -> Result<Self, ClientError> {
+        let rt: tokio::runtime::Runtime = Builder::new_multi_thread().enable_all().build()?;
+
         Ok(Self {
             program_id,
             cfg,
-            sub_client: Arc::new(RwLock::new(None)),
-            internal_rpc_client: rpc_client,
+            transport,
             rt,
         })
     }
@@ -71,7 +82,7 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
             self.cfg.options,
             #[cfg(not(feature = "async"))]
             self.rt.handle(),
-            &self.internal_rpc_client,
+            &*self.transport,
         )
     }
 
@@ -80,6 +91,66 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         self.rt.block_on(self.account_internal(address))
     }
 
+    /// Send a transaction signed beforehand, e.g. offline with [`crate::add_signatures`].
+    pub fn send_signed_transaction(&self, tx: &Transaction) -> Result<Signature, ClientError> {
+        self.rt.block_on(self.send_signed_transaction_internal(tx))
+    }
+
+    /// Fetch the IDL of the program from its IDL account, see [`idl_address`](crate::idl_address).
+    pub fn fetch_idl(&self) -> Result<Idl, ClientError> {
+        self.rt.block_on(self.fetch_idl_internal())
+    }
+
+    /// Fetch the account at `address` and decode it with the IDL of the program, returning the
+    /// name of its type along with its value.
+    pub fn dynamic_account(
+        &self,
+        program: &DynamicProgram,
+        address: Pubkey,
+    ) -> Result<(String, Value), ClientError> {
+        self.rt
+            .block_on(self.dynamic_account_internal(program, address))
+    }
+
+    /// Build the instruction `name` with [`DynamicProgram::instruction`], fetching the accounts
+    /// whose fields are PDA seeds.
+    pub fn dynamic_instruction(
+        &self,
+        program: &DynamicProgram,
+        name: &str,
+        args: &Value,
+        accounts: &Value,
+    ) -> Result<Instruction, ClientError> {
+        self.rt
+            .block_on(self.dynamic_instruction_internal(program, name, args, accounts))
+    }
+
+    /// Returns the accounts at the given addresses, in the same order.
+    ///
+    /// The accounts are fetched with `getMultipleAccounts`, in chunks of
+    /// [`MAX_MULTIPLE_ACCOUNTS`](crate::MAX_MULTIPLE_ACCOUNTS) addresses. Accounts that don't exist are `None`.
+    pub fn accounts_multiple<T: AccountDeserialize>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Result<T, ClientError>>>, ClientError> {
+        self.rt.block_on(self.accounts_multiple_internal(addresses))
+    }
+
+    /// Returns the accounts at the given addresses, each decoded to the variant of `A` matching
+    /// its discriminator, e.g. the `parsers::Account` enum generated by `declare_program!`.
+    ///
+    /// See [`Self::accounts_multiple`].
+    pub fn accounts_multiple_any<A>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Result<A, ClientError>>>, ClientError>
+    where
+        A: for<'a> TryFrom<&'a [u8], Error = anchor_lang::error::Error>,
+    {
+        self.rt
+            .block_on(self.accounts_multiple_any_internal(addresses))
+    }
+
     /// Returns all program accounts of the given type matching the given filters
     pub fn accounts<T: AccountDeserialize + Discriminator>(
         &self,
@@ -97,6 +168,37 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         self.rt.block_on(self.accounts_lazy_internal(filters))
     }
 
+    /// Returns the address lookup table at the given address.
+    pub fn address_lookup_table(
+        &self,
+        address: Pubkey,
+    ) -> Result<AddressLookupTableAccount, ClientError> {
+        self.rt
+            .block_on(self.address_lookup_table_internal(address))
+    }
+
+    /// Creates an address lookup table with the payer as its authority, and extends it with the
+    /// given addresses, e.g. [`RequestBuilder::lookup_table_addresses`].
+    ///
+    /// Note that the table can only be used starting from the slot after it is extended.
+    pub fn create_lookup_table(
+        &self,
+        addresses: Vec<Pubkey>,
+    ) -> Result<AddressLookupTableAccount, ClientError> {
+        self.rt
+            .block_on(self.create_lookup_table_internal(addresses))
+    }
+
+    /// Extends an address lookup table, whose authority is the payer, with the given addresses.
+    pub fn extend_lookup_table(
+        &self,
+        table: Pubkey,
+        addresses: Vec<Pubkey>,
+    ) -> Result<(), ClientError> {
+        self.rt
+            .block_on(self.extend_lookup_table_internal(table, addresses))
+    }
+
     pub fn on<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
         &self,
         f: impl FnMut(&EventContext, T) + Send + 'static,
@@ -110,6 +212,113 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
             _lifetime_marker: PhantomData,
         })
     }
+
+    /// Subscribe to the changes of the account at `address`, decoded as `T`.
+    ///
+    /// Data that can't be decoded as `T`, e.g. of a closed account, is passed as an error.
+    pub fn subscribe_account<T: AccountDeserialize>(
+        &self,
+        address: Pubkey,
+        f: impl FnMut(&AccountContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self
+            .rt
+            .block_on(self.subscribe_account_internal(address, f))?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            runtime_handle: self.rt.handle(),
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Subscribe to the changes of the program's accounts of type `T` matching `filters`.
+    ///
+    /// Notifications that can't be decoded as `T` are passed as an error, with the default pubkey
+    /// in the context if their address is invalid.
+    pub fn subscribe_program_accounts<T: AccountDeserialize + Discriminator>(
+        &self,
+        filters: Vec<RpcFilterType>,
+        f: impl FnMut(&AccountContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self
+            .rt
+            .block_on(self.subscribe_program_accounts_internal(filters, f))?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            runtime_handle: self.rt.handle(),
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Subscribe to program logs, reconnecting with backoff when the connection is lost.
+    ///
+    /// Each reconnection is notified with an [`EventNotification::Gap`], and the missed events
+    /// are fetched over RPC if [`ReconnectConfig::backfill`] is set.
+    pub fn on_with_reconnect<T: anchor_lang::Event + anchor_lang::AnchorDeserialize + Send>(
+        &self,
+        config: ReconnectConfig,
+        f: impl FnMut(EventNotification<T>) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self
+            .rt
+            .block_on(self.on_with_reconnect_internal(config, f))?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            runtime_handle: self.rt.handle(),
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Subscribe to the events the program emits with `emit_cpi!`.
+    ///
+    /// The transactions of the program are fetched to decode the events from their inner
+    /// instructions, so events are received once their transaction is confirmed. This costs an
+    /// RPC request per transaction, which is why these events aren't delivered by `on`, whose
+    /// events are decoded from the logs of the subscription alone.
+    ///
+    /// A transaction that can't be fetched after a few attempts, or whose events can't be
+    /// decoded, is passed as an error, and the subscription goes on.
+    pub fn on_cpi<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        f: impl FnMut(&EventContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self.rt.block_on(self.on_cpi_internal(f))?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            runtime_handle: self.rt.handle(),
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Fetch a transaction and parse the events the program emitted in it with `emit_cpi!`.
+    pub fn cpi_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        signature: &Signature,
+    ) -> Result<Vec<T>, ClientError> {
+        self.rt.block_on(self.cpi_events_internal(signature))
+    }
+
+    /// Replay the events of the program from its transaction history, oldest first.
+    ///
+    /// The history is paged from the newest transaction, so with [`ReplayConfig::limit`] the
+    /// newest transactions are replayed. Page through the older ones by resuming with the
+    /// returned cursor as [`ReplayConfig::before`]. To stitch the history with a live
+    /// subscription, replay the events before the first transaction received from the
+    /// subscription.
+    pub fn replay_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        config: ReplayConfig,
+    ) -> Result<ReplayedEvents<T>, ClientError> {
+        self.rt.block_on(self.replay_events_internal(config))
+    }
 }
 
 impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Box<dyn Signer + 'a>> {
@@ -119,7 +328,7 @@ impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Box<dyn S
         payer: C,
         options: Option<CommitmentConfig>,
         handle: &'a Handle,
-        rpc_client: &'a AsyncRpcClient,
+        transport: &'a dyn Transport,
     ) -> Self {
         Self {
             program_id,
@@ -130,8 +339,11 @@ impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Box<dyn S
             instructions: Vec::new(),
             instruction_data: None,
             signers: Vec::new(),
+            address_lookup_tables: Vec::new(),
+            compute_budget: None,
+            durable_nonce: None,
             handle,
-            internal_rpc_client: rpc_client,
+            transport,
             _phantom: PhantomData,
         }
     }
@@ -146,10 +358,53 @@ impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Box<dyn S
         self.handle.block_on(self.signed_transaction_internal())
     }
 
+    pub fn signed_versioned_transaction(&self) -> Result<VersionedTransaction, ClientError> {
+        self.handle
+            .block_on(self.signed_versioned_transaction_internal())
+    }
+
+    /// Build the request's transaction without signing it, e.g. to sign it offline.
+    ///
+    /// Errors if the request has address lookup tables, which legacy transactions can't use.
+    pub fn unsigned_transaction(&self) -> Result<Transaction, ClientError> {
+        self.handle.block_on(self.unsigned_transaction_internal())
+    }
+
+    /// Build the request's transaction signed by the payer and the request's signers, leaving
+    /// the signatures of the other signers to be added with [`crate::add_signatures`].
+    ///
+    /// Errors if the request has address lookup tables, which legacy transactions can't use.
+    pub fn partially_signed_transaction(&self) -> Result<Transaction, ClientError> {
+        self.handle
+            .block_on(self.partially_signed_transaction_internal())
+    }
+
+    /// Simulate the request, without sending it.
+    pub fn simulate(&self) -> Result<Simulation, ClientError> {
+        self.handle.block_on(self.simulate_internal())
+    }
+
     pub fn send(&self) -> Result<Signature, ClientError> {
         self.handle.block_on(self.send_internal())
     }
 
+    /// Send the request and decode the value returned by the request's program.
+    ///
+    /// Errors if the transaction didn't set any return data, or if it was set by another program.
+    pub fn send_and_return<T: AnchorDeserialize>(&self) -> Result<(Signature, T), ClientError> {
+        self.handle.block_on(self.send_and_return_internal())
+    }
+
+    /// Send the request with the given policy, rebroadcasting it until it's confirmed, fails or
+    /// expires.
+    ///
+    /// Failures in the preflight simulation and on chain are returned as
+    /// [`SendOutcome::Failed`], along with the Anchor error logged by the program.
+    pub fn send_with_policy(&self, policy: SendPolicy) -> Result<SendOutcome, ClientError> {
+        self.handle
+            .block_on(self.send_with_policy_internal(&policy))
+    }
+
     pub fn send_with_spinner_and_config(
         &self,
         config: RpcSendTransactionConfig,
@@ -0,0 +1,840 @@
+//! Program client built from an IDL at runtime, without code generation.
+//!
+//! [`DynamicProgram`] is the counterpart of the `Program` class of the TypeScript client: it builds
+//! instructions from JSON arguments, resolves the constant and PDA accounts of the instructions,
+//! and decodes accounts, events and errors into JSON values.
+//!
+//! Values are represented as described in [`anchor_lang_idl::coder`].
+
+use crate::{ClientError, PROGRAM_DATA, PROGRAM_LOG};
+use anchor_lang::error::{ErrorCode, FromErrorCode};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::AnchorDeserialize;
+use anchor_lang_idl::coder::IdlCoder;
+use anchor_lang_idl::types::{
+    Idl, IdlDefinedFields, IdlErrorCode, IdlInstruction, IdlInstructionAccount,
+    IdlInstructionAccountItem, IdlSeed, IdlType, IdlTypeDefTy,
+};
+use regex::Regex;
+use serde_json::Value;
+use solana_instruction::{error::InstructionError, AccountMeta, Instruction};
+use solana_rpc_client_api::response::TransactionError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Seed of the legacy IDL account of a program, see [`idl_address`].
+const IDL_SEED: &str = "anchor:idl";
+
+/// Program client built from the IDL of the program.
+///
+/// # Example
+///
+/// ```ignore
+/// let dynamic = DynamicProgram::from_file("target/idl/my_program.json")?;
+///
+/// let ix = dynamic.instruction(
+///     "deposit",
+///     &json!({ "amount": 100 }),
+///     &json!({ "user": payer.pubkey().to_string() }),
+/// )?;
+/// program.request().instruction(ix).send()?;
+///
+/// let (name, value) = program.dynamic_account(&dynamic, vault)?;
+/// assert_eq!(value["amount"], 100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynamicProgram {
+    program_id: Pubkey,
+    idl: Idl,
+}
+
+impl DynamicProgram {
+    pub fn new(idl: Idl) -> Result<Self, ClientError> {
+        let program_id = Pubkey::from_str(&idl.address)
+            .map_err(|e| idl_error(format!("Invalid program address: {e}")))?;
+        Ok(Self { program_id, idl })
+    }
+
+    /// Load the IDL from a JSON file, which may also use the legacy IDL format.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Self::from_json(&std::fs::read(path)?)
+    }
+
+    /// Load the IDL from JSON, which may also use the legacy IDL format.
+    pub fn from_json(idl: &[u8]) -> Result<Self, ClientError> {
+        let idl = anchor_lang_idl::convert::convert_idl(idl)
+            .map_err(|e| idl_error(format!("Invalid IDL: {e}")))?;
+        Self::new(idl)
+    }
+
+    pub fn id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub fn idl(&self) -> &Idl {
+        &self.idl
+    }
+
+    /// Build the instruction `name` with the given arguments and accounts.
+    ///
+    /// `args` is an object of the instruction's arguments. `accounts` is an object of the
+    /// addresses of the instruction's accounts, nested in objects for the composite accounts,
+    /// along with an array of addresses for the remaining accounts.
+    ///
+    /// Accounts with a constant address or whose PDA seeds only depend on the arguments and other
+    /// accounts can be omitted. Omitted optional accounts are set to `None`.
+    ///
+    /// PDA seeds that are fields of other accounts require fetching these accounts, see
+    /// [`DynamicProgram::instruction_with`].
+    pub fn instruction(
+        &self,
+        name: &str,
+        args: &Value,
+        accounts: &Value,
+    ) -> Result<Instruction, ClientError> {
+        self.build_instruction(name, args, accounts, None)
+    }
+
+    /// Build the instruction `name` like [`DynamicProgram::instruction`], fetching the data of
+    /// the accounts whose fields are PDA seeds with `fetch`.
+    ///
+    /// `fetch` is called once per account, with its address.
+    pub fn instruction_with(
+        &self,
+        name: &str,
+        args: &Value,
+        accounts: &Value,
+        mut fetch: impl FnMut(&Pubkey) -> Result<Vec<u8>, ClientError>,
+    ) -> Result<Instruction, ClientError> {
+        self.build_instruction(name, args, accounts, Some(&mut fetch))
+    }
+
+    fn build_instruction<'f>(
+        &self,
+        name: &str,
+        args: &Value,
+        accounts: &Value,
+        fetch: Option<&'f mut FetchFn<'f>>,
+    ) -> Result<Instruction, ClientError> {
+        let ix = self
+            .idl
+            .instructions
+            .iter()
+            .find(|ix| ix.name == name)
+            .ok_or_else(|| idl_error(format!("Instruction `{name}` not found")))?;
+
+        let mut data = ix.discriminator.clone();
+        for arg in &ix.args {
+            let value = args.get(&arg.name).unwrap_or(&Value::Null);
+            let arg_data = self
+                .coder()
+                .encode(&arg.ty, value)
+                .map_err(|e| idl_error(format!("Argument `{}`: {e:#}", arg.name)))?;
+            data.extend(arg_data);
+        }
+
+        let accounts = AccountsResolver::new(self, ix, args, accounts, fetch).resolve()?;
+        Ok(Instruction::new_with_bytes(
+            self.program_id,
+            &data,
+            accounts,
+        ))
+    }
+
+    /// Decode the data of an account, returning the name of its type along with its value.
+    pub fn decode_account(&self, data: &[u8]) -> Result<(String, Value), ClientError> {
+        let account = self
+            .idl
+            .accounts
+            .iter()
+            .find(|acc| data.starts_with(&acc.discriminator))
+            .ok_or_else(|| idl_error("Unknown account discriminator"))?;
+
+        let value = self.decode_type(&account.name, &data[account.discriminator.len()..])?;
+        Ok((account.name.clone(), value))
+    }
+
+    /// Decode the data of an event, without the `Program data: ` prefix or base64 encoding,
+    /// returning the name of the event along with its value.
+    pub fn decode_event(&self, data: &[u8]) -> Result<(String, Value), ClientError> {
+        let event = self
+            .idl
+            .events
+            .iter()
+            .find(|event| data.starts_with(&event.discriminator))
+            .ok_or_else(|| idl_error("Unknown event discriminator"))?;
+
+        let value = self.decode_type(&event.name, &data[event.discriminator.len()..])?;
+        Ok((event.name.clone(), value))
+    }
+
+    /// Parse the events emitted by the program in the logs of a transaction, including in CPIs.
+    ///
+    /// Logs that aren't events of the program are skipped.
+    pub fn parse_events(&self, logs: &[String]) -> Vec<(String, Value)> {
+        use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+
+        let re = Regex::new(r"^Program ([1-9A-HJ-NP-Za-km-z]+) (invoke \[\d+\]|success|failed)")
+            .unwrap();
+        let program_id = self.program_id.to_string();
+
+        let mut stack: Vec<&str> = vec![];
+        let mut events = vec![];
+        for log in logs {
+            if let Some(captures) = re.captures(log) {
+                match captures.get(2).unwrap().as_str() {
+                    "success" | "failed" => {
+                        stack.pop();
+                    }
+                    _ => stack.push(captures.get(1).unwrap().as_str()),
+                }
+                continue;
+            }
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+
+            let data = log
+                .strip_prefix(PROGRAM_DATA)
+                .or_else(|| log.strip_prefix(PROGRAM_LOG))
+                .and_then(|data| STANDARD.decode(data).ok());
+            if let Some(event) = data.and_then(|data| self.decode_event(&data).ok()) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Decode the custom error of a failed instruction into an error of the program, or an Anchor
+    /// framework error.
+    ///
+    /// The value is an object with the `code`, `name` and `msg` of the error.
+    pub fn decode_error(&self, error: &TransactionError) -> Option<Value> {
+        let TransactionError::InstructionError(_, InstructionError::Custom(code)) = error else {
+            return None;
+        };
+        let error = self
+            .idl
+            .errors
+            .iter()
+            .find(|e| e.code == *code)
+            .cloned()
+            .or_else(|| {
+                ErrorCode::from_error_code(*code).map(|e| IdlErrorCode {
+                    code: *code,
+                    name: e.name(),
+                    msg: Some(e.to_string()),
+                })
+            })?;
+
+        serde_json::to_value(error).ok()
+    }
+
+    /// Encode the value of the defined type `name`.
+    pub fn encode_type(&self, name: &str, value: &Value) -> Result<Vec<u8>, ClientError> {
+        self.coder()
+            .encode(&defined(name), value)
+            .map_err(|e| idl_error(format!("{e:#}")))
+    }
+
+    /// Decode the data of the defined type `name`.
+    pub fn decode_type(&self, name: &str, mut data: &[u8]) -> Result<Value, ClientError> {
+        self.coder()
+            .decode(&defined(name), &mut data)
+            .map_err(|e| idl_error(format!("{e:#}")))
+    }
+
+    fn coder(&self) -> IdlCoder<'_> {
+        IdlCoder::new(&self.idl.types)
+    }
+}
+
+/// Address of the IDL account of a program, as created by the legacy `anchor idl init`.
+pub fn idl_address(program_id: &Pubkey) -> Pubkey {
+    let base = Pubkey::find_program_address(&[], program_id).0;
+    Pubkey::create_with_seed(&base, IDL_SEED, program_id).expect("Seed is always valid")
+}
+
+/// Decode the data of an IDL account: an 8-byte discriminator, the authority of the account and
+/// the zlib-compressed IDL.
+pub(crate) fn decode_idl_account(data: &[u8]) -> Result<Idl, ClientError> {
+    use std::io::Read;
+
+    let mut data = data
+        .get(8 + 32..)
+        .ok_or_else(|| idl_error("Invalid IDL account"))?;
+    let compressed = <Vec<u8> as AnchorDeserialize>::deserialize(&mut data)?;
+
+    let mut idl = vec![];
+    flate2::read::ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut idl)?;
+    anchor_lang_idl::convert::convert_idl(&idl).map_err(|e| idl_error(format!("Invalid IDL: {e}")))
+}
+
+fn idl_error(msg: impl ToString) -> ClientError {
+    ClientError::IdlError(msg.to_string())
+}
+
+fn defined(name: &str) -> IdlType {
+    IdlType::Defined {
+        name: name.into(),
+        generics: vec![],
+    }
+}
+
+/// Fetcher of the data of an account, see [`DynamicProgram::instruction_with`].
+type FetchFn<'a> = dyn FnMut(&Pubkey) -> Result<Vec<u8>, ClientError> + 'a;
+
+/// Resolver of the accounts of an instruction, from the given addresses, the constant addresses
+/// and the PDA seeds of the IDL.
+struct AccountsResolver<'a, 'f> {
+    program: &'a DynamicProgram,
+    ix: &'a IdlInstruction,
+    args: &'a Value,
+    accounts: &'a Value,
+    /// Fetcher of the data of the accounts whose fields are seeds
+    fetch: Option<&'f mut FetchFn<'f>>,
+    /// Decoded accounts whose fields are seeds, along with the name of their type
+    fetched: HashMap<Pubkey, (String, Value)>,
+    /// Resolved addresses, keyed by the path of the account, e.g. `nested.vault`
+    resolved: HashMap<String, Pubkey>,
+    /// Why the unresolved PDAs can't be resolved, keyed by the path of the account
+    unresolved: HashMap<String, String>,
+}
+
+impl<'a, 'f> AccountsResolver<'a, 'f> {
+    fn new(
+        program: &'a DynamicProgram,
+        ix: &'a IdlInstruction,
+        args: &'a Value,
+        accounts: &'a Value,
+        fetch: Option<&'f mut FetchFn<'f>>,
+    ) -> Self {
+        Self {
+            program,
+            ix,
+            args,
+            accounts,
+            fetch,
+            fetched: HashMap::new(),
+            resolved: HashMap::new(),
+            unresolved: HashMap::new(),
+        }
+    }
+
+    fn resolve(mut self) -> Result<Vec<AccountMeta>, ClientError> {
+        let mut pending = vec![];
+        flatten_accounts(&self.ix.accounts, "", &mut pending);
+
+        // Seeds may depend on accounts resolved later, so resolve until there's no progress
+        loop {
+            let count = self.resolved.len();
+            for (path, acc) in &pending {
+                if !self.resolved.contains_key(path) {
+                    if let Some(pubkey) = self.resolve_account(path, acc)? {
+                        self.resolved.insert(path.clone(), pubkey);
+                    }
+                }
+            }
+            if self.resolved.len() == count {
+                break;
+            }
+        }
+
+        let mut metas = vec![];
+        self.push_metas(&self.ix.accounts, "", &mut metas)?;
+        Ok(metas)
+    }
+
+    fn push_metas(
+        &self,
+        items: &[IdlInstructionAccountItem],
+        prefix: &str,
+        metas: &mut Vec<AccountMeta>,
+    ) -> Result<(), ClientError> {
+        for item in items {
+            match item {
+                IdlInstructionAccountItem::Single(acc) => {
+                    let path = format!("{prefix}{}", acc.name);
+                    let meta = match self.resolved.get(&path) {
+                        Some(pubkey) if acc.writable => AccountMeta::new(*pubkey, acc.signer),
+                        Some(pubkey) => AccountMeta::new_readonly(*pubkey, acc.signer),
+                        // Anchor uses the program id as the address of omitted optional accounts
+                        None if acc.optional => AccountMeta::new_readonly(self.program.id(), false),
+                        None => {
+                            let mut msg = format!(
+                                "Unable to resolve account `{path}` of instruction `{}`",
+                                self.ix.name
+                            );
+                            if let Some(reason) = self.unresolved.get(&path) {
+                                msg.push_str(&format!(": {reason}"));
+                            }
+                            return Err(idl_error(msg));
+                        }
+                    };
+                    metas.push(meta);
+                }
+                IdlInstructionAccountItem::Composite(accs) => {
+                    let prefix = format!("{prefix}{}.", accs.name);
+                    self.push_metas(&accs.accounts, &prefix, metas)?;
+                }
+                IdlInstructionAccountItem::Remaining(remaining) => {
+                    let path = format!("{prefix}{}", remaining.name);
+                    let addresses = match self.provided(&path) {
+                        Some(Value::Array(addresses)) => addresses.as_slice(),
+                        Some(_) => return Err(idl_error(format!("`{path}` must be an array"))),
+                        None => &[],
+                    };
+                    for address in addresses {
+                        let pubkey = parse_pubkey(address)
+                            .map_err(|e| idl_error(format!("Account `{path}`: {e}")))?;
+                        metas.push(match remaining.writable {
+                            true => AccountMeta::new(pubkey, remaining.signer),
+                            false => AccountMeta::new_readonly(pubkey, remaining.signer),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Address of the account, `None` if it can't be resolved yet.
+    fn resolve_account(
+        &mut self,
+        path: &str,
+        acc: &IdlInstructionAccount,
+    ) -> Result<Option<Pubkey>, ClientError> {
+        if let Some(address) = self.provided(path) {
+            return parse_pubkey(address)
+                .map(Some)
+                .map_err(|e| idl_error(format!("Account `{path}`: {e}")));
+        }
+        if let Some(address) = &acc.address {
+            return Pubkey::from_str(address)
+                .map(Some)
+                .map_err(|e| idl_error(format!("Account `{path}`: {e}")));
+        }
+        let Some(pda) = &acc.pda else {
+            return Ok(None);
+        };
+
+        let prefix = path.rsplit_once('.').map(|(prefix, _)| prefix);
+        let mut seeds = vec![];
+        for seed in &pda.seeds {
+            match self.seed(seed, prefix, path)? {
+                Some(seed) => seeds.push(seed),
+                None => return Ok(None),
+            }
+        }
+        let program_id = match &pda.program {
+            Some(seed) => match self.seed(seed, prefix, path)? {
+                Some(program_id) => Pubkey::try_from(program_id.as_slice())
+                    .map_err(|_| idl_error(format!("Invalid PDA program of `{path}`")))?,
+                None => return Ok(None),
+            },
+            None => self.program.id(),
+        };
+
+        let seeds = seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Ok(Some(Pubkey::find_program_address(&seeds, &program_id).0))
+    }
+
+    /// Bytes of the seed of the PDA at `pda_path`, `None` if it depends on an account that isn't
+    /// resolved yet, or on the fields of an account that can't be fetched.
+    fn seed(
+        &mut self,
+        seed: &IdlSeed,
+        prefix: Option<&str>,
+        pda_path: &str,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        let seed = match seed {
+            IdlSeed::Const(seed) => return Ok(Some(seed.value.clone())),
+            IdlSeed::Arg(seed) => return self.arg_seed(&seed.path).map(Some),
+            IdlSeed::Account(seed) => seed,
+        };
+
+        // The path is the account, followed by the fields of its data, e.g. `vault.authority`
+        let (name, fields) = match seed.path.split_once('.') {
+            Some((name, fields)) => (name, Some(fields)),
+            None => (seed.path.as_str(), None),
+        };
+        let pubkey = prefix
+            .and_then(|prefix| self.resolved.get(&format!("{prefix}.{name}")))
+            .or_else(|| self.resolved.get(name))
+            .copied();
+        let (pubkey, fields) = match (pubkey, fields) {
+            (Some(pubkey), None) => return Ok(Some(pubkey.to_bytes().to_vec())),
+            (Some(pubkey), Some(fields)) => (pubkey, fields),
+            (None, _) => {
+                self.unresolved.insert(
+                    pda_path.to_owned(),
+                    format!(
+                        "its seed `{}` depends on the unresolved account `{name}`",
+                        seed.path
+                    ),
+                );
+                return Ok(None);
+            }
+        };
+
+        if !self.fetched.contains_key(&pubkey) {
+            let Some(fetch) = self.fetch.as_mut() else {
+                self.unresolved.insert(
+                    pda_path.to_owned(),
+                    format!(
+                        "its seed `{}` is a field of account `{name}`, which requires fetching \
+                         the account with `DynamicProgram::instruction_with`",
+                        seed.path
+                    ),
+                );
+                return Ok(None);
+            };
+            let data = fetch(&pubkey)?;
+            let account = match self
+                .program
+                .idl
+                .accounts
+                .iter()
+                .find(|acc| Some(&acc.name) == seed.account.as_ref())
+            {
+                // Accounts of other programs may not have the discriminator of the IDL
+                Some(acc) => {
+                    let data =
+                        data.strip_prefix(acc.discriminator.as_slice())
+                            .ok_or_else(|| {
+                                idl_error(format!("Seed account `{name}` isn't a `{}`", acc.name))
+                            })?;
+                    (acc.name.clone(), self.program.decode_type(&acc.name, data)?)
+                }
+                None => self.program.decode_account(&data)?,
+            };
+            self.fetched.insert(pubkey, account);
+        }
+
+        let (ty, value) = &self.fetched[&pubkey];
+        self.field_seed(
+            "account",
+            &seed.path,
+            &defined(ty),
+            value,
+            fields.split('.'),
+        )
+        .map(Some)
+    }
+
+    /// Bytes of the argument at `path`, e.g. `params.owner`.
+    fn arg_seed(&self, path: &str) -> Result<Vec<u8>, ClientError> {
+        let mut segments = path.split('.');
+        let name = segments.next().unwrap_or_default();
+        let ty = &self
+            .ix
+            .args
+            .iter()
+            .find(|arg| arg.name == name)
+            .ok_or_else(|| idl_error(format!("Seed argument `{path}` not found")))?
+            .ty;
+        let value = self.args.get(name).unwrap_or(&Value::Null);
+        self.field_seed("argument", path, ty, value, segments)
+    }
+
+    /// Bytes of the field at `segments` of a value of type `ty`, the seed `kind` at `path`.
+    fn field_seed<'s, 't>(
+        &self,
+        kind: &str,
+        path: &str,
+        mut ty: &'t IdlType,
+        mut value: &'t Value,
+        segments: impl Iterator<Item = &'s str>,
+    ) -> Result<Vec<u8>, ClientError>
+    where
+        'a: 't,
+    {
+        let coder = self.program.coder();
+        for segment in segments {
+            let field = match ty {
+                IdlType::Defined { name, .. } => match &coder
+                    .type_def(name)
+                    .map_err(|e| idl_error(e.to_string()))?
+                    .ty
+                {
+                    IdlTypeDefTy::Struct {
+                        fields: Some(IdlDefinedFields::Named(fields)),
+                    } => fields.iter().find(|field| field.name == segment),
+                    _ => None,
+                },
+                _ => None,
+            }
+            .ok_or_else(|| idl_error(format!("Seed {kind} `{path}` not found")))?;
+            ty = &field.ty;
+            value = value.get(segment).unwrap_or(&Value::Null);
+        }
+
+        // Strings and bytes are used as seeds without their length prefix
+        match (ty, value) {
+            (IdlType::String, Value::String(s)) => Ok(s.as_bytes().to_vec()),
+            _ => {
+                let mut seed = coder
+                    .encode(ty, value)
+                    .map_err(|e| idl_error(format!("Seed {kind} `{path}`: {e:#}")))?;
+                if matches!(ty, IdlType::Bytes) {
+                    seed.drain(..4);
+                }
+                Ok(seed)
+            }
+        }
+    }
+
+    /// Address given for the account at `path`.
+    fn provided(&self, path: &str) -> Option<&'a Value> {
+        path.split('.')
+            .try_fold(self.accounts, |value, segment| value.get(segment))
+            .filter(|value| !value.is_null())
+    }
+}
+
+fn flatten_accounts<'a>(
+    items: &'a [IdlInstructionAccountItem],
+    prefix: &str,
+    accounts: &mut Vec<(String, &'a IdlInstructionAccount)>,
+) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(acc) => {
+                accounts.push((format!("{prefix}{}", acc.name), acc));
+            }
+            IdlInstructionAccountItem::Composite(accs) => {
+                flatten_accounts(&accs.accounts, &format!("{prefix}{}.", accs.name), accounts);
+            }
+            IdlInstructionAccountItem::Remaining(_) => {}
+        }
+    }
+}
+
+fn parse_pubkey(value: &Value) -> Result<Pubkey, String> {
+    let address = value.as_str().ok_or("expected a base58 address")?;
+    Pubkey::from_str(address).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+
+    const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+    fn program() -> DynamicProgram {
+        let idl = json!({
+            "address": PROGRAM_ID,
+            "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "deposit",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    { "name": "user", "writable": true, "signer": true },
+                    {
+                        "name": "vault",
+                        "writable": true,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "value": [118, 97, 117, 108, 116] },
+                                { "kind": "account", "path": "user" },
+                                { "kind": "arg", "path": "name" }
+                            ]
+                        }
+                    },
+                    { "name": "system_program", "address": "11111111111111111111111111111111" }
+                ],
+                "args": [
+                    { "name": "name", "type": "string" },
+                    { "name": "amount", "type": "u64" }
+                ]
+            }, {
+                "name": "withdraw",
+                "discriminator": [2, 2, 2, 2, 2, 2, 2, 2],
+                "accounts": [
+                    { "name": "vault", "writable": true },
+                    {
+                        "name": "receipt",
+                        "pda": {
+                            "seeds": [
+                                { "kind": "account", "path": "vault.authority", "account": "Vault" }
+                            ]
+                        }
+                    }
+                ],
+                "args": []
+            }],
+            "accounts": [{ "name": "Vault", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+            "events": [{ "name": "Deposited", "discriminator": [7, 7, 7, 7, 7, 7, 7, 7] }],
+            "errors": [{ "code": 6000, "name": "InsufficientFunds", "msg": "Not enough funds" }],
+            "types": [
+                {
+                    "name": "Vault",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "authority", "type": "pubkey" },
+                            { "name": "amount", "type": "u128" },
+                            { "name": "kind", "type": { "defined": { "name": "Kind" } } },
+                            { "name": "memo", "type": { "option": "string" } }
+                        ]
+                    }
+                },
+                {
+                    "name": "Kind",
+                    "type": {
+                        "kind": "enum",
+                        "variants": [
+                            { "name": "Personal" },
+                            { "name": "Shared", "fields": [{ "name": "owners", "type": "u8" }] }
+                        ]
+                    }
+                },
+                {
+                    "name": "Deposited",
+                    "type": { "kind": "struct", "fields": [{ "name": "amount", "type": "u64" }] }
+                }
+            ]
+        });
+        DynamicProgram::from_json(idl.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn build_instruction() {
+        let program = program();
+        let user = Pubkey::new_unique();
+        let ix = program
+            .instruction(
+                "deposit",
+                &json!({ "name": "main", "amount": 100 }),
+                &json!({ "user": user.to_string() }),
+            )
+            .unwrap();
+
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        data.extend_from_slice(&[4, 0, 0, 0]);
+        data.extend_from_slice(b"main");
+        data.extend_from_slice(&100u64.to_le_bytes());
+        assert_eq!(ix.data, data);
+
+        // The string seed is used without its length prefix
+        let (vault, _) =
+            Pubkey::find_program_address(&[b"vault", user.as_ref(), b"main"], &program.id());
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(user, true),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(Pubkey::default(), false),
+            ]
+        );
+
+        assert!(program
+            .instruction(
+                "deposit",
+                &json!({ "name": "main", "amount": 100 }),
+                &json!({})
+            )
+            .is_err());
+        assert!(program
+            .instruction(
+                "deposit",
+                &json!({ "name": "main", "amount": -1 }),
+                &json!({ "user": user.to_string() })
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn build_instruction_with_account_field_seed() {
+        let program = program();
+        let vault = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let mut data = vec![9; 8];
+        data.extend(
+            program
+                .encode_type(
+                    "Vault",
+                    &json!({
+                        "authority": authority.to_string(),
+                        "amount": 0,
+                        "kind": "Personal",
+                        "memo": null,
+                    }),
+                )
+                .unwrap(),
+        );
+        let accounts = json!({ "vault": vault.to_string() });
+
+        let err = program
+            .instruction("withdraw", &json!({}), &accounts)
+            .unwrap_err();
+        assert!(err.to_string().contains("`vault.authority`"), "{err}");
+
+        let mut fetched = vec![];
+        let ix = program
+            .instruction_with("withdraw", &json!({}), &accounts, |address| {
+                fetched.push(*address);
+                Ok(data.clone())
+            })
+            .unwrap();
+        assert_eq!(fetched, vec![vault]);
+        let (receipt, _) = Pubkey::find_program_address(&[authority.as_ref()], &program.id());
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(receipt, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_account_event_and_error() {
+        let program = program();
+        let authority = Pubkey::new_unique();
+        let value = json!({
+            "authority": authority.to_string(),
+            "amount": u128::MAX.to_string(),
+            "kind": { "Shared": { "owners": 3 } },
+            "memo": null,
+        });
+
+        let mut data = vec![9; 8];
+        data.extend(program.encode_type("Vault", &value).unwrap());
+        assert_eq!(
+            program.decode_account(&data).unwrap(),
+            ("Vault".into(), value)
+        );
+
+        let mut event = vec![7; 8];
+        event.extend_from_slice(&5u64.to_le_bytes());
+        let logs = [
+            format!("Program {PROGRAM_ID} invoke [1]"),
+            format!("Program data: {}", STANDARD.encode(&event)),
+            "Program 11111111111111111111111111111111 invoke [2]".into(),
+            format!("Program data: {}", STANDARD.encode(&event)),
+            "Program 11111111111111111111111111111111 success".into(),
+            format!("Program {PROGRAM_ID} success"),
+        ];
+        assert_eq!(
+            program.parse_events(&logs),
+            vec![("Deposited".into(), json!({ "amount": 5 }))]
+        );
+
+        let error = |code| TransactionError::InstructionError(0, InstructionError::Custom(code));
+        assert_eq!(
+            program.decode_error(&error(6000)).unwrap()["name"],
+            "InsufficientFunds"
+        );
+        assert_eq!(
+            program.decode_error(&error(2000)).unwrap()["name"],
+            "ConstraintMut"
+        );
+        assert!(program.decode_error(&error(1)).is_none());
+    }
+}
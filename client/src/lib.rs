@@ -68,53 +68,92 @@
 //! useful for mocking RPC responses, e.g. via [`RpcClient::new_mock`].
 //!
 //! [`RpcClient::new_mock`]: https://docs.rs/solana-rpc-client/3.0.0/solana_rpc_client/rpc_client/struct.RpcClient.html#method.new_mock
+//!
+//! # Transports
+//!
+//! Requests and subscriptions are made through a [`Transport`], which is an [`RpcTransport`] to the
+//! client's cluster by default. Use [`Client::program_with_transport`] to make them through another
+//! transport, e.g. a [`MockTransport`] that serves accounts from memory in unit tests.
+//!
+//! Each subscription of the [`RpcTransport`] opens its own PubSub connection, so the number of
+//! open connections grows with the number of active subscriptions.
+//!
+//! # Dynamic client
+//!
+//! [`DynamicProgram`] builds instructions and decodes accounts, events and errors from an IDL
+//! loaded at runtime, e.g. with [`Program::fetch_idl`], for programs without generated bindings.
 
+use anchor_lang::error::{ErrorCode, FromErrorCode};
 use anchor_lang::solana_program::program_error::ProgramError;
 use anchor_lang::solana_program::pubkey::Pubkey;
 use anchor_lang::{AccountDeserialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_lang_idl::types::Idl;
 use futures::{Future, StreamExt};
 use regex::Regex;
 use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_address_lookup_table_interface::{
+    instruction as lookup_table_instruction, state::AddressLookupTable,
+};
 use solana_commitment_config::CommitmentConfig;
-use solana_instruction::{AccountMeta, Instruction};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_instruction::{error::InstructionError, AccountMeta, Instruction};
+use solana_message::{v0, CompileError, Message, VersionedMessage};
 use solana_program::hash::Hash;
-use solana_pubsub_client::nonblocking::pubsub_client::{PubsubClient, PubsubClientError};
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClientError;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use solana_rpc_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
 use solana_rpc_client_api::{
-    client_error::Error as SolanaClientError,
+    client_error::{Error as SolanaClientError, ErrorKind as SolanaClientErrorKind},
     config::{
         RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
-        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+        RpcSimulateTransactionConfig, RpcTransactionConfig, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
     },
     filter::{Memcmp, RpcFilterType},
-    response::{Response as RpcResponse, RpcLogsResponse},
+    request::{RpcError, RpcResponseErrorData},
+    response::{
+        Response as RpcResponse, RpcKeyedAccount, RpcLogsResponse, RpcPrioritizationFee,
+        RpcSimulateTransactionResult, TransactionError, UiReturnDataEncoding,
+        UiTransactionReturnData,
+    },
 };
+use solana_rpc_client_nonce_utils::Error as NonceError;
 use solana_signature::Signature;
 use solana_signer::{Signer, SignerError};
-use solana_transaction::Transaction;
+use solana_system_interface::instruction as system_instruction;
+use solana_transaction::{versioned::VersionedTransaction, Transaction};
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInnerInstructions, UiInstruction,
+    UiLoadedAddresses, UiTransactionEncoding,
+};
+use std::collections::{HashMap, HashSet};
 use std::iter::Map;
 use std::marker::PhantomData;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use std::vec::IntoIter;
 use thiserror::Error;
 use tokio::{
     runtime::Handle,
-    sync::{
-        mpsc::{unbounded_channel, UnboundedReceiver},
-        RwLock,
-    },
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
     task::JoinHandle,
 };
 
 pub use anchor_lang;
+pub use anchor_lang_idl;
 pub use cluster::Cluster;
+pub use dynamic::{idl_address, DynamicProgram};
 #[cfg(feature = "async")]
 pub use nonblocking::ThreadSafeSigner;
 pub use solana_account_decoder;
+pub use solana_message::AddressLookupTableAccount;
+pub use transport::{MockTransport, RpcTransport, Subscription, Transport, TransportFuture};
 
 mod cluster;
+mod dynamic;
+mod transport;
 
 #[cfg(not(feature = "async"))]
 mod blocking;
@@ -124,7 +163,26 @@ mod nonblocking;
 const PROGRAM_LOG: &str = "Program log: ";
 const PROGRAM_DATA: &str = "Program data: ";
 
-type UnsubscribeFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+/// Seed of the event authority PDA that signs the self-CPIs of `emit_cpi!`.
+const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// Maximum number of addresses to extend a lookup table with in a single transaction.
+const MAX_LOOKUP_TABLE_EXTEND_ADDRESSES: usize = 30;
+
+/// Maximum compute unit limit of a transaction.
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Maximum number of accounts to get the recent prioritization fees of.
+const MAX_PRIORITIZATION_FEE_ACCOUNTS: usize = 128;
+
+/// Maximum number of signatures returned by `getSignaturesForAddress`.
+const MAX_SIGNATURES_PER_PAGE: usize = 1000;
+
+/// Delay between two status checks of a transaction sent with a [`SendPolicy`].
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub type UnsubscribeFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+type PriorityFeeFn = Arc<dyn Fn(&[RpcPrioritizationFee]) -> u64 + Send + Sync>;
 /// Client defines the base configuration for building RPC clients to
 /// communicate with Anchor programs running on a Solana cluster. It's
 /// primary use is to build a `Program` client via the `program` method.
@@ -171,6 +229,22 @@ impl<C: Clone + Deref<Target = impl Signer>> Client<C> {
             rpc_client,
         )
     }
+
+    /// Create a program client that makes its requests through the given transport, e.g. a
+    /// [`MockTransport`] in tests.
+    pub fn program_with_transport(
+        &self,
+        program_id: Pubkey,
+        transport: Arc<dyn Transport>,
+    ) -> Result<Program<C>, ClientError> {
+        let cfg = Config {
+            cluster: self.cfg.cluster.clone(),
+            options: self.cfg.options,
+            payer: self.cfg.payer.clone(),
+        };
+
+        Program::new_with_transport(program_id, cfg, transport)
+    }
 }
 
 /// Auxiliary data structure to align the types of the Solana CLI utils with Anchor client.
@@ -218,7 +292,12 @@ pub struct EventUnsubscriber<'a> {
 
 impl EventUnsubscriber<'_> {
     async fn unsubscribe_internal(mut self) {
-        if let Some(unsubscribe) = self.rx.recv().await {
+        if let Some(mut unsubscribe) = self.rx.recv().await {
+            // Subscriptions with reconnection send the unsubscribe function of each connection
+            self.rx.close();
+            while let Ok(latest) = self.rx.try_recv() {
+                unsubscribe = latest;
+            }
             unsubscribe().await;
         }
 
@@ -230,10 +309,9 @@ impl EventUnsubscriber<'_> {
 pub struct Program<C> {
     program_id: Pubkey,
     cfg: Config<C>,
-    sub_client: Arc<RwLock<Option<PubsubClient>>>,
     #[cfg(not(feature = "async"))]
     rt: tokio::runtime::Runtime,
-    internal_rpc_client: AsyncRpcClient,
+    transport: Arc<dyn Transport>,
 }
 
 impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
@@ -245,9 +323,24 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         self.program_id
     }
 
+    /// Transport the requests of the program are made through.
+    pub fn transport(&self) -> &dyn Transport {
+        &*self.transport
+    }
+
+    /// RPC client the requests of the program are made with, if its transport has one.
+    pub fn rpc_client(&self) -> Option<&AsyncRpcClient> {
+        self.transport.rpc_client()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the program was created with a transport that doesn't have an RPC client.
     #[cfg(feature = "mock")]
+    #[deprecated(note = "Use `Program::rpc_client` instead")]
     pub fn internal_rpc(&self) -> &AsyncRpcClient {
-        &self.internal_rpc_client
+        self.rpc_client()
+            .expect("Program's transport doesn't have an RPC client")
     }
 
     async fn account_internal<T: AccountDeserialize>(
@@ -255,16 +348,111 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         address: Pubkey,
     ) -> Result<T, ClientError> {
         let account = self
-            .internal_rpc_client
-            .get_account_with_commitment(&address, CommitmentConfig::processed())
-            .await
-            .map_err(Box::new)?
-            .value
+            .transport
+            .get_account(&address, CommitmentConfig::processed())
+            .await?
             .ok_or(ClientError::AccountNotFound)?;
         let mut data: &[u8] = &account.data;
         T::try_deserialize(&mut data).map_err(Into::into)
     }
 
+    async fn fetch_idl_internal(&self) -> Result<Idl, ClientError> {
+        let account = self
+            .transport
+            .get_account(&idl_address(&self.program_id), self.transport.commitment())
+            .await?
+            .ok_or(ClientError::AccountNotFound)?;
+
+        dynamic::decode_idl_account(&account.data)
+    }
+
+    async fn dynamic_account_internal(
+        &self,
+        program: &DynamicProgram,
+        address: Pubkey,
+    ) -> Result<(String, serde_json::Value), ClientError> {
+        let account = self
+            .transport
+            .get_account(&address, self.transport.commitment())
+            .await?
+            .ok_or(ClientError::AccountNotFound)?;
+
+        program.decode_account(&account.data)
+    }
+
+    async fn dynamic_instruction_internal(
+        &self,
+        program: &DynamicProgram,
+        name: &str,
+        args: &serde_json::Value,
+        accounts: &serde_json::Value,
+    ) -> Result<Instruction, ClientError> {
+        // Fetch the accounts the resolution is missing, and resolve again until none is missing
+        let mut fetched = HashMap::new();
+        loop {
+            let mut missing = None;
+            let ix = program.instruction_with(name, args, accounts, |address| {
+                fetched.get(address).cloned().ok_or_else(|| {
+                    missing = Some(*address);
+                    ClientError::AccountNotFound
+                })
+            });
+            let Some(address) = missing else {
+                return ix;
+            };
+            let account = self
+                .transport
+                .get_account(&address, self.transport.commitment())
+                .await?
+                .ok_or(ClientError::AccountNotFound)?;
+            fetched.insert(address, account.data);
+        }
+    }
+
+    async fn multiple_accounts_data_internal(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Vec<u8>>>, ClientError> {
+        let requests = addresses.chunks(MAX_MULTIPLE_ACCOUNTS).map(|chunk| {
+            self.transport
+                .get_multiple_accounts(chunk, CommitmentConfig::processed())
+        });
+
+        Ok(futures::future::try_join_all(requests)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|account| account.map(|account| account.data))
+            .collect())
+    }
+
+    async fn accounts_multiple_internal<T: AccountDeserialize>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Result<T, ClientError>>>, ClientError> {
+        Ok(self
+            .multiple_accounts_data_internal(addresses)
+            .await?
+            .into_iter()
+            .map(|data| data.map(|data| T::try_deserialize(&mut &data[..]).map_err(Into::into)))
+            .collect())
+    }
+
+    async fn accounts_multiple_any_internal<A>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<Result<A, ClientError>>>, ClientError>
+    where
+        A: for<'a> TryFrom<&'a [u8], Error = anchor_lang::error::Error>,
+    {
+        Ok(self
+            .multiple_accounts_data_internal(addresses)
+            .await?
+            .into_iter()
+            .map(|data| data.map(|data| A::try_from(&data[..]).map_err(Into::into)))
+            .collect())
+    }
+
     async fn accounts_lazy_internal<T: AccountDeserialize + Discriminator>(
         &self,
         filters: Vec<RpcFilterType>,
@@ -282,35 +470,95 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
 
         Ok(ProgramAccountsIterator {
             inner: self
-                .internal_rpc_client
-                .get_program_ui_accounts_with_config(&self.id(), config)
-                .await
-                .map_err(Box::new)?
+                .transport
+                .get_program_accounts(&self.id(), config)
+                .await?
                 .into_iter()
-                .map(|(key, account)| {
-                    let data = account
-                        .data
-                        .decode()
-                        .expect("account was fetched with binary encoding");
-                    Ok((key, T::try_deserialize(&mut data.as_slice())?))
-                }),
+                .map(|(key, account)| Ok((key, decode_account(&account)?))),
         })
     }
 
-    async fn init_sub_client_if_needed(&self) -> Result<(), ClientError> {
-        let lock = &self.sub_client;
-        let mut client = lock.write().await;
+    async fn address_lookup_table_internal(
+        &self,
+        address: Pubkey,
+    ) -> Result<AddressLookupTableAccount, ClientError> {
+        let account = self
+            .transport
+            .get_account(&address, CommitmentConfig::processed())
+            .await?
+            .ok_or(ClientError::AccountNotFound)?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|e| ProgramError::try_from(e).unwrap_or(ProgramError::InvalidAccountData))?;
 
-        if client.is_none() {
-            let sub_client = PubsubClient::new(self.cfg.cluster.ws_url())
-                .await
-                .map_err(Box::new)?;
-            *client = Some(sub_client);
+        Ok(AddressLookupTableAccount {
+            key: address,
+            addresses: table.addresses.to_vec(),
+        })
+    }
+
+    async fn create_lookup_table_internal(
+        &self,
+        addresses: Vec<Pubkey>,
+    ) -> Result<AddressLookupTableAccount, ClientError> {
+        let payer = self.payer();
+        let recent_slot = self
+            .transport
+            .get_slot(CommitmentConfig::finalized())
+            .await?;
+        let (ix, key) = lookup_table_instruction::create_lookup_table(payer, payer, recent_slot);
+        self.send_payer_instructions(&[ix]).await?;
+        self.extend_lookup_table_internal(key, addresses.clone())
+            .await?;
+
+        Ok(AddressLookupTableAccount { key, addresses })
+    }
+
+    async fn extend_lookup_table_internal(
+        &self,
+        table: Pubkey,
+        addresses: Vec<Pubkey>,
+    ) -> Result<(), ClientError> {
+        let payer = self.payer();
+        for chunk in addresses.chunks(MAX_LOOKUP_TABLE_EXTEND_ADDRESSES) {
+            let ix = lookup_table_instruction::extend_lookup_table(
+                table,
+                payer,
+                Some(payer),
+                chunk.to_vec(),
+            );
+            self.send_payer_instructions(&[ix]).await?;
         }
 
         Ok(())
     }
 
+    async fn send_signed_transaction_internal(
+        &self,
+        tx: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        if !tx.is_signed() {
+            return Err(SignerError::NotEnoughSigners.into());
+        }
+
+        self.transport
+            .send_and_confirm_transaction(&tx.clone().into())
+            .await
+    }
+
+    /// Send instructions only signed by the payer.
+    async fn send_payer_instructions(
+        &self,
+        instructions: &[Instruction],
+    ) -> Result<Signature, ClientError> {
+        let latest_hash = self.transport.get_latest_blockhash().await?;
+        let mut tx = Transaction::new_with_payer(instructions, Some(&self.payer()));
+        tx.try_sign(&[&*self.cfg.payer], latest_hash)?;
+
+        self.transport
+            .send_and_confirm_transaction(&tx.into())
+            .await
+    }
+
     async fn on_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
         &self,
         mut f: impl FnMut(&EventContext, T) + Send + 'static,
@@ -321,7 +569,6 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         ),
         ClientError,
     > {
-        self.init_sub_client_if_needed().await?;
         let (tx, rx) = unbounded_channel::<_>();
         let config = RpcTransactionLogsConfig {
             commitment: self.cfg.options,
@@ -329,34 +576,323 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         let program_id_str = self.program_id.to_string();
         let filter = RpcTransactionLogsFilter::Mentions(vec![program_id_str.clone()]);
 
-        let lock = Arc::clone(&self.sub_client);
+        let transport = Arc::clone(&self.transport);
 
         let handle = tokio::spawn(async move {
-            if let Some(ref client) = *lock.read().await {
-                let (mut notifications, unsubscribe) = client
-                    .logs_subscribe(filter, config)
-                    .await
-                    .map_err(Box::new)?;
+            let (mut notifications, unsubscribe) = transport.logs_subscribe(filter, config).await?;
+
+            tx.send(unsubscribe).map_err(unsubscribe_failed)?;
+
+            while let Some(logs) = notifications.next().await {
+                let ctx = EventContext {
+                    signature: logs.value.signature.parse().unwrap(),
+                    slot: logs.context.slot,
+                };
+                let events = parse_logs_response(logs, &program_id_str)?;
+                for e in events {
+                    f(&ctx, e);
+                }
+            }
+            Ok::<(), ClientError>(())
+        });
+
+        Ok((handle, rx))
+    }
+
+    async fn cpi_events_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        signature: &Signature,
+    ) -> Result<Vec<T>, ClientError> {
+        let commitment = self.cfg.options.unwrap_or_default();
+        let tx = get_transaction(&*self.transport, signature, commitment).await?;
+        parse_cpi_events(&tx, &self.program_id)
+    }
+
+    async fn replay_events_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        config: ReplayConfig,
+    ) -> Result<ReplayedEvents<T>, ClientError> {
+        let commitment = confirmed_commitment(self.cfg.options.unwrap_or_default());
+
+        let history =
+            transaction_history(&*self.transport, &self.program_id, commitment, &config).await?;
+
+        let mut events = vec![];
+        for (signature, slot) in &history.signatures {
+            let tx = get_transaction(&*self.transport, signature, commitment).await?;
+            let ctx = EventContext {
+                signature: *signature,
+                slot: *slot,
+            };
+            for event in parse_transaction_events(&tx, &self.program_id)? {
+                events.push((ctx.clone(), event));
+            }
+        }
+
+        Ok(ReplayedEvents {
+            events,
+            cursor: history.cursor,
+            is_complete: history.is_complete,
+        })
+    }
+
+    async fn subscribe_account_internal<T: AccountDeserialize>(
+        &self,
+        address: Pubkey,
+        mut f: impl FnMut(&AccountContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        let (tx, rx) = unbounded_channel::<_>();
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: self.cfg.options,
+            ..RpcAccountInfoConfig::default()
+        };
+
+        let transport = Arc::clone(&self.transport);
+
+        let handle = tokio::spawn(async move {
+            let (mut notifications, unsubscribe) =
+                transport.account_subscribe(address, config).await?;
+
+            tx.send(unsubscribe).map_err(unsubscribe_failed)?;
 
-                tx.send(unsubscribe).map_err(|e| {
-                    ClientError::SolanaClientPubsubError(Box::new(
-                        PubsubClientError::RequestFailed {
-                            message: "Unsubscribe failed".to_string(),
-                            reason: e.to_string(),
-                        },
-                    ))
-                })?;
+            while let Some(account) = notifications.next().await {
+                let ctx = AccountContext {
+                    pubkey: address,
+                    slot: account.context.slot,
+                };
+                f(&ctx, decode_account(&account.value));
+            }
+            Ok::<(), ClientError>(())
+        });
+
+        Ok((handle, rx))
+    }
+
+    async fn subscribe_program_accounts_internal<T: AccountDeserialize + Discriminator>(
+        &self,
+        filters: Vec<RpcFilterType>,
+        mut f: impl FnMut(&AccountContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        let (tx, rx) = unbounded_channel::<_>();
+        let account_type_filter =
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, T::DISCRIMINATOR));
+        let config = RpcProgramAccountsConfig {
+            filters: Some([vec![account_type_filter], filters].concat()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: self.cfg.options,
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let program_id = self.program_id;
+
+        let transport = Arc::clone(&self.transport);
+
+        let handle = tokio::spawn(async move {
+            let (mut notifications, unsubscribe) =
+                transport.program_subscribe(program_id, config).await?;
+
+            tx.send(unsubscribe).map_err(unsubscribe_failed)?;
+
+            while let Some(keyed_account) = notifications.next().await {
+                let (pubkey, account) = decode_keyed_account(&keyed_account.value);
+                let ctx = AccountContext {
+                    pubkey,
+                    slot: keyed_account.context.slot,
+                };
+                f(&ctx, account);
+            }
+            Ok::<(), ClientError>(())
+        });
+
+        Ok((handle, rx))
+    }
+
+    async fn on_with_reconnect_internal<
+        T: anchor_lang::Event + anchor_lang::AnchorDeserialize + Send,
+    >(
+        &self,
+        config: ReconnectConfig,
+        mut f: impl FnMut(EventNotification<T>) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        let (tx, rx) = unbounded_channel::<_>();
+        let commitment = self.cfg.options.unwrap_or_default();
+        let program_id = self.program_id;
+        let transport = Arc::clone(&self.transport);
+
+        let handle = tokio::spawn(async move {
+            let program_id_str = program_id.to_string();
+            let mut last_seen: Option<EventContext> = None;
+            let mut backfilled: HashSet<Signature> = HashSet::new();
+            let mut is_reconnection = false;
+            let mut attempt = 0;
+
+            // The receiver is closed on unsubscribe
+            while !tx.is_closed() {
+                let filter = RpcTransactionLogsFilter::Mentions(vec![program_id_str.clone()]);
+                let logs_config = RpcTransactionLogsConfig {
+                    commitment: Some(commitment),
+                };
+                let (mut notifications, unsubscribe) =
+                    match transport.logs_subscribe(filter, logs_config).await {
+                        Ok(subscription) => subscription,
+                        Err(e) => {
+                            if !wait_to_reconnect(&config, attempt).await {
+                                return Err(e);
+                            }
+                            attempt += 1;
+                            continue;
+                        }
+                    };
+
+                if let Err(unsubscribe) = tx.send(unsubscribe) {
+                    unsubscribe.0().await;
+                    break;
+                }
+
+                if is_reconnection {
+                    let backfill = match &last_seen {
+                        Some(last_seen) if config.backfill => Some(
+                            backfill_events::<T>(
+                                &*transport,
+                                &program_id,
+                                commitment,
+                                last_seen.signature,
+                            )
+                            .await,
+                        ),
+                        _ => None,
+                    };
+                    let (backfill, backfill_error) = match backfill {
+                        Some(Ok(backfill)) => (Some(backfill), None),
+                        Some(Err(e)) => (None, Some(e)),
+                        None => (None, None),
+                    };
+                    f(EventNotification::Gap {
+                        last_seen: last_seen.clone(),
+                        backfilled: backfill.is_some(),
+                        backfill_error,
+                    });
+
+                    // Skip the transactions that are both backfilled and notified live
+                    backfilled.clear();
+                    for (ctx, events) in backfill.into_iter().flatten() {
+                        backfilled.insert(ctx.signature);
+                        for e in events {
+                            f(EventNotification::Event(ctx.clone(), e));
+                        }
+                        last_seen = Some(ctx);
+                    }
+                }
+                is_reconnection = true;
 
                 while let Some(logs) = notifications.next().await {
+                    // Only a connection that delivers notifications resets the backoff, so that an
+                    // endpoint dropping every connection isn't resubscribed to in a tight loop
+                    attempt = 0;
                     let ctx = EventContext {
                         signature: logs.value.signature.parse().unwrap(),
                         slot: logs.context.slot,
                     };
-                    let events = parse_logs_response(logs, &program_id_str)?;
-                    for e in events {
-                        f(&ctx, e);
+                    if backfilled.contains(&ctx.signature) {
+                        continue;
+                    }
+
+                    last_seen = Some(ctx.clone());
+                    // Logs that can't be decoded don't end the subscription
+                    match parse_logs_response(logs, &program_id_str) {
+                        Ok(events) => {
+                            for e in events {
+                                f(EventNotification::Event(ctx.clone(), e));
+                            }
+                        }
+                        Err(e) => f(EventNotification::Error(ctx, e)),
                     }
                 }
+
+                // The notifications also end on unsubscribe
+                if tx.is_closed() {
+                    break;
+                }
+                if !wait_to_reconnect(&config, attempt).await {
+                    return Err(ClientError::SolanaClientPubsubError(Box::new(
+                        PubsubClientError::ConnectionClosed(
+                            "Maximum reconnection attempts reached".to_string(),
+                        ),
+                    )));
+                }
+                attempt += 1;
+            }
+            Ok::<(), ClientError>(())
+        });
+
+        Ok((handle, rx))
+    }
+
+    async fn on_cpi_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        mut f: impl FnMut(&EventContext, Result<T, ClientError>) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        let (tx, rx) = unbounded_channel::<_>();
+        // Subscribe with the commitment the transactions are fetched with
+        let commitment = confirmed_commitment(self.cfg.options.unwrap_or_default());
+        let config = RpcTransactionLogsConfig {
+            commitment: Some(commitment),
+        };
+        let program_id = self.program_id;
+        let filter = RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]);
+
+        let transport = Arc::clone(&self.transport);
+
+        let handle = tokio::spawn(async move {
+            let (mut notifications, unsubscribe) = transport.logs_subscribe(filter, config).await?;
+
+            tx.send(unsubscribe).map_err(unsubscribe_failed)?;
+
+            while let Some(logs) = notifications.next().await {
+                // Events of failed transactions are reverted
+                if logs.value.err.is_some() {
+                    continue;
+                }
+
+                let ctx = EventContext {
+                    signature: logs.value.signature.parse().unwrap(),
+                    slot: logs.context.slot,
+                };
+                // A transaction that can't be fetched or parsed doesn't end the subscription
+                let events = get_notified_transaction(&*transport, &ctx.signature, commitment)
+                    .await
+                    .and_then(|transaction| parse_cpi_events(&transaction, &program_id));
+                match events {
+                    Ok(events) => events.into_iter().for_each(|e| f(&ctx, Ok(e))),
+                    Err(e) => f(&ctx, Err(e)),
+                }
             }
             Ok::<(), ClientError>(())
         });
@@ -371,6 +907,23 @@ pub struct ProgramAccountsIterator<T> {
     inner: Map<IntoIter<(Pubkey, UiAccount)>, AccountConverterFunction<T>>,
 }
 
+/// Convert the filters built with the filter builder of an `#[account(filter)]` to RPC filters,
+/// e.g. to fetch accounts with `Program::accounts`.
+///
+/// ```ignore
+/// let accounts = program.accounts::<MyAccount>(rpc_filters(
+///     MyAccount::filter().authority(authority).build(),
+/// ))?;
+/// ```
+pub fn rpc_filters(filters: Vec<anchor_lang::filter::Memcmp>) -> Vec<RpcFilterType> {
+    filters
+        .into_iter()
+        .map(|filter| {
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(filter.offset, &filter.bytes))
+        })
+        .collect()
+}
+
 /// Function type that accepts solana accounts and returns deserialized anchor accounts
 type AccountConverterFunction<T> = fn((Pubkey, UiAccount)) -> Result<(Pubkey, T), ClientError>;
 
@@ -477,43 +1030,471 @@ impl Execution {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EventContext {
     pub signature: Signature,
     pub slot: u64,
 }
 
-#[derive(Debug, Error)]
-pub enum ClientError {
-    #[error("Account not found")]
-    AccountNotFound,
-    #[error("{0}")]
-    AnchorError(#[from] anchor_lang::error::Error),
-    #[error("{0}")]
-    ProgramError(#[from] ProgramError),
-    #[error("{0}")]
-    SolanaClientError(#[from] Box<SolanaClientError>),
-    #[error("{0}")]
-    SolanaClientPubsubError(#[from] Box<PubsubClientError>),
-    #[error("Unable to parse log: {0}")]
-    LogParseError(String),
-    #[error(transparent)]
-    IOError(#[from] std::io::Error),
-    #[error("{0}")]
-    SignerError(#[from] SignerError),
+#[derive(Debug, Clone)]
+pub struct AccountContext {
+    pub pubkey: Pubkey,
+    pub slot: u64,
 }
 
-pub trait AsSigner {
-    fn as_signer(&self) -> &dyn Signer;
+/// Transactions to replay the events of, see `Program::replay_events`.
+///
+/// Signature bounds are exclusive and slot bounds inclusive.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplayConfig {
+    /// Replay the transactions after this one
+    pub after: Option<Signature>,
+    /// Replay the transactions before this one
+    pub before: Option<Signature>,
+    /// Replay the transactions from this slot
+    pub min_slot: Option<u64>,
+    /// Replay the transactions up to this slot
+    pub max_slot: Option<u64>,
+    /// Maximum number of transactions to replay, the newest ones within the bounds
+    pub limit: Option<usize>,
 }
 
-impl AsSigner for Box<dyn Signer + '_> {
-    fn as_signer(&self) -> &dyn Signer {
-        self.as_ref()
-    }
+/// Events replayed from the history of a program, see `Program::replay_events`.
+#[derive(Debug)]
+pub struct ReplayedEvents<T> {
+    /// Events in the order of their transactions, oldest first
+    pub events: Vec<(EventContext, T)>,
+    /// Oldest transaction scanned, including the ones without events, to replay the older
+    /// transactions with [`ReplayConfig::before`]
+    pub cursor: Option<Signature>,
+    /// Whether all transactions within the bounds were replayed, i.e. `limit` wasn't reached
+    pub is_complete: bool,
 }
 
-/// `RequestBuilder` provides a builder interface to create and send
+/// Reconnection policy of a subscription, see `Program::on_with_reconnect`.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnection attempt, doubled after each failed attempt
+    pub initial_backoff: Duration,
+    /// Maximum delay between reconnection attempts
+    pub max_backoff: Duration,
+    /// Maximum number of consecutive failed attempts before giving up, unlimited if `None`.
+    ///
+    /// A connection that ends before receiving any notification counts as a failed attempt.
+    pub max_attempts: Option<u32>,
+    /// Whether to fetch the events missed while disconnected over RPC.
+    ///
+    /// The missed transactions are fetched, so their events emitted with `emit_cpi!` are
+    /// notified along with the ones emitted with `emit!`.
+    pub backfill: bool,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            backfill: false,
+        }
+    }
+}
+
+/// Notification of a subscription with reconnection, see `Program::on_with_reconnect`.
+#[derive(Debug)]
+pub enum EventNotification<T> {
+    /// Event emitted by the program
+    Event(EventContext, T),
+    /// The events of the transaction couldn't be decoded from its logs
+    Error(EventContext, ClientError),
+    /// The subscription was reconnected after losing its connection.
+    ///
+    /// Events of the transactions after `last_seen` were missed, unless they were `backfilled`,
+    /// in which case they are notified right after this.
+    Gap {
+        /// Last transaction seen before the connection was lost, if any
+        last_seen: Option<EventContext>,
+        backfilled: bool,
+        /// Error the backfill failed with, if any
+        backfill_error: Option<ClientError>,
+    },
+}
+
+/// Policy of [`RequestBuilder::send_with_policy`] to send and confirm a transaction with.
+#[derive(Debug, Clone)]
+pub struct SendPolicy {
+    /// Commitment to wait for, the commitment of the client if `None`
+    pub commitment: Option<CommitmentConfig>,
+    /// Whether to skip the preflight simulation of the RPC node
+    pub skip_preflight: bool,
+    /// Delay between two sends of the transaction while it's unconfirmed
+    pub rebroadcast_interval: Duration,
+    /// Maximum number of times to resend the transaction with the same blockhash.
+    ///
+    /// Once the retries run out, the transaction is no longer resent, but its status is still
+    /// polled until its blockhash expires.
+    pub max_retries: u32,
+    /// Number of times to re-sign the transaction with a new blockhash once its blockhash expired.
+    ///
+    /// Transactions with a durable nonce are never re-signed.
+    pub blockhash_refreshes: u32,
+}
+
+impl Default for SendPolicy {
+    fn default() -> Self {
+        Self {
+            commitment: None,
+            skip_preflight: false,
+            rebroadcast_interval: Duration::from_secs(2),
+            max_retries: 30,
+            blockhash_refreshes: 0,
+        }
+    }
+}
+
+/// Outcome of a transaction sent with [`RequestBuilder::send_with_policy`].
+#[derive(Debug)]
+pub enum SendOutcome {
+    /// The transaction was confirmed with the commitment of the policy
+    Confirmed(Signature),
+    /// The transaction failed, either in the preflight simulation or on chain
+    Failed(TransactionFailure),
+    /// The transaction wasn't confirmed before its blockhash expired, so it can't be confirmed
+    /// anymore. Holds the signature of the last transaction sent.
+    Expired(Signature),
+    /// The transaction with a durable nonce wasn't confirmed before the retries of the policy ran
+    /// out. It may still be confirmed until its nonce is advanced.
+    RetriesExhausted(Signature),
+}
+
+/// Failed transaction, see [`SendOutcome::Failed`].
+#[derive(Debug)]
+pub struct TransactionFailure {
+    pub signature: Signature,
+    pub error: TransactionError,
+    /// Logs of the transaction, empty if they couldn't be fetched
+    pub logs: Vec<String>,
+    /// Anchor error logged by the failing program, if any
+    pub anchor_error: Option<AnchorErrorLog>,
+}
+
+impl TransactionFailure {
+    fn new(signature: Signature, error: TransactionError, logs: Vec<String>) -> Self {
+        let anchor_error = parse_anchor_error(&logs);
+        Self {
+            signature,
+            error,
+            logs,
+            anchor_error,
+        }
+    }
+
+    /// Decode the error of the failed instruction, see [`decode_error`].
+    pub fn decode_error<E: FromErrorCode>(&self) -> Option<DecodedError<E>> {
+        decode_error(&self.error)
+    }
+}
+
+/// Error logged by an Anchor program, see [`parse_anchor_error`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorErrorLog {
+    /// Name of the error code, e.g. `ConstraintMut`
+    pub name: String,
+    pub number: u32,
+    pub message: String,
+    pub origin: Option<AnchorErrorOrigin>,
+}
+
+/// Where an [`AnchorErrorLog`] was thrown from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnchorErrorOrigin {
+    /// Source file and line of the program
+    Source { file: String, line: u32 },
+    /// Name of the account that failed its constraints
+    Account(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Account not found")]
+    AccountNotFound,
+    #[error("{0}")]
+    AnchorError(#[from] anchor_lang::error::Error),
+    #[error("{0}")]
+    ProgramError(#[from] ProgramError),
+    #[error("{0}")]
+    SolanaClientError(#[from] Box<SolanaClientError>),
+    #[error("{0}")]
+    SolanaClientPubsubError(#[from] Box<PubsubClientError>),
+    #[error("Unable to parse log: {0}")]
+    LogParseError(String),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error("{0}")]
+    SignerError(#[from] SignerError),
+    #[error("{0}")]
+    CompileError(#[from] CompileError),
+    #[error("Return data was set by {0} instead of the request's program")]
+    ReturnDataProgramMismatch(Pubkey),
+    #[error("The transaction didn't set any return data")]
+    NoReturnData,
+    #[error("Simulation failed: {0}")]
+    SimulationError(TransactionError),
+    #[error("Unable to parse transaction: {0}")]
+    TransactionParseError(String),
+    #[error("Unable to parse account: {0}")]
+    AccountParseError(String),
+    #[error("Invalid durable nonce: {0}")]
+    NonceError(#[from] NonceError),
+    #[error("IDL error: {0}")]
+    IdlError(String),
+    #[error("Address lookup tables can only be used in versioned transactions")]
+    VersionedTransactionRequired,
+}
+
+impl ClientError {
+    /// Error of the transaction that failed the preflight simulation, or the simulation of the
+    /// request's compute budget.
+    pub fn transaction_error(&self) -> Option<TransactionError> {
+        match self {
+            ClientError::SolanaClientError(e) => e.get_transaction_error(),
+            ClientError::SimulationError(e) => Some(e.clone()),
+            _ => None,
+        }
+    }
+
+    /// Decode the error of the failed instruction, see [`decode_error`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// match program.request().args(instruction::Withdraw { amount }).send() {
+    ///     Err(e) => match e.decode_error::<MyError>() {
+    ///         Some(DecodedError::Program(MyError::InsufficientFunds)) => { /* ... */ }
+    ///         Some(DecodedError::Anchor(ErrorCode::ConstraintHasOne)) => { /* ... */ }
+    ///         _ => return Err(e),
+    ///     },
+    ///     Ok(signature) => { /* ... */ }
+    /// }
+    /// ```
+    pub fn decode_error<E: FromErrorCode>(&self) -> Option<DecodedError<E>> {
+        decode_error(&self.transaction_error()?)
+    }
+
+    /// Anchor error logged by the program of the transaction that failed the preflight
+    /// simulation, which includes where the error was thrown from.
+    pub fn anchor_error_log(&self) -> Option<AnchorErrorLog> {
+        let (_, logs) = preflight_failure(self)?;
+        parse_anchor_error(&logs)
+    }
+}
+
+/// Custom error of a failed instruction, decoded with [`decode_error`].
+#[derive(Debug, Clone, Copy)]
+pub enum DecodedError<E> {
+    /// Error of the program's `#[error_code]` enum
+    Program(E),
+    /// Error of the Anchor framework
+    Anchor(ErrorCode),
+}
+
+/// Decode the custom error of a failed instruction into the `#[error_code]` enum `E` of its
+/// program, or into an Anchor framework [`ErrorCode`].
+///
+/// The error number doesn't tell which program failed, so the custom errors of non-Anchor
+/// programs may decode into `E` if their numbers overlap.
+pub fn decode_error<E: FromErrorCode>(error: &TransactionError) -> Option<DecodedError<E>> {
+    let TransactionError::InstructionError(_, InstructionError::Custom(code)) = error else {
+        return None;
+    };
+    E::from_error_code(*code)
+        .map(DecodedError::Program)
+        .or_else(|| ErrorCode::from_error_code(*code).map(DecodedError::Anchor))
+}
+
+/// Strategy to set the compute unit price of a request with, in micro-lamports.
+#[derive(Clone)]
+pub enum PriorityFee {
+    /// Fixed price
+    Fixed(u64),
+    /// Percentile (0-100) of the prioritization fees paid in recent slots for the writable
+    /// accounts of the request
+    RecentPercentile(u8),
+    /// Price computed from the prioritization fees paid in recent slots for the writable accounts
+    /// of the request
+    Callback(PriorityFeeFn),
+}
+
+/// Compute budget of a request, see [`RequestBuilder::compute_budget`].
+#[derive(Clone)]
+pub struct ComputeBudget {
+    margin: u64,
+    priority_fee: Option<PriorityFee>,
+}
+
+impl Default for ComputeBudget {
+    fn default() -> Self {
+        Self {
+            margin: 10,
+            priority_fee: None,
+        }
+    }
+}
+
+impl ComputeBudget {
+    /// Set the safety margin added to the simulated compute units, in percent (defaults to 10).
+    #[must_use]
+    pub fn margin(mut self, percent: u64) -> Self {
+        self.margin = percent;
+        self
+    }
+
+    /// Set the compute unit price with the given strategy (unset by default).
+    #[must_use]
+    pub fn priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// Compute unit limit for the given simulated compute units, with the safety margin on top.
+    fn unit_limit(&self, units_consumed: u64) -> u32 {
+        let units = units_consumed.saturating_mul(100 + self.margin) / 100;
+        units.min(MAX_COMPUTE_UNIT_LIMIT.into()) as u32
+    }
+}
+
+/// Durable nonce to send a request with instead of a recent blockhash.
+#[derive(Debug, Clone, Copy)]
+struct DurableNonce {
+    account: Pubkey,
+    authority: Pubkey,
+    /// Value of the nonce, fetched when the transaction is built if unset
+    value: Option<Hash>,
+}
+
+/// Add signatures made offline to a partially signed transaction, e.g. one built with
+/// `RequestBuilder::partially_signed_transaction`.
+///
+/// Errors if a signature isn't the one of a signer of the transaction over its message.
+pub fn add_signatures(
+    tx: &mut Transaction,
+    signatures: &[(Pubkey, Signature)],
+) -> Result<(), ClientError> {
+    let message_data = tx.message_data();
+    let signers =
+        &tx.message.account_keys[..usize::from(tx.message.header.num_required_signatures)];
+
+    for (pubkey, signature) in signatures {
+        let index = signers
+            .iter()
+            .position(|signer| signer == pubkey)
+            .ok_or(SignerError::KeypairPubkeyMismatch)?;
+        if !signature.verify(pubkey.as_ref(), &message_data) {
+            return Err(SignerError::InvalidInput(format!("Invalid signature of {pubkey}")).into());
+        }
+        tx.signatures[index] = *signature;
+    }
+
+    Ok(())
+}
+
+/// `percentile` of the given prioritization fees, or zero if there are none.
+fn fee_percentile(fees: &[RpcPrioritizationFee], percentile: u8) -> u64 {
+    let mut fees: Vec<u64> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+    fees.sort_unstable();
+    let index = fees.len().saturating_sub(1) * usize::from(percentile.min(100)) / 100;
+    fees.get(index).copied().unwrap_or_default()
+}
+
+/// Result of simulating a request.
+#[derive(Debug)]
+pub struct Simulation {
+    /// Error the transaction failed with, if any
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Return data, along with the program that set it
+    pub return_data: Option<(Pubkey, Vec<u8>)>,
+    program_id: Pubkey,
+}
+
+impl Simulation {
+    fn new(result: RpcSimulateTransactionResult, program_id: Pubkey) -> Result<Self, ClientError> {
+        Ok(Self {
+            err: result.err.map(Into::into),
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+            return_data: result.return_data.map(parse_return_data).transpose()?,
+            program_id,
+        })
+    }
+
+    /// Decode the value returned by the request's program, or `None` if nothing was returned.
+    pub fn return_value<T: anchor_lang::AnchorDeserialize>(
+        &self,
+    ) -> Result<Option<T>, ClientError> {
+        self.return_data
+            .as_ref()
+            .map(|(program_id, data)| decode_return_data(&self.program_id, program_id, data))
+            .transpose()
+    }
+
+    /// Parse the events of type `T` emitted by the request's program.
+    pub fn events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+    ) -> Result<Vec<T>, ClientError> {
+        parse_logs(&self.logs, &self.program_id.to_string())
+    }
+}
+
+fn parse_return_data(
+    return_data: UiTransactionReturnData,
+) -> Result<(Pubkey, Vec<u8>), ClientError> {
+    use anchor_lang::__private::base64;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    let program_id = return_data
+        .program_id
+        .parse()
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let (data, encoding) = return_data.data;
+    let data = match encoding {
+        UiReturnDataEncoding::Base64 => STANDARD
+            .decode(data)
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    };
+
+    Ok((program_id, data))
+}
+
+/// Decode return data set by `return_program_id`, expecting it to be set by `program_id`.
+fn decode_return_data<T: anchor_lang::AnchorDeserialize>(
+    program_id: &Pubkey,
+    return_program_id: &Pubkey,
+    data: &[u8],
+) -> Result<T, ClientError> {
+    if return_program_id != program_id {
+        return Err(ClientError::ReturnDataProgramMismatch(*return_program_id));
+    }
+
+    // Trailing zeros of the return data are trimmed by the runtime
+    let mut reader = std::io::Read::chain(data, std::io::repeat(0));
+    T::deserialize_reader(&mut reader)
+        .map_err(|e| ClientError::AnchorError(anchor_lang::error::Error::from(e)))
+}
+
+pub trait AsSigner {
+    fn as_signer(&self) -> &dyn Signer;
+}
+
+impl AsSigner for Box<dyn Signer + '_> {
+    fn as_signer(&self) -> &dyn Signer {
+        self.as_ref()
+    }
+}
+
+/// `RequestBuilder` provides a builder interface to create and send
 /// transactions to a cluster.
 pub struct RequestBuilder<'a, C, S: 'a> {
     cluster: String,
@@ -524,9 +1505,12 @@ pub struct RequestBuilder<'a, C, S: 'a> {
     payer: C,
     instruction_data: Option<Vec<u8>>,
     signers: Vec<S>,
+    address_lookup_tables: Vec<AddressLookupTableAccount>,
+    compute_budget: Option<ComputeBudget>,
+    durable_nonce: Option<DurableNonce>,
     #[cfg(not(feature = "async"))]
     handle: &'a Handle,
-    internal_rpc_client: &'a AsyncRpcClient,
+    transport: &'a dyn Transport,
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -593,110 +1577,1055 @@ impl<C: Deref<Target = impl Signer> + Clone, S: AsSigner> RequestBuilder<'_, C,
         self
     }
 
-    #[must_use]
-    pub fn options(mut self, options: CommitmentConfig) -> Self {
-        self.options = options;
-        self
+    #[must_use]
+    pub fn options(mut self, options: CommitmentConfig) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[must_use]
+    pub fn args(mut self, args: impl InstructionData) -> Self {
+        self.instruction_data = Some(args.data());
+        self
+    }
+
+    /// Add an address lookup table to load accounts from.
+    ///
+    /// Requests with lookup tables are sent as versioned transactions with a v0 message.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let request = program
+    ///     .request()
+    ///     .accounts(accounts::Settle { .. })
+    ///     .args(instruction::Settle {});
+    ///
+    /// // Create a lookup table from the accounts of the request, once
+    /// let table = program.create_lookup_table(request.lookup_table_addresses())?;
+    ///
+    /// request.address_lookup_table(table).send()?;
+    /// ```
+    #[must_use]
+    pub fn address_lookup_table(mut self, table: AddressLookupTableAccount) -> Self {
+        self.address_lookup_tables.push(table);
+        self
+    }
+
+    /// Set the compute unit limit and price of the request automatically when it's signed.
+    ///
+    /// The limit is estimated by simulating the request, with the budget's safety margin on top,
+    /// and the price is set with the budget's [`PriorityFee`] strategy. Compute budget
+    /// instructions added to the request manually take precedence.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// program
+    ///     .request()
+    ///     .accounts(accounts::Initialize { .. })
+    ///     .args(instruction::Initialize {})
+    ///     .compute_budget(
+    ///         ComputeBudget::default()
+    ///             .margin(20)
+    ///             .priority_fee(PriorityFee::RecentPercentile(75)),
+    ///     )
+    ///     .send()?;
+    /// ```
+    #[must_use]
+    pub fn compute_budget(mut self, budget: ComputeBudget) -> Self {
+        self.compute_budget = Some(budget);
+        self
+    }
+
+    /// Send the request with the durable nonce of `nonce_account` instead of a recent blockhash.
+    ///
+    /// An `AdvanceNonceAccount` instruction, which `nonce_authority` must sign, is prepended to the
+    /// request, and the nonce is fetched when the transaction is built. To build the transaction
+    /// offline, use [`Self::durable_nonce_with_value`] instead.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Online, or with the nonce fetched beforehand
+    /// let tx = program
+    ///     .request()
+    ///     .accounts(accounts::Withdraw { .. })
+    ///     .args(instruction::Withdraw { amount })
+    ///     .durable_nonce_with_value(nonce_account, custodian, nonce)
+    ///     .partially_signed_transaction()?;
+    ///
+    /// // Offline
+    /// let signature = custodian_keypair.sign_message(&tx.message_data());
+    ///
+    /// // Online, whenever
+    /// let mut tx = tx;
+    /// add_signatures(&mut tx, &[(custodian, signature)])?;
+    /// program.send_signed_transaction(&tx)?;
+    /// ```
+    #[must_use]
+    pub fn durable_nonce(mut self, nonce_account: Pubkey, nonce_authority: Pubkey) -> Self {
+        self.durable_nonce = Some(DurableNonce {
+            account: nonce_account,
+            authority: nonce_authority,
+            value: None,
+        });
+        self
+    }
+
+    /// Send the request with the given value of the durable nonce of `nonce_account`, so that the
+    /// nonce isn't fetched.
+    ///
+    /// See [`Self::durable_nonce`].
+    #[must_use]
+    pub fn durable_nonce_with_value(
+        mut self,
+        nonce_account: Pubkey,
+        nonce_authority: Pubkey,
+        nonce: Hash,
+    ) -> Self {
+        self.durable_nonce = Some(DurableNonce {
+            account: nonce_account,
+            authority: nonce_authority,
+            value: Some(nonce),
+        });
+        self
+    }
+
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = self.instructions.clone();
+        if let Some(ix_data) = &self.instruction_data {
+            instructions.push(Instruction {
+                program_id: self.program_id,
+                data: ix_data.clone(),
+                accounts: self.accounts.clone(),
+            });
+        }
+
+        instructions
+    }
+
+    /// Accounts of the request that can be loaded from an address lookup table.
+    ///
+    /// Signers and invoked programs must be in the transaction itself, so they are excluded.
+    pub fn lookup_table_addresses(&self) -> Vec<Pubkey> {
+        let instructions = self.instructions();
+        let mut addresses: Vec<Pubkey> = vec![];
+        for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+            let is_program = instructions.iter().any(|ix| ix.program_id == meta.pubkey);
+            if !meta.is_signer
+                && !is_program
+                && meta.pubkey != self.payer.pubkey()
+                && !addresses.contains(&meta.pubkey)
+            {
+                addresses.push(meta.pubkey);
+            }
+        }
+
+        addresses
+    }
+
+    fn signed_transaction_with_blockhash(
+        &self,
+        instructions: &[Instruction],
+        latest_hash: Hash,
+    ) -> Result<Transaction, ClientError> {
+        let mut signers: Vec<&dyn Signer> = self.signers.iter().map(|s| s.as_signer()).collect();
+        signers.push(&*self.payer);
+
+        let mut tx = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        tx.try_sign(&unique_signers(signers), latest_hash)?;
+
+        Ok(tx)
+    }
+
+    pub fn transaction(&self) -> Transaction {
+        let instructions = &self.instructions();
+        Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()))
+    }
+
+    /// Compile the request into a v0 message using the request's address lookup tables.
+    pub fn message_v0(&self, recent_blockhash: Hash) -> Result<v0::Message, ClientError> {
+        self.compile_message_v0(&self.instructions(), recent_blockhash)
+    }
+
+    fn compile_message_v0(
+        &self,
+        instructions: &[Instruction],
+        recent_blockhash: Hash,
+    ) -> Result<v0::Message, ClientError> {
+        v0::Message::try_compile(
+            &self.payer.pubkey(),
+            instructions,
+            &self.address_lookup_tables,
+            recent_blockhash,
+        )
+        .map_err(Into::into)
+    }
+
+    /// Sign the instructions into a legacy transaction, or a versioned one if the request uses
+    /// address lookup tables.
+    fn transaction_with_blockhash(
+        &self,
+        instructions: &[Instruction],
+        latest_hash: Hash,
+    ) -> Result<VersionedTransaction, ClientError> {
+        if self.address_lookup_tables.is_empty() {
+            Ok(self
+                .signed_transaction_with_blockhash(instructions, latest_hash)?
+                .into())
+        } else {
+            self.signed_versioned_transaction_with_blockhash(instructions, latest_hash)
+        }
+    }
+
+    fn unsigned_transaction_with_blockhash(
+        &self,
+        instructions: &[Instruction],
+        latest_hash: Hash,
+    ) -> Result<VersionedTransaction, ClientError> {
+        let message = if self.address_lookup_tables.is_empty() {
+            VersionedMessage::Legacy(Message::new_with_blockhash(
+                instructions,
+                Some(&self.payer.pubkey()),
+                &latest_hash,
+            ))
+        } else {
+            VersionedMessage::V0(self.compile_message_v0(instructions, latest_hash)?)
+        };
+
+        Ok(VersionedTransaction {
+            signatures: vec![
+                Signature::default();
+                usize::from(message.header().num_required_signatures)
+            ],
+            message,
+        })
+    }
+
+    fn signed_versioned_transaction_with_blockhash(
+        &self,
+        instructions: &[Instruction],
+        latest_hash: Hash,
+    ) -> Result<VersionedTransaction, ClientError> {
+        let mut signers: Vec<&dyn Signer> = self.signers.iter().map(|s| s.as_signer()).collect();
+        signers.push(&*self.payer);
+
+        let message = VersionedMessage::V0(self.compile_message_v0(instructions, latest_hash)?);
+        VersionedTransaction::try_new(message, &unique_signers(signers)).map_err(Into::into)
+    }
+
+    async fn signed_versioned_transaction_internal(
+        &self,
+    ) -> Result<VersionedTransaction, ClientError> {
+        let instructions = self.transaction_instructions_internal().await?;
+        let latest_hash = self.blockhash_internal().await?;
+
+        self.signed_versioned_transaction_with_blockhash(&instructions, latest_hash)
+    }
+
+    async fn signed_transaction_internal(&self) -> Result<Transaction, ClientError> {
+        let instructions = self.transaction_instructions_internal().await?;
+        let latest_hash = self.blockhash_internal().await?;
+
+        let tx = self.signed_transaction_with_blockhash(&instructions, latest_hash)?;
+        Ok(tx)
+    }
+
+    /// Blockhash of the request's transaction, which is the durable nonce of the request if any.
+    async fn blockhash_internal(&self) -> Result<Hash, ClientError> {
+        let Some(nonce) = &self.durable_nonce else {
+            return self.transport.get_latest_blockhash().await;
+        };
+        if let Some(value) = nonce.value {
+            return Ok(value);
+        }
+
+        let account = self
+            .transport
+            .get_account(&nonce.account, self.transport.commitment())
+            .await?
+            .ok_or(ClientError::AccountNotFound)?;
+        let data = solana_rpc_client_nonce_utils::data_from_account(&account)?;
+        if data.authority != nonce.authority {
+            return Err(NonceError::InvalidAuthority {
+                provided: nonce.authority,
+                expected: data.authority,
+            }
+            .into());
+        }
+
+        Ok(data.blockhash())
+    }
+
+    /// Instructions of the request's transaction: the request's instructions, prefixed with the
+    /// instruction advancing its durable nonce and the instructions of its compute budget.
+    async fn transaction_instructions_internal(&self) -> Result<Vec<Instruction>, ClientError> {
+        let mut instructions = self.nonce_instructions();
+        instructions.append(&mut self.budgeted_instructions_internal().await?);
+        Ok(instructions)
+    }
+
+    /// Instruction advancing the durable nonce of the request, which must come first.
+    fn nonce_instructions(&self) -> Vec<Instruction> {
+        self.durable_nonce
+            .iter()
+            .map(|nonce| {
+                system_instruction::advance_nonce_account(&nonce.account, &nonce.authority)
+            })
+            .collect()
+    }
+
+    /// Build the request's transaction without signing it.
+    async fn unsigned_transaction_internal(&self) -> Result<Transaction, ClientError> {
+        // Legacy transactions would silently leave out the lookup tables
+        if !self.address_lookup_tables.is_empty() {
+            return Err(ClientError::VersionedTransactionRequired);
+        }
+
+        let instructions = self.transaction_instructions_internal().await?;
+        let blockhash = self.blockhash_internal().await?;
+        let message =
+            Message::new_with_blockhash(&instructions, Some(&self.payer.pubkey()), &blockhash);
+
+        Ok(Transaction::new_unsigned(message))
+    }
+
+    /// Build the request's transaction, signed by the payer and the request's signers only.
+    async fn partially_signed_transaction_internal(&self) -> Result<Transaction, ClientError> {
+        let mut signers: Vec<&dyn Signer> = self.signers.iter().map(|s| s.as_signer()).collect();
+        signers.push(&*self.payer);
+
+        let mut tx = self.unsigned_transaction_internal().await?;
+        let blockhash = tx.message.recent_blockhash;
+        tx.try_partial_sign(&unique_signers(signers), blockhash)?;
+
+        Ok(tx)
+    }
+
+    /// Instructions of the request, prefixed with the compute budget instructions of the
+    /// request's [`ComputeBudget`], if any.
+    async fn budgeted_instructions_internal(&self) -> Result<Vec<Instruction>, ClientError> {
+        let mut instructions = self.instructions();
+        let Some(budget) = &self.compute_budget else {
+            return Ok(instructions);
+        };
+
+        // Compute budget instructions added manually take precedence
+        let has_budget_ix = |tag: u8| {
+            instructions.iter().any(|ix| {
+                solana_compute_budget_interface::check_id(&ix.program_id)
+                    && ix.data.first() == Some(&tag)
+            })
+        };
+        // Tags of `SetComputeUnitLimit` and `SetComputeUnitPrice` respectively
+        let set_limit = !has_budget_ix(2);
+        let set_price = budget.priority_fee.is_some() && !has_budget_ix(3);
+
+        let mut budget_ixs = vec![];
+        if set_limit {
+            // Simulate with the maximum limit, so that the default limit doesn't fail the simulation
+            let mut sim_ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+                MAX_COMPUTE_UNIT_LIMIT,
+            )];
+            sim_ixs.extend(self.nonce_instructions());
+            sim_ixs.extend_from_slice(&instructions);
+            let simulation = self.simulate_instructions_internal(&sim_ixs).await?;
+            if let Some(err) = simulation.err {
+                return Err(ClientError::SimulationError(err));
+            }
+
+            // Keep the default limit if the node doesn't report the consumed units
+            if let Some(units_consumed) = simulation.units_consumed {
+                let units = budget.unit_limit(units_consumed);
+                budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+            }
+        }
+        if let Some(priority_fee) = budget.priority_fee.as_ref().filter(|_| set_price) {
+            let price = self
+                .compute_unit_price_internal(&instructions, priority_fee)
+                .await?;
+            budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+
+        budget_ixs.append(&mut instructions);
+        Ok(budget_ixs)
+    }
+
+    async fn compute_unit_price_internal(
+        &self,
+        instructions: &[Instruction],
+        priority_fee: &PriorityFee,
+    ) -> Result<u64, ClientError> {
+        Ok(match priority_fee {
+            PriorityFee::Fixed(price) => *price,
+            PriorityFee::RecentPercentile(percentile) => {
+                let fees = self
+                    .recent_prioritization_fees_internal(instructions)
+                    .await?;
+                fee_percentile(&fees, *percentile)
+            }
+            PriorityFee::Callback(callback) => callback(
+                &self
+                    .recent_prioritization_fees_internal(instructions)
+                    .await?,
+            ),
+        })
+    }
+
+    async fn recent_prioritization_fees_internal(
+        &self,
+        instructions: &[Instruction],
+    ) -> Result<Vec<RpcPrioritizationFee>, ClientError> {
+        // Prioritization fees are local to the accounts the transaction write locks
+        let mut writable: Vec<Pubkey> = vec![];
+        for meta in instructions.iter().flat_map(|ix| &ix.accounts) {
+            if meta.is_writable && !writable.contains(&meta.pubkey) {
+                writable.push(meta.pubkey);
+            }
+        }
+        writable.truncate(MAX_PRIORITIZATION_FEE_ACCOUNTS);
+
+        self.transport
+            .get_recent_prioritization_fees(&writable)
+            .await
+    }
+
+    async fn simulate_internal(&self) -> Result<Simulation, ClientError> {
+        let instructions = self.transaction_instructions_internal().await?;
+        self.simulate_instructions_internal(&instructions).await
+    }
+
+    async fn simulate_instructions_internal(
+        &self,
+        instructions: &[Instruction],
+    ) -> Result<Simulation, ClientError> {
+        // Simulate without signatures, so that requests missing signers, e.g. the authority of
+        // their durable nonce, can be simulated
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.transport.commitment()),
+            ..RpcSimulateTransactionConfig::default()
+        };
+
+        let tx = self.unsigned_transaction_with_blockhash(instructions, Hash::default())?;
+        let result = self.transport.simulate_transaction(&tx, config).await?;
+
+        Simulation::new(result, self.program_id)
+    }
+
+    async fn send_internal(&self) -> Result<Signature, ClientError> {
+        let instructions = self.transaction_instructions_internal().await?;
+        let latest_hash = self.blockhash_internal().await?;
+
+        let tx = self.transaction_with_blockhash(&instructions, latest_hash)?;
+        self.transport.send_and_confirm_transaction(&tx).await
+    }
+
+    async fn send_and_return_internal<T: anchor_lang::AnchorDeserialize>(
+        &self,
+    ) -> Result<(Signature, T), ClientError> {
+        let signature = self.send_internal().await?;
+
+        let tx = get_transaction(self.transport, &signature, self.transport.commitment()).await?;
+
+        let return_data: Option<UiTransactionReturnData> =
+            tx.transaction.meta.and_then(|meta| meta.return_data.into());
+        let (program_id, data) = parse_return_data(return_data.ok_or(ClientError::NoReturnData)?)?;
+        let value = decode_return_data(&self.program_id, &program_id, &data)?;
+
+        Ok((signature, value))
+    }
+
+    async fn send_with_policy_internal(
+        &self,
+        policy: &SendPolicy,
+    ) -> Result<SendOutcome, ClientError> {
+        let commitment = policy.commitment.unwrap_or(self.transport.commitment());
+        let instructions = self.transaction_instructions_internal().await?;
+
+        let mut refreshes = 0;
+        loop {
+            // Durable nonces don't expire with the block height
+            let (blockhash, last_valid_block_height) = match self.durable_nonce {
+                Some(_) => (self.blockhash_internal().await?, u64::MAX),
+                None => {
+                    self.transport
+                        .get_latest_blockhash_with_commitment(commitment)
+                        .await?
+                }
+            };
+            let tx = self.transaction_with_blockhash(&instructions, blockhash)?;
+            let signature = tx.signatures[0];
+
+            let mut is_expired = false;
+            for attempt in 0..=policy.max_retries {
+                // Rebroadcasts would fail the preflight check once the transaction is processed
+                let config = RpcSendTransactionConfig {
+                    skip_preflight: policy.skip_preflight || attempt != 0,
+                    preflight_commitment: Some(commitment.commitment),
+                    max_retries: Some(0),
+                    ..RpcSendTransactionConfig::default()
+                };
+                if let Err(e) = self
+                    .transport
+                    .send_transaction_with_config(&tx, config)
+                    .await
+                {
+                    return match preflight_failure(&e) {
+                        Some((error, logs)) => Ok(SendOutcome::Failed(TransactionFailure::new(
+                            signature, error, logs,
+                        ))),
+                        None => Err(e),
+                    };
+                }
+
+                if let Some(outcome) = self
+                    .confirm_internal(&signature, commitment, policy.rebroadcast_interval)
+                    .await?
+                {
+                    return Ok(outcome);
+                }
+                let block_height = self.transport.get_block_height(commitment).await?;
+                if block_height > last_valid_block_height {
+                    is_expired = true;
+                    break;
+                }
+            }
+
+            if self.durable_nonce.is_some() {
+                return Ok(SendOutcome::RetriesExhausted(signature));
+            }
+            // The transaction may still be confirmed until its blockhash expires
+            while !is_expired {
+                if let Some(outcome) = self
+                    .confirm_internal(&signature, commitment, policy.rebroadcast_interval)
+                    .await?
+                {
+                    return Ok(outcome);
+                }
+                let block_height = self.transport.get_block_height(commitment).await?;
+                is_expired = block_height > last_valid_block_height;
+            }
+            // Re-signing a transaction that was processed right before its blockhash expired
+            // would execute the request twice
+            if let Some(outcome) = self.settle_expired_internal(&signature, commitment).await? {
+                return Ok(outcome);
+            }
+
+            if refreshes == policy.blockhash_refreshes {
+                return Ok(SendOutcome::Expired(signature));
+            }
+            refreshes += 1;
+        }
+    }
+
+    /// Outcome of a transaction whose blockhash expired, or `None` if it was never processed.
+    ///
+    /// The transaction can't be processed anymore, but if it already was, it's polled until it
+    /// reaches the commitment, or until it's dropped along with its fork.
+    async fn settle_expired_internal(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<SendOutcome>, ClientError> {
+        loop {
+            if let Some(outcome) = self
+                .confirm_internal(signature, commitment, Duration::ZERO)
+                .await?
+            {
+                return Ok(Some(outcome));
+            }
+
+            let statuses = self
+                .transport
+                .get_signature_statuses(std::slice::from_ref(signature))
+                .await?;
+            if statuses.into_iter().next().flatten().is_none() {
+                return Ok(None);
+            }
+            tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Poll the status of the transaction until it reaches the commitment or fails, for up to
+    /// `timeout`.
+    async fn confirm_internal(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+        timeout: Duration,
+    ) -> Result<Option<SendOutcome>, ClientError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let statuses = self
+                .transport
+                .get_signature_statuses(std::slice::from_ref(signature))
+                .await?;
+            if let Some(status) = statuses.into_iter().next().flatten() {
+                if let Some(error) = status.err {
+                    // The logs are only informative, so failing to fetch them isn't an error
+                    let logs = get_transaction(self.transport, signature, commitment)
+                        .await
+                        .ok()
+                        .and_then(|tx| tx.transaction.meta)
+                        .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages))
+                        .unwrap_or_default();
+                    return Ok(Some(SendOutcome::Failed(TransactionFailure::new(
+                        *signature, error, logs,
+                    ))));
+                }
+                if status.satisfies_commitment(commitment) {
+                    return Ok(Some(SendOutcome::Confirmed(*signature)));
+                }
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(CONFIRMATION_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    async fn send_with_spinner_and_config_internal(
+        &self,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, ClientError> {
+        let instructions = self.transaction_instructions_internal().await?;
+        let latest_hash = self.blockhash_internal().await?;
+        let commitment = self.transport.commitment();
+
+        let tx = self.transaction_with_blockhash(&instructions, latest_hash)?;
+        self.transport
+            .send_and_confirm_transaction_with_spinner_and_config(&tx, commitment, config)
+            .await
+    }
+}
+
+/// Transactions of a program within the bounds of a [`ReplayConfig`].
+struct TransactionHistory {
+    /// Signatures and slots of the successful transactions, oldest first
+    signatures: Vec<(Signature, u64)>,
+    /// Oldest transaction scanned
+    cursor: Option<Signature>,
+    /// Whether all transactions within the bounds were scanned
+    is_complete: bool,
+}
+
+/// Successful transactions of `program_id` within the bounds of `config`.
+///
+/// The history is paged from the newest transaction, so paging stops once the `limit` of
+/// `config` is reached, keeping the newest transactions.
+async fn transaction_history(
+    transport: &dyn Transport,
+    program_id: &Pubkey,
+    commitment: CommitmentConfig,
+    config: &ReplayConfig,
+) -> Result<TransactionHistory, ClientError> {
+    let mut signatures = vec![];
+    let mut before = config.before;
+    let mut is_complete = true;
+    'pages: loop {
+        let page = transport
+            .get_signatures_for_address(
+                program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: config.after,
+                    limit: Some(MAX_SIGNATURES_PER_PAGE),
+                    commitment: Some(confirmed_commitment(commitment)),
+                },
+            )
+            .await?;
+        let is_last_page = page.len() < MAX_SIGNATURES_PER_PAGE;
+
+        for status in page {
+            let signature: Signature = status
+                .signature
+                .parse()
+                .map_err(|_| ClientError::TransactionParseError(status.signature))?;
+
+            if config
+                .min_slot
+                .is_some_and(|min_slot| status.slot < min_slot)
+            {
+                break 'pages;
+            }
+            // Events of failed transactions are reverted
+            let is_replayed = config
+                .max_slot
+                .is_none_or(|max_slot| status.slot <= max_slot)
+                && status.err.is_none();
+            if is_replayed {
+                if config.limit.is_some_and(|limit| signatures.len() == limit) {
+                    is_complete = false;
+                    break 'pages;
+                }
+                signatures.push((signature, status.slot));
+            }
+            before = Some(signature);
+        }
+
+        if is_last_page {
+            break;
+        }
+    }
+
+    signatures.reverse();
+    Ok(TransactionHistory {
+        signatures,
+        cursor: before,
+        is_complete,
+    })
+}
+
+/// Parse the events emitted with `emit!` and `emit_cpi!` in the transactions of `program_id`
+/// after `after`, oldest first.
+async fn backfill_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    transport: &dyn Transport,
+    program_id: &Pubkey,
+    commitment: CommitmentConfig,
+    after: Signature,
+) -> Result<Vec<(EventContext, Vec<T>)>, ClientError> {
+    let config = ReplayConfig {
+        after: Some(after),
+        ..ReplayConfig::default()
+    };
+    let history = transaction_history(transport, program_id, commitment, &config).await?;
+
+    let mut transactions = vec![];
+    for (signature, slot) in history.signatures {
+        let tx = get_transaction(transport, &signature, commitment).await?;
+        let events = parse_transaction_events(&tx, program_id)?;
+        transactions.push((EventContext { signature, slot }, events));
     }
 
-    #[must_use]
-    pub fn args(mut self, args: impl InstructionData) -> Self {
-        self.instruction_data = Some(args.data());
-        self
+    Ok(transactions)
+}
+
+/// Wait before reconnecting a subscription, or return `false` if the attempts are exhausted.
+async fn wait_to_reconnect(config: &ReconnectConfig, attempt: u32) -> bool {
+    if config
+        .max_attempts
+        .is_some_and(|max_attempts| attempt >= max_attempts)
+    {
+        return false;
     }
 
-    pub fn instructions(&self) -> Vec<Instruction> {
-        let mut instructions = self.instructions.clone();
-        if let Some(ix_data) = &self.instruction_data {
-            instructions.push(Instruction {
-                program_id: self.program_id,
-                data: ix_data.clone(),
-                accounts: self.accounts.clone(),
-            });
-        }
+    let backoff = config
+        .initial_backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(config.max_backoff);
+    tokio::time::sleep(backoff).await;
+    true
+}
 
-        instructions
+/// Maximum number of accounts a single `getMultipleAccounts` request can fetch.
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Error of a subscription whose unsubscribe function couldn't be handed over.
+fn unsubscribe_failed(reason: impl ToString) -> ClientError {
+    ClientError::SolanaClientPubsubError(Box::new(PubsubClientError::RequestFailed {
+        message: "Unsubscribe failed".to_string(),
+        reason: reason.to_string(),
+    }))
+}
+
+/// Decode an account fetched with a binary encoding, checking its discriminator.
+fn decode_account<T: AccountDeserialize>(account: &UiAccount) -> Result<T, ClientError> {
+    let data = account.data.decode().ok_or_else(|| {
+        ClientError::AccountParseError("account data isn't binary encoded".to_owned())
+    })?;
+    T::try_deserialize(&mut data.as_slice()).map_err(Into::into)
+}
+
+/// Decode the account of a program account notification.
+///
+/// The address is the default pubkey if the notification has an invalid one.
+fn decode_keyed_account<T: AccountDeserialize>(
+    keyed_account: &RpcKeyedAccount,
+) -> (Pubkey, Result<T, ClientError>) {
+    match keyed_account.pubkey.parse() {
+        Ok(pubkey) => (pubkey, decode_account(&keyed_account.account)),
+        Err(e) => (
+            Pubkey::default(),
+            Err(ClientError::AccountParseError(format!(
+                "invalid address `{}`: {e}",
+                keyed_account.pubkey
+            ))),
+        ),
     }
+}
 
-    fn signed_transaction_with_blockhash(
-        &self,
-        latest_hash: Hash,
-    ) -> Result<Transaction, ClientError> {
-        let signers: Vec<&dyn Signer> = self.signers.iter().map(|s| s.as_signer()).collect();
-        let mut all_signers = signers;
-        all_signers.push(&*self.payer);
+/// Commitment to fetch transactions with, as they can only be fetched once confirmed.
+fn confirmed_commitment(commitment: CommitmentConfig) -> CommitmentConfig {
+    if commitment.is_at_least_confirmed() {
+        commitment
+    } else {
+        CommitmentConfig::confirmed()
+    }
+}
 
-        let mut tx = self.transaction();
-        tx.try_sign(&all_signers, latest_hash)?;
+/// Error and logs of a transaction that failed the preflight simulation of the RPC node.
+fn preflight_failure(e: &ClientError) -> Option<(TransactionError, Vec<String>)> {
+    let ClientError::SolanaClientError(e) = e else {
+        return None;
+    };
+    match e.kind() {
+        SolanaClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => Some((
+            result.err.clone()?.into(),
+            result.logs.clone().unwrap_or_default(),
+        )),
+        _ => None,
+    }
+}
 
-        Ok(tx)
+/// Parse the last error logged by an Anchor program in the logs of a transaction.
+pub fn parse_anchor_error(logs: &[String]) -> Option<AnchorErrorLog> {
+    let re = Regex::new(
+        r"^Program log: AnchorError (?:occurred|thrown in (?P<file>.+):(?P<line>\d+)|caused by account: (?P<account>.+))\. Error Code: (?P<name>\w+)\. Error Number: (?P<number>\d+)\. Error Message: (?P<message>.*)\.$",
+    )
+    .unwrap();
+
+    logs.iter().rev().find_map(|log| {
+        let captures = re.captures(log)?;
+        let origin = match (captures.name("file"), captures.name("line")) {
+            (Some(file), Some(line)) => Some(AnchorErrorOrigin::Source {
+                file: file.as_str().to_owned(),
+                line: line.as_str().parse().ok()?,
+            }),
+            _ => captures
+                .name("account")
+                .map(|account| AnchorErrorOrigin::Account(account.as_str().to_owned())),
+        };
+
+        Some(AnchorErrorLog {
+            name: captures["name"].to_owned(),
+            number: captures["number"].parse().ok()?,
+            message: captures["message"].to_owned(),
+            origin,
+        })
+    })
+}
+
+async fn get_transaction(
+    transport: &dyn Transport,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(confirmed_commitment(commitment)),
+        max_supported_transaction_version: Some(0),
+    };
+    transport.get_transaction(signature, config).await
+}
+
+/// Number of attempts to fetch a transaction notified by a subscription.
+const NOTIFIED_TRANSACTION_FETCH_ATTEMPTS: u32 = 3;
+
+/// Delay before fetching a notified transaction again, doubled after each attempt.
+const NOTIFIED_TRANSACTION_FETCH_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Fetch a transaction notified by a subscription, which may not be available over RPC as soon
+/// as its logs are notified.
+async fn get_notified_transaction(
+    transport: &dyn Transport,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+    let mut backoff = NOTIFIED_TRANSACTION_FETCH_BACKOFF;
+    for _ in 1..NOTIFIED_TRANSACTION_FETCH_ATTEMPTS {
+        if let Ok(tx) = get_transaction(transport, signature, commitment).await {
+            return Ok(tx);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
     }
 
-    pub fn transaction(&self) -> Transaction {
-        let instructions = &self.instructions();
-        Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()))
+    get_transaction(transport, signature, commitment).await
+}
+
+/// Parse the events of type `T` emitted with `emit_cpi!` by `program_id` in the given
+/// transaction.
+///
+/// The transaction must be fetched with a binary encoding, e.g. [`UiTransactionEncoding::Base64`].
+pub fn parse_cpi_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    program_id: &Pubkey,
+) -> Result<Vec<T>, ClientError> {
+    let events = parse_indexed_cpi_events(tx, program_id)?;
+    Ok(events.into_iter().map(|(_, event)| event).collect())
+}
+
+/// Parse the events emitted with `emit_cpi!` along with the index of their instruction among
+/// the inner instructions of the transaction.
+fn parse_indexed_cpi_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    program_id: &Pubkey,
+) -> Result<Vec<(usize, T)>, ClientError> {
+    let Some(meta) = &tx.transaction.meta else {
+        return Ok(vec![]);
+    };
+    let inner_instructions: Option<&Vec<UiInnerInstructions>> =
+        meta.inner_instructions.as_ref().into();
+    let Some(inner_instructions) = inner_instructions else {
+        return Ok(vec![]);
+    };
+
+    let account_keys = transaction_account_keys(tx)?;
+    let key = |index: &u8| account_keys.get(usize::from(*index));
+    let event_authority = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id).0;
+
+    let mut events = vec![];
+    for (index, ix) in inner_instructions
+        .iter()
+        .flat_map(|ixs| &ixs.instructions)
+        .enumerate()
+    {
+        let UiInstruction::Compiled(ix) = ix else {
+            continue;
+        };
+        // Only the program itself can sign with its event authority
+        if key(&ix.program_id_index) != Some(program_id)
+            || ix.accounts.first().and_then(key) != Some(&event_authority)
+        {
+            continue;
+        }
+
+        let data = bs58::decode(&ix.data)
+            .into_vec()
+            .map_err(|e| ClientError::TransactionParseError(e.to_string()))?;
+        let Some(mut data) = data
+            .strip_prefix(anchor_lang::event::EVENT_IX_TAG_LE)
+            .and_then(|data| data.strip_prefix(T::DISCRIMINATOR))
+        else {
+            continue;
+        };
+        let event =
+            T::deserialize(&mut data).map_err(|e| ClientError::LogParseError(e.to_string()))?;
+        events.push((index, event));
     }
 
-    async fn signed_transaction_internal(&self) -> Result<Transaction, ClientError> {
-        let latest_hash = self
-            .internal_rpc_client
-            .get_latest_blockhash()
-            .await
-            .map_err(Box::new)?;
+    Ok(events)
+}
 
-        let tx = self.signed_transaction_with_blockhash(latest_hash)?;
-        Ok(tx)
+/// Parse the events of type `T` emitted by `program_id` in the given transaction, both with
+/// `emit!` and `emit_cpi!`, in the order they were emitted.
+///
+/// If the logs of the transaction were truncated, the events emitted with `emit_cpi!` after the
+/// truncation are returned last.
+pub fn parse_transaction_events<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    program_id: &Pubkey,
+) -> Result<Vec<T>, ClientError> {
+    let logs: Option<&Vec<String>> = tx
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.log_messages.as_ref().into());
+    let logs = logs.map(Vec::as_slice).unwrap_or_default();
+    let mut events = parse_indexed_logs(logs, &program_id.to_string())?;
+
+    // Inner instructions are in the order their invocations are logged
+    let regex = Regex::new(r"^Program [1-9A-HJ-NP-Za-km-z]+ invoke \[(\d+)\]$").unwrap();
+    let invocations: Vec<usize> = logs
+        .iter()
+        .enumerate()
+        .filter(|(_, log)| {
+            regex
+                .captures(log)
+                .is_some_and(|captures| &captures[1] != "1")
+        })
+        .map(|(index, _)| index)
+        .collect();
+    for (index, event) in parse_indexed_cpi_events(tx, program_id)? {
+        let log_index = invocations.get(index).copied().unwrap_or(usize::MAX);
+        events.push((log_index, event));
     }
+    events.sort_by_key(|(log_index, _)| *log_index);
 
-    async fn send_internal(&self) -> Result<Signature, ClientError> {
-        let latest_hash = self
-            .internal_rpc_client
-            .get_latest_blockhash()
-            .await
-            .map_err(Box::new)?;
-        let tx = self.signed_transaction_with_blockhash(latest_hash)?;
+    Ok(events.into_iter().map(|(_, event)| event).collect())
+}
 
-        self.internal_rpc_client
-            .send_and_confirm_transaction(&tx)
-            .await
-            .map_err(|e| Box::new(e).into())
+/// Keys of the accounts of the transaction, including the ones loaded from lookup tables, in
+/// the order instructions index them.
+fn transaction_account_keys(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Vec<Pubkey>, ClientError> {
+    let transaction = tx.transaction.transaction.decode().ok_or_else(|| {
+        ClientError::TransactionParseError("Unsupported transaction encoding".into())
+    })?;
+    let mut keys = transaction.message.static_account_keys().to_vec();
+
+    let loaded_addresses: Option<&UiLoadedAddresses> = tx
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.loaded_addresses.as_ref().into());
+    if let Some(loaded_addresses) = loaded_addresses {
+        for key in loaded_addresses
+            .writable
+            .iter()
+            .chain(&loaded_addresses.readonly)
+        {
+            let key = key
+                .parse()
+                .map_err(|_| ClientError::TransactionParseError(format!("Invalid key {key}")))?;
+            keys.push(key);
+        }
     }
 
-    async fn send_with_spinner_and_config_internal(
-        &self,
-        config: RpcSendTransactionConfig,
-    ) -> Result<Signature, ClientError> {
-        let latest_hash = self
-            .internal_rpc_client
-            .get_latest_blockhash()
-            .await
-            .map_err(Box::new)?;
-        let tx = self.signed_transaction_with_blockhash(latest_hash)?;
-
-        self.internal_rpc_client
-            .send_and_confirm_transaction_with_spinner_and_config(
-                &tx,
-                self.internal_rpc_client.commitment(),
-                config,
-            )
-            .await
-            .map_err(|e| Box::new(e).into())
+    Ok(keys)
+}
+
+/// Remove signers with duplicate public keys, which would fail to sign a transaction.
+fn unique_signers(signers: Vec<&dyn Signer>) -> Vec<&dyn Signer> {
+    let mut unique: Vec<&dyn Signer> = Vec::with_capacity(signers.len());
+    for signer in signers {
+        if !unique.iter().any(|s| s.pubkey() == signer.pubkey()) {
+            unique.push(signer);
+        }
     }
+
+    unique
 }
 
 fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
     logs: RpcResponse<RpcLogsResponse>,
     program_id_str: &str,
 ) -> Result<Vec<T>, ClientError> {
-    let mut logs = &logs.value.logs[..];
-    let mut events: Vec<T> = Vec::new();
+    parse_logs(&logs.value.logs, program_id_str)
+}
+
+fn parse_logs<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    logs: &[String],
+    program_id_str: &str,
+) -> Result<Vec<T>, ClientError> {
+    let events = parse_indexed_logs(logs, program_id_str)?;
+    Ok(events.into_iter().map(|(_, event)| event).collect())
+}
+
+/// Parse the events of the logs along with the index of the log they were emitted in.
+fn parse_indexed_logs<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    logs: &[String],
+    program_id_str: &str,
+) -> Result<Vec<(usize, T)>, ClientError> {
+    let mut logs = logs;
+    let mut events: Vec<(usize, T)> = Vec::new();
     if !logs.is_empty() {
         if let Ok(mut execution) = Execution::new(&mut logs) {
-            // Create a new peekable iterator so that we can peek at the next log whilst iterating
-            let mut logs_iter = logs.iter().peekable();
+            // Create a new peekable iterator so that we can peek at the next log whilst iterating.
+            // The first log was consumed by `Execution::new`.
+            let mut logs_iter = logs.iter().zip(1..).peekable();
             let regex = Regex::new(r"^Program ([1-9A-HJ-NP-Za-km-z]+) invoke \[[\d]+\]$").unwrap();
 
-            while let Some(l) = logs_iter.next() {
+            while let Some((l, index)) = logs_iter.next() {
                 // Parse the log.
                 let (event, new_program, did_pop) = {
                     if program_id_str == execution.program() {
@@ -708,7 +2637,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
                 };
                 // Emit the event.
                 if let Some(e) = event {
-                    events.push(e);
+                    events.push((index, e));
                 }
                 // Switch program context on CPI.
                 if let Some(new_program) = new_program {
@@ -725,7 +2654,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
                     // We need to ensure that the `Execution` instance is updated with
                     // the next program ID, or else `execution.program()` will cause
                     // a panic during the next iteration.
-                    if let Some(&next_log) = logs_iter.peek() {
+                    if let Some(&(next_log, _)) = logs_iter.peek() {
                         if next_log.ends_with("invoke [1]") {
                             let next_instruction =
                                 regex.captures(next_log).unwrap().get(1).unwrap().as_str();
@@ -744,6 +2673,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
 
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
     use solana_rpc_client_api::response::RpcResponseContext;
 
     // Creating a mock struct that implements `anchor_lang::events`
@@ -753,6 +2683,12 @@ mod tests {
     #[event]
     pub struct MockEvent {}
 
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[event]
+    pub struct ValueEvent {
+        value: u8,
+    }
+
     use super::*;
     #[test]
     fn new_execution() {
@@ -916,4 +2852,903 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn compute_budget_unit_limit() {
+        let budget = ComputeBudget::default();
+        assert_eq!(budget.unit_limit(1000), 1100);
+        assert_eq!(budget.margin(50).unit_limit(1000), 1500);
+        assert_eq!(
+            ComputeBudget::default().unit_limit(u64::MAX),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+
+    #[test]
+    fn compute_budget_simulation() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let ix = Instruction::new_with_bytes(program.id(), &[], vec![]);
+        let request = program
+            .request()
+            .instruction(ix.clone())
+            .compute_budget(ComputeBudget::default());
+
+        futures::executor::block_on(async {
+            let mut simulation = transport
+                .simulate_transaction(&VersionedTransaction::default(), Default::default())
+                .await
+                .unwrap();
+            simulation.units_consumed = Some(1000);
+            transport.set_simulation(simulation.clone());
+            let instructions = request.budgeted_instructions_internal().await.unwrap();
+            assert_eq!(
+                instructions,
+                vec![
+                    ComputeBudgetInstruction::set_compute_unit_limit(1100),
+                    ix.clone()
+                ]
+            );
+
+            // No limit is set rather than a limit of zero
+            simulation.units_consumed = None;
+            transport.set_simulation(simulation);
+            let instructions = request.budgeted_instructions_internal().await.unwrap();
+            assert_eq!(instructions, vec![ix]);
+        });
+    }
+
+    #[test]
+    fn nonce_with_compute_budget() {
+        use solana_keypair::Keypair;
+        use solana_signer::Signer;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let ix = Instruction::new_with_bytes(program.id(), &[], vec![]);
+        let (nonce_account, custodian) = (Pubkey::new_unique(), Keypair::new());
+        let request = program
+            .request()
+            .instruction(ix.clone())
+            .durable_nonce_with_value(nonce_account, custodian.pubkey(), Hash::new_unique())
+            .compute_budget(ComputeBudget::default());
+
+        futures::executor::block_on(async {
+            let mut simulation = transport
+                .simulate_transaction(&VersionedTransaction::default(), Default::default())
+                .await
+                .unwrap();
+            simulation.units_consumed = Some(1000);
+            transport.set_simulation(simulation);
+
+            // The budget is simulated without the signature of the nonce authority
+            let tx = request.unsigned_transaction_internal().await.unwrap();
+            let instructions: Vec<Pubkey> = tx
+                .message
+                .instructions
+                .iter()
+                .map(|ix| *ix.program_id(&tx.message.account_keys))
+                .collect();
+            assert_eq!(
+                instructions,
+                vec![
+                    solana_system_interface::program::ID,
+                    solana_compute_budget_interface::ID,
+                    program.id(),
+                ]
+            );
+
+            let mut tx = request
+                .partially_signed_transaction_internal()
+                .await
+                .unwrap();
+            let signature = custodian.sign_message(&tx.message_data());
+            add_signatures(&mut tx, &[(custodian.pubkey(), signature)]).unwrap();
+            assert!(tx.is_signed());
+
+            // Legacy transactions can't use lookup tables
+            let table = AddressLookupTableAccount {
+                key: Pubkey::new_unique(),
+                addresses: vec![program.id()],
+            };
+            let request = program
+                .request()
+                .instruction(ix)
+                .address_lookup_table(table);
+            assert!(matches!(
+                request.unsigned_transaction_internal().await,
+                Err(ClientError::VersionedTransactionRequired)
+            ));
+        });
+    }
+
+    #[test]
+    fn recent_fee_percentile() {
+        let fees: Vec<RpcPrioritizationFee> = [50, 0, 10, 40, 20, 30]
+            .into_iter()
+            .enumerate()
+            .map(|(slot, prioritization_fee)| RpcPrioritizationFee {
+                slot: slot as u64,
+                prioritization_fee,
+            })
+            .collect();
+
+        assert_eq!(fee_percentile(&[], 50), 0);
+        assert_eq!(fee_percentile(&fees, 0), 0);
+        assert_eq!(fee_percentile(&fees, 50), 20);
+        assert_eq!(fee_percentile(&fees, 75), 30);
+        assert_eq!(fee_percentile(&fees, 100), 50);
+        assert_eq!(fee_percentile(&fees, 200), 50);
+    }
+
+    #[test]
+    fn decode_trimmed_return_data() {
+        let program_id = Pubkey::new_unique();
+        let value: (u8, u64) = decode_return_data(&program_id, &program_id, &[1]).unwrap();
+        assert_eq!(value, (1, 0));
+        let value: u64 = decode_return_data(&program_id, &program_id, &[]).unwrap();
+        assert_eq!(value, 0);
+
+        let other_program_id = Pubkey::new_unique();
+        assert!(matches!(
+            decode_return_data::<u64>(&program_id, &other_program_id, &[1]),
+            Err(ClientError::ReturnDataProgramMismatch(id)) if id == other_program_id
+        ));
+    }
+
+    #[test]
+    fn mock_transport() {
+        futures::executor::block_on(async {
+            let transport = MockTransport::new();
+            let program_id = Pubkey::new_unique();
+            let address = Pubkey::new_unique();
+            let account = solana_account::Account {
+                data: vec![1, 2, 3],
+                owner: program_id,
+                ..Default::default()
+            };
+            transport.set_account(address, account.clone());
+
+            let accounts = transport
+                .get_multiple_accounts(
+                    &[address, Pubkey::new_unique()],
+                    CommitmentConfig::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(accounts, vec![Some(account), None]);
+
+            let tx = VersionedTransaction::default();
+            transport.send_and_confirm_transaction(&tx).await.unwrap();
+            assert_eq!(transport.sent_transactions(), vec![tx]);
+
+            // Logs are replayed to the subscriptions mentioning the program
+            let invoke = format!("Program {program_id} invoke [1]");
+            transport.push_logs(Signature::default(), vec![invoke.clone()]);
+            transport.push_logs(Signature::default(), vec!["Program log: other".into()]);
+            let filter = RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]);
+            let config = RpcTransactionLogsConfig { commitment: None };
+            let (mut notifications, unsubscribe) =
+                transport.logs_subscribe(filter, config).await.unwrap();
+            assert_eq!(notifications.next().await.unwrap().value.logs, vec![invoke]);
+
+            unsubscribe().await;
+            assert!(notifications.next().await.is_none());
+
+            // Transactions are returned newest first, within the bounds of the request
+            let transactions: Vec<_> = (1..=3)
+                .map(|slot| confirmed_transaction(slot, program_id, vec![], json!([])))
+                .collect();
+            let signatures: Vec<Signature> = transactions
+                .iter()
+                .map(|tx| tx.transaction.transaction.decode().unwrap().signatures[0])
+                .collect();
+            transactions
+                .into_iter()
+                .for_each(|tx| transport.push_transaction(tx));
+
+            let config = |before, until| GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: None,
+                commitment: None,
+            };
+            let history = |before, until| {
+                let transport = &transport;
+                async move {
+                    transport
+                        .get_signatures_for_address(&program_id, config(before, until))
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|status| (status.signature.parse().unwrap(), status.slot))
+                        .collect::<Vec<(Signature, u64)>>()
+                }
+            };
+            assert_eq!(
+                history(None, None).await,
+                vec![(signatures[2], 3), (signatures[1], 2), (signatures[0], 1)]
+            );
+            assert_eq!(
+                history(Some(signatures[2]), Some(signatures[0])).await,
+                vec![(signatures[1], 2)]
+            );
+            assert!(transport
+                .get_signatures_for_address(&Pubkey::new_unique(), config(None, None))
+                .await
+                .unwrap()
+                .is_empty());
+
+            let tx = get_transaction(&transport, &signatures[1], CommitmentConfig::default())
+                .await
+                .unwrap();
+            assert_eq!(tx.slot, 2);
+            assert!(get_transaction(
+                &transport,
+                &Signature::default(),
+                CommitmentConfig::default()
+            )
+            .await
+            .is_err());
+        });
+    }
+
+    #[test]
+    fn on_cpi_reports_failed_transactions() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+
+        let data = [
+            anchor_lang::event::EVENT_IX_TAG_LE,
+            MockEvent::DISCRIMINATOR,
+        ]
+        .concat();
+        let inner_instructions = json!([{
+            "index": 0,
+            "instructions": [{
+                "programIdIndex": 1,
+                "accounts": [2],
+                "data": bs58::encode(data).into_string(),
+                "stackHeight": 2,
+            }],
+        }]);
+        let invoke = format!("Program {} invoke [1]", program.id());
+        transport.push_transaction(confirmed_transaction(
+            1,
+            program.id(),
+            vec![invoke.clone()],
+            inner_instructions,
+        ));
+        // Logs of a transaction that can't be fetched
+        let missing = Signature::from([2; 64]);
+        transport.push_logs(missing, vec![invoke]);
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (tx, mut rx) = unbounded_channel();
+            let (handle, _unsubscribe) = program
+                .on_cpi_internal(
+                    move |ctx, event: std::result::Result<MockEvent, ClientError>| {
+                        let _ = tx.send((ctx.signature, event));
+                    },
+                )
+                .await
+                .unwrap();
+
+            let (signature, event) = rx.recv().await.unwrap();
+            assert_eq!(signature, Signature::from([1; 64]));
+            assert!(event.is_ok());
+            let (signature, event) = rx.recv().await.unwrap();
+            assert_eq!(signature, missing);
+            assert!(event.is_err());
+            assert!(!handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn decode_keyed_account_errors() {
+        use solana_account_decoder::{
+            encode_ui_account, parse_account_data::ParsedAccount, UiAccountData,
+        };
+
+        #[derive(Debug, PartialEq)]
+        struct ByteAccount(u8);
+
+        impl AccountDeserialize for ByteAccount {
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+                match buf {
+                    [value] => Ok(Self(*value)),
+                    _ => Err(ErrorCode::AccountDidNotDeserialize.into()),
+                }
+            }
+        }
+
+        let pubkey = Pubkey::new_unique();
+        let account = |data: Vec<u8>| {
+            let account = solana_account::Account {
+                data,
+                ..Default::default()
+            };
+            encode_ui_account(&pubkey, &account, UiAccountEncoding::Base64, None, None)
+        };
+        let keyed_account = |pubkey: String, account| RpcKeyedAccount { pubkey, account };
+
+        let (address, decoded) = decode_keyed_account::<ByteAccount>(&keyed_account(
+            pubkey.to_string(),
+            account(vec![7]),
+        ));
+        assert_eq!(address, pubkey);
+        assert_eq!(decoded.unwrap(), ByteAccount(7));
+
+        let (address, decoded) = decode_keyed_account::<ByteAccount>(&keyed_account(
+            pubkey.to_string(),
+            account(vec![1, 2]),
+        ));
+        assert_eq!(address, pubkey);
+        assert!(matches!(decoded, Err(ClientError::AnchorError(_))));
+
+        let (address, decoded) = decode_keyed_account::<ByteAccount>(&keyed_account(
+            "invalid".to_owned(),
+            account(vec![7]),
+        ));
+        assert_eq!(address, Pubkey::default());
+        assert!(matches!(decoded, Err(ClientError::AccountParseError(_))));
+
+        let mut json_account = account(vec![7]);
+        json_account.data = UiAccountData::Json(ParsedAccount {
+            program: "program".to_owned(),
+            parsed: json!({}),
+            space: 1,
+        });
+        let (_, decoded) =
+            decode_keyed_account::<ByteAccount>(&keyed_account(pubkey.to_string(), json_account));
+        assert!(matches!(decoded, Err(ClientError::AccountParseError(_))));
+    }
+
+    #[test]
+    fn accounts_multiple_chunks() {
+        use solana_keypair::Keypair;
+
+        /// Account enum dispatching on the first byte, like the `parsers::Account` enum of
+        /// `declare_program!`
+        #[derive(Debug, PartialEq)]
+        enum AnyAccount {
+            Value(u8),
+            Empty,
+        }
+
+        impl TryFrom<&[u8]> for AnyAccount {
+            type Error = anchor_lang::error::Error;
+
+            fn try_from(data: &[u8]) -> std::result::Result<Self, Self::Error> {
+                match data {
+                    [0, value] => Ok(Self::Value(*value)),
+                    [1] => Ok(Self::Empty),
+                    _ => Err(ErrorCode::AccountDiscriminatorNotFound.into()),
+                }
+            }
+        }
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+
+        // Every third account is missing
+        let addresses: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+        let data = |index: usize| match index % 3 {
+            0 => Some(vec![0, index as u8]),
+            1 => Some(vec![1]),
+            _ => None,
+        };
+        for (index, address) in addresses.iter().enumerate() {
+            if let Some(data) = data(index) {
+                let account = solana_account::Account {
+                    data,
+                    owner: program.id(),
+                    ..Default::default()
+                };
+                transport.set_account(*address, account);
+            }
+        }
+        let unknown = Pubkey::new_unique();
+        transport.set_account(
+            unknown,
+            solana_account::Account {
+                data: vec![2],
+                ..Default::default()
+            },
+        );
+
+        futures::executor::block_on(async {
+            let accounts = program
+                .multiple_accounts_data_internal(&addresses)
+                .await
+                .unwrap();
+            assert_eq!(accounts, (0..250).map(data).collect::<Vec<_>>());
+
+            let accounts = program
+                .accounts_multiple_any_internal::<AnyAccount>(&addresses)
+                .await
+                .unwrap();
+            assert_eq!(accounts.len(), 250);
+            for (index, account) in accounts.into_iter().enumerate() {
+                let expected = match index % 3 {
+                    0 => Some(AnyAccount::Value(index as u8)),
+                    1 => Some(AnyAccount::Empty),
+                    _ => None,
+                };
+                assert_eq!(account.map(|account| account.unwrap()), expected);
+            }
+
+            let accounts = program
+                .accounts_multiple_any_internal::<AnyAccount>(&[unknown])
+                .await
+                .unwrap();
+            assert!(matches!(accounts[..], [Some(Err(_))]));
+        });
+    }
+
+    /// Log of `ValueEvent { value }` emitted with `emit!`.
+    fn value_event_log(value: u8) -> String {
+        use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+
+        let data = [ValueEvent::DISCRIMINATOR, &[value]].concat();
+        format!("{PROGRAM_DATA}{}", STANDARD.encode(data))
+    }
+
+    #[test]
+    fn transaction_events_order() {
+        let program_id = Pubkey::new_unique();
+        let data = [
+            anchor_lang::event::EVENT_IX_TAG_LE,
+            ValueEvent::DISCRIMINATOR,
+            &[2],
+        ]
+        .concat();
+        let inner_instructions = json!([{
+            "index": 0,
+            "instructions": [{
+                "programIdIndex": 1,
+                "accounts": [2],
+                "data": bs58::encode(data).into_string(),
+                "stackHeight": 2,
+            }],
+        }]);
+        let logs = vec![
+            format!("Program {program_id} invoke [1]"),
+            value_event_log(1),
+            format!("Program {program_id} invoke [2]"),
+            format!("Program {program_id} success"),
+            value_event_log(3),
+            format!("Program {program_id} success"),
+        ];
+        let tx = confirmed_transaction(1, program_id, logs, inner_instructions);
+
+        // Events emitted with `emit!` and `emit_cpi!` are interleaved
+        let events: Vec<ValueEvent> = parse_transaction_events(&tx, &program_id).unwrap();
+        assert_eq!(events, [1, 2, 3].map(|value| ValueEvent { value }).to_vec());
+    }
+
+    #[test]
+    fn replay_events_pages() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        for slot in 1..=3 {
+            let logs = vec![
+                format!("Program {} invoke [1]", program.id()),
+                value_event_log(slot),
+                format!("Program {} success", program.id()),
+            ];
+            transport.push_transaction(confirmed_transaction(
+                slot.into(),
+                program.id(),
+                logs,
+                json!([]),
+            ));
+        }
+        let signature = |slot: u8| Signature::from([slot; 64]);
+        let values = |replayed: &ReplayedEvents<ValueEvent>| {
+            replayed
+                .events
+                .iter()
+                .map(|(_, event)| event.value)
+                .collect::<Vec<_>>()
+        };
+
+        futures::executor::block_on(async {
+            // The newest transactions are replayed first
+            let config = ReplayConfig {
+                limit: Some(2),
+                ..ReplayConfig::default()
+            };
+            let replayed = program.replay_events_internal(config).await.unwrap();
+            assert_eq!(values(&replayed), vec![2, 3]);
+            assert_eq!(replayed.cursor, Some(signature(2)));
+            assert!(!replayed.is_complete);
+
+            let config = ReplayConfig {
+                before: replayed.cursor,
+                ..config
+            };
+            let replayed = program.replay_events_internal(config).await.unwrap();
+            assert_eq!(values(&replayed), vec![1]);
+            assert_eq!(replayed.cursor, Some(signature(1)));
+            assert!(replayed.is_complete);
+
+            // The cursor is the oldest transaction scanned, even without events
+            let config = ReplayConfig {
+                max_slot: Some(0),
+                ..ReplayConfig::default()
+            };
+            let replayed = program
+                .replay_events_internal::<ValueEvent>(config)
+                .await
+                .unwrap();
+            assert!(replayed.events.is_empty());
+            assert_eq!(replayed.cursor, Some(signature(1)));
+        });
+    }
+
+    #[test]
+    fn on_with_reconnect_reports_malformed_logs() {
+        use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let invoke = format!("Program {} invoke [1]", program.id());
+        // Event without its data
+        let malformed = format!(
+            "{PROGRAM_DATA}{}",
+            STANDARD.encode(ValueEvent::DISCRIMINATOR)
+        );
+        transport.push_logs(Signature::from([1; 64]), vec![invoke.clone(), malformed]);
+        transport.push_logs(Signature::from([2; 64]), vec![invoke, value_event_log(2)]);
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (tx, mut rx) = unbounded_channel();
+            let (handle, _unsubscribe) = program
+                .on_with_reconnect_internal(ReconnectConfig::default(), move |notification| {
+                    let _ = tx.send(notification);
+                })
+                .await
+                .unwrap();
+
+            let notification = rx.recv().await.unwrap();
+            assert!(matches!(
+                notification,
+                EventNotification::Error(ctx, ClientError::LogParseError(_))
+                    if ctx.signature == Signature::from([1; 64])
+            ));
+            let notification = rx.recv().await.unwrap();
+            assert!(matches!(
+                notification,
+                EventNotification::Event(_, ValueEvent { value: 2 })
+            ));
+            assert!(!handle.is_finished());
+        });
+    }
+
+    #[test]
+    fn on_with_reconnect_backs_off_dropped_connections() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let config = ReconnectConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_attempts: Some(2),
+            ..ReconnectConfig::default()
+        };
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (tx, mut rx) = unbounded_channel();
+            let (handle, mut unsubscribe) = program
+                .on_with_reconnect_internal::<ValueEvent>(config, move |notification| {
+                    let _ = tx.send(notification);
+                })
+                .await
+                .unwrap();
+
+            // Connections dropped without notifications are failed attempts
+            for _ in 0..2 {
+                assert!(unsubscribe.recv().await.is_some());
+                transport.disconnect();
+                let notification = rx.recv().await.unwrap();
+                assert!(matches!(notification, EventNotification::Gap { .. }));
+            }
+            assert!(unsubscribe.recv().await.is_some());
+            transport.disconnect();
+            assert!(matches!(
+                handle.await.unwrap(),
+                Err(ClientError::SolanaClientPubsubError(_))
+            ));
+        });
+    }
+
+    /// Confirmed transaction of `program_id` as fetched with a binary encoding, with the given
+    /// logs and inner instructions (in their JSON RPC format).
+    ///
+    /// The account keys of the transaction are the payer, `program_id` and the event authority
+    /// of `program_id`, in that order.
+    fn confirmed_transaction(
+        slot: u64,
+        program_id: Pubkey,
+        logs: Vec<String>,
+        inner_instructions: serde_json::Value,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let payer = Pubkey::new_unique();
+        let event_authority = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &program_id).0;
+        let message = v0::Message {
+            header: solana_message::MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 2,
+            },
+            account_keys: vec![payer, program_id, event_authority],
+            instructions: vec![solana_message::compiled_instruction::CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![2],
+                data: vec![],
+            }],
+            ..Default::default()
+        };
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::from([slot as u8; 64])],
+            message: VersionedMessage::V0(message),
+        };
+        let tx = bs58::encode(bincode::serialize(&tx).unwrap()).into_string();
+
+        serde_json::from_value(json!({
+            "slot": slot,
+            "blockTime": null,
+            "transaction": [tx, "base58"],
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": inner_instructions,
+                "logMessages": logs,
+                "loadedAddresses": { "writable": [], "readonly": [] },
+            },
+            "version": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_anchor_error_logs() {
+        let logs = [
+            "Program log: AnchorError caused by account: vault. Error Code: ConstraintMut. Error Number: 2000. Error Message: A mut constraint was violated.",
+            "Program log: AnchorError thrown in programs/vault/src/lib.rs:42. Error Code: InsufficientFunds. Error Number: 6000. Error Message: Not enough funds. Need more.",
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS failed: custom program error: 0x1770",
+        ]
+        .map(String::from);
+
+        // The last error logged is the one that failed the transaction
+        let error = parse_anchor_error(&logs).unwrap();
+        assert_eq!(
+            error,
+            AnchorErrorLog {
+                name: "InsufficientFunds".into(),
+                number: 6000,
+                message: "Not enough funds. Need more".into(),
+                origin: Some(AnchorErrorOrigin::Source {
+                    file: "programs/vault/src/lib.rs".into(),
+                    line: 42,
+                }),
+            }
+        );
+
+        let error = parse_anchor_error(&logs[..1]).unwrap();
+        assert_eq!(error.number, 2000);
+        assert_eq!(
+            error.origin,
+            Some(AnchorErrorOrigin::Account("vault".into()))
+        );
+        assert!(parse_anchor_error(&logs[2..]).is_none());
+    }
+
+    #[error_code]
+    pub enum MyError {
+        InsufficientFunds,
+    }
+
+    #[test]
+    fn decode_instruction_errors() {
+        let custom = |code| TransactionError::InstructionError(1, InstructionError::Custom(code));
+
+        assert!(matches!(
+            decode_error::<MyError>(&custom(6000)),
+            Some(DecodedError::Program(MyError::InsufficientFunds))
+        ));
+        assert!(matches!(
+            decode_error::<MyError>(&custom(2000)),
+            Some(DecodedError::Anchor(ErrorCode::ConstraintMut))
+        ));
+        assert!(decode_error::<MyError>(&custom(1)).is_none());
+        assert!(decode_error::<MyError>(&TransactionError::AccountInUse).is_none());
+
+        let e = ClientError::SimulationError(custom(6000));
+        assert!(matches!(
+            e.decode_error::<MyError>(),
+            Some(DecodedError::Program(MyError::InsufficientFunds))
+        ));
+    }
+
+    #[test]
+    fn send_with_policy_outcomes() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let ix = Instruction::new_with_bytes(program.id(), &[], vec![]);
+        let policy = SendPolicy::default();
+
+        futures::executor::block_on(async {
+            let outcome = program
+                .request()
+                .instruction(ix.clone())
+                .send_with_policy_internal(&policy)
+                .await
+                .unwrap();
+            assert!(matches!(outcome, SendOutcome::Confirmed(_)));
+
+            // Preflight failures are decoded
+            let log = "Program log: AnchorError occurred. Error Code: InsufficientFunds. Error Number: 6000. Error Message: Not enough funds.";
+            transport.set_simulation(RpcSimulateTransactionResult {
+                err: Some(
+                    TransactionError::InstructionError(
+                        0,
+                        solana_instruction::error::InstructionError::Custom(6000),
+                    )
+                    .into(),
+                ),
+                logs: Some(vec![log.into()]),
+                ..transport
+                    .simulate_transaction(&VersionedTransaction::default(), Default::default())
+                    .await
+                    .unwrap()
+            });
+            let outcome = program
+                .request()
+                .instruction(ix)
+                .send_with_policy_internal(&policy)
+                .await
+                .unwrap();
+            let SendOutcome::Failed(failure) = outcome else {
+                panic!("Expected a failure, got {outcome:?}");
+            };
+            assert!(matches!(
+                failure.decode_error::<MyError>(),
+                Some(DecodedError::Program(MyError::InsufficientFunds))
+            ));
+            assert_eq!(failure.anchor_error.unwrap().name, "InsufficientFunds");
+            assert_eq!(transport.sent_transactions().len(), 1);
+        });
+    }
+
+    #[test]
+    fn send_with_policy_unconfirmed() {
+        use solana_keypair::Keypair;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = Client::new(Cluster::Localnet, Arc::new(Keypair::new()));
+        let program = client
+            .program_with_transport(Pubkey::new_unique(), transport.clone())
+            .unwrap();
+        let ix = Instruction::new_with_bytes(program.id(), &[], vec![]);
+        let policy = SendPolicy {
+            rebroadcast_interval: Duration::from_millis(10),
+            max_retries: 1,
+            ..SendPolicy::default()
+        };
+        transport.set_drop_transactions(true);
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            // Transactions with a durable nonce don't expire
+            let outcome = program
+                .request()
+                .instruction(ix.clone())
+                .durable_nonce_with_value(Pubkey::new_unique(), program.payer(), Hash::new_unique())
+                .send_with_policy_internal(&policy)
+                .await
+                .unwrap();
+            assert!(matches!(outcome, SendOutcome::RetriesExhausted(_)));
+            assert_eq!(transport.sent_transactions().len(), 2);
+
+            // The transaction is polled without being resent until its blockhash expires
+            let expire = {
+                let transport = transport.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_millis(100));
+                    // Past the last valid block height of the blockhashes of the mock
+                    transport.set_slot(1000);
+                })
+            };
+            let outcome = program
+                .request()
+                .instruction(ix)
+                .send_with_policy_internal(&policy)
+                .await
+                .unwrap();
+            expire.join().unwrap();
+            assert!(matches!(outcome, SendOutcome::Expired(_)));
+            assert_eq!(transport.sent_transactions().len(), 4);
+        });
+    }
+
+    #[test]
+    fn add_offline_signatures() {
+        use solana_keypair::Keypair;
+        use solana_signer::Signer;
+
+        let payer = Keypair::new();
+        let custodian = Keypair::new();
+        let ix = system_instruction::transfer(&custodian.pubkey(), &payer.pubkey(), 1);
+        let message =
+            Message::new_with_blockhash(&[ix], Some(&payer.pubkey()), &Hash::new_unique());
+        let mut tx = Transaction::new_unsigned(message);
+        tx.try_partial_sign(&[&payer], tx.message.recent_blockhash)
+            .unwrap();
+        assert!(!tx.is_signed());
+
+        // Signatures of other signers or over other messages are rejected
+        let other = Keypair::new();
+        let signature = other.sign_message(&tx.message_data());
+        assert!(add_signatures(&mut tx, &[(other.pubkey(), signature)]).is_err());
+        let signature = custodian.sign_message(b"other message");
+        assert!(add_signatures(&mut tx, &[(custodian.pubkey(), signature)]).is_err());
+        assert!(!tx.is_signed());
+
+        let signature = custodian.sign_message(&tx.message_data());
+        add_signatures(&mut tx, &[(custodian.pubkey(), signature)]).unwrap();
+        assert!(tx.is_signed());
+    }
 }
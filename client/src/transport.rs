@@ -0,0 +1,1030 @@
+//! Transports the client sends its RPC requests and subscriptions through.
+//!
+//! [`RpcTransport`] talks to a cluster with the Solana RPC and PubSub clients, and is the default
+//! transport of [`Program`](crate::Program). [`MockTransport`] serves everything from memory,
+//! which allows testing off-chain code against the client without a validator.
+
+use crate::{ClientError, UnsubscribeFn};
+use futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream},
+    FutureExt, StreamExt,
+};
+use solana_account::Account;
+use solana_account_decoder::{encode_ui_account, UiAccount, UiAccountEncoding};
+use solana_commitment_config::CommitmentConfig;
+use solana_program::hash::Hash;
+use solana_pubsub_client::nonblocking::pubsub_client::{PubsubClient, PubsubClientResult};
+use solana_rpc_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use solana_rpc_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_rpc_client_api::{
+    client_error::{Error as SolanaClientError, ErrorKind as SolanaClientErrorKind},
+    config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcSimulateTransactionConfig, RpcTransactionConfig, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
+    },
+    custom_error::JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+    filter::RpcFilterType,
+    request::{RpcError, RpcResponseErrorData},
+    response::{
+        Response as RpcResponse, RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount,
+        RpcLogsResponse, RpcPrioritizationFee, RpcResponseContext, RpcSimulateTransactionResult,
+    },
+};
+use solana_signature::Signature;
+use solana_transaction::versioned::VersionedTransaction;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionConfirmationStatus, TransactionStatus,
+    UiLoadedAddresses,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{mpsc, oneshot};
+
+/// Number of blocks a blockhash stays valid for.
+const MAX_PROCESSING_AGE: u64 = 150;
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+
+/// Future returned by the requests of a [`Transport`].
+pub type TransportFuture<'a, T> = BoxFuture<'a, Result<T, ClientError>>;
+
+/// Notifications of a subscription, along with the function to unsubscribe with.
+///
+/// The notifications end when the subscription is closed, e.g. after unsubscribing or when the
+/// connection is lost.
+pub type Subscription<T> = (BoxStream<'static, T>, UnsubscribeFn);
+
+/// Requests and subscriptions the client makes to a cluster.
+///
+/// The methods mirror the ones of the Solana RPC and PubSub clients.
+pub trait Transport: Send + Sync {
+    /// Commitment the requests are made with by default.
+    fn commitment(&self) -> CommitmentConfig;
+
+    /// RPC client the requests are made with, if the transport is backed by one.
+    fn rpc_client(&self) -> Option<&AsyncRpcClient> {
+        None
+    }
+
+    fn get_account<'a>(
+        &'a self,
+        pubkey: &'a Pubkey,
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Option<Account>>;
+
+    fn get_multiple_accounts<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Vec<Option<Account>>>;
+
+    fn get_program_accounts<'a>(
+        &'a self,
+        program_id: &'a Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'a, Vec<(Pubkey, UiAccount)>>;
+
+    fn get_latest_blockhash(&self) -> TransportFuture<'_, Hash>;
+
+    /// Latest blockhash, along with the last block height at which it's valid.
+    fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'_, (Hash, u64)>;
+
+    fn get_slot(&self, commitment: CommitmentConfig) -> TransportFuture<'_, u64>;
+
+    fn get_block_height(&self, commitment: CommitmentConfig) -> TransportFuture<'_, u64>;
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        addresses: &'a [Pubkey],
+    ) -> TransportFuture<'a, Vec<RpcPrioritizationFee>>;
+
+    fn get_signatures_for_address<'a>(
+        &'a self,
+        address: &'a Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> TransportFuture<'a, Vec<RpcConfirmedTransactionStatusWithSignature>>;
+
+    fn get_transaction<'a>(
+        &'a self,
+        signature: &'a Signature,
+        config: RpcTransactionConfig,
+    ) -> TransportFuture<'a, EncodedConfirmedTransactionWithStatusMeta>;
+
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> TransportFuture<'a, Vec<Option<TransactionStatus>>>;
+
+    /// Send the transaction without waiting for its confirmation.
+    fn send_transaction_with_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature>;
+
+    fn send_and_confirm_transaction<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+    ) -> TransportFuture<'a, Signature>;
+
+    /// Send and confirm the transaction, showing the progress on the terminal if supported.
+    fn send_and_confirm_transaction_with_spinner_and_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        commitment: CommitmentConfig,
+        config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature>;
+
+    fn simulate_transaction<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> TransportFuture<'a, RpcSimulateTransactionResult>;
+
+    fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcLogsResponse>>>;
+
+    fn account_subscribe(
+        &self,
+        pubkey: Pubkey,
+        config: RpcAccountInfoConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<UiAccount>>>;
+
+    fn program_subscribe(
+        &self,
+        program_id: Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcKeyedAccount>>>;
+}
+
+/// Transport of a cluster's RPC and PubSub endpoints.
+///
+/// Each subscription is made over its own PubSub connection, so that a lost connection only ends
+/// the subscriptions that were using it.
+pub struct RpcTransport {
+    rpc_client: AsyncRpcClient,
+    ws_url: String,
+}
+
+impl RpcTransport {
+    pub fn new(rpc_client: AsyncRpcClient, ws_url: impl Into<String>) -> Self {
+        Self {
+            rpc_client,
+            ws_url: ws_url.into(),
+        }
+    }
+
+    pub fn rpc_client(&self) -> &AsyncRpcClient {
+        &self.rpc_client
+    }
+
+    /// Make a subscription on a dedicated connection, forwarding its notifications until it's
+    /// closed.
+    fn subscribe<T, F>(&self, subscribe: F) -> TransportFuture<'_, Subscription<T>>
+    where
+        T: Send + 'static,
+        F: for<'c> FnOnce(
+                &'c PubsubClient,
+            )
+                -> BoxFuture<'c, PubsubClientResult<(BoxStream<'c, T>, UnsubscribeFn)>>
+            + Send
+            + 'static,
+    {
+        let ws_url = self.ws_url.clone();
+        async move {
+            let client = PubsubClient::new(&ws_url).await.map_err(Box::new)?;
+            let (subscribed_tx, subscribed_rx) = oneshot::channel();
+            let (tx, rx) = mpsc::unbounded_channel();
+
+            tokio::spawn(async move {
+                let (mut notifications, unsubscribe) = match subscribe(&client).await {
+                    Ok(subscription) => subscription,
+                    Err(e) => {
+                        let _ = subscribed_tx.send(Err(e));
+                        return;
+                    }
+                };
+                if subscribed_tx.send(Ok(unsubscribe)).is_err() {
+                    return;
+                }
+
+                while let Some(notification) = notifications.next().await {
+                    if tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let unsubscribe = subscribed_rx
+                .await
+                .map_err(crate::unsubscribe_failed)?
+                .map_err(Box::new)?;
+            let notifications = stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|notification| (notification, rx))
+            });
+
+            Ok((notifications.boxed(), unsubscribe))
+        }
+        .boxed()
+    }
+}
+
+impl Transport for RpcTransport {
+    fn commitment(&self) -> CommitmentConfig {
+        self.rpc_client.commitment()
+    }
+
+    fn rpc_client(&self) -> Option<&AsyncRpcClient> {
+        Some(&self.rpc_client)
+    }
+
+    fn get_account<'a>(
+        &'a self,
+        pubkey: &'a Pubkey,
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Option<Account>> {
+        async move {
+            self.rpc_client
+                .get_account_with_commitment(pubkey, commitment)
+                .await
+                .map(|response| response.value)
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_multiple_accounts<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Vec<Option<Account>>> {
+        async move {
+            self.rpc_client
+                .get_multiple_accounts_with_commitment(pubkeys, commitment)
+                .await
+                .map(|response| response.value)
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_program_accounts<'a>(
+        &'a self,
+        program_id: &'a Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'a, Vec<(Pubkey, UiAccount)>> {
+        async move {
+            self.rpc_client
+                .get_program_ui_accounts_with_config(program_id, config)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_latest_blockhash(&self) -> TransportFuture<'_, Hash> {
+        async move {
+            self.rpc_client
+                .get_latest_blockhash()
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> TransportFuture<'_, (Hash, u64)> {
+        async move {
+            self.rpc_client
+                .get_latest_blockhash_with_commitment(commitment)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_slot(&self, commitment: CommitmentConfig) -> TransportFuture<'_, u64> {
+        async move {
+            self.rpc_client
+                .get_slot_with_commitment(commitment)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_block_height(&self, commitment: CommitmentConfig) -> TransportFuture<'_, u64> {
+        async move {
+            self.rpc_client
+                .get_block_height_with_commitment(commitment)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        addresses: &'a [Pubkey],
+    ) -> TransportFuture<'a, Vec<RpcPrioritizationFee>> {
+        async move {
+            self.rpc_client
+                .get_recent_prioritization_fees(addresses)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_signatures_for_address<'a>(
+        &'a self,
+        address: &'a Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> TransportFuture<'a, Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        async move {
+            self.rpc_client
+                .get_signatures_for_address_with_config(address, config)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_transaction<'a>(
+        &'a self,
+        signature: &'a Signature,
+        config: RpcTransactionConfig,
+    ) -> TransportFuture<'a, EncodedConfirmedTransactionWithStatusMeta> {
+        async move {
+            self.rpc_client
+                .get_transaction_with_config(signature, config)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> TransportFuture<'a, Vec<Option<TransactionStatus>>> {
+        async move {
+            self.rpc_client
+                .get_signature_statuses(signatures)
+                .await
+                .map(|response| response.value)
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn send_transaction_with_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature> {
+        async move {
+            self.rpc_client
+                .send_transaction_with_config(transaction, config)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn send_and_confirm_transaction<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+    ) -> TransportFuture<'a, Signature> {
+        async move {
+            self.rpc_client
+                .send_and_confirm_transaction(transaction)
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn send_and_confirm_transaction_with_spinner_and_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        commitment: CommitmentConfig,
+        config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature> {
+        async move {
+            self.rpc_client
+                .send_and_confirm_transaction_with_spinner_and_config(
+                    transaction,
+                    commitment,
+                    config,
+                )
+                .await
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn simulate_transaction<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> TransportFuture<'a, RpcSimulateTransactionResult> {
+        async move {
+            self.rpc_client
+                .simulate_transaction_with_config(transaction, config)
+                .await
+                .map(|response| response.value)
+                .map_err(|e| Box::new(e).into())
+        }
+        .boxed()
+    }
+
+    fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcLogsResponse>>> {
+        self.subscribe(move |client| client.logs_subscribe(filter, config).boxed())
+    }
+
+    fn account_subscribe(
+        &self,
+        pubkey: Pubkey,
+        config: RpcAccountInfoConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<UiAccount>>> {
+        self.subscribe(move |client| {
+            async move { client.account_subscribe(&pubkey, Some(config)).await }.boxed()
+        })
+    }
+
+    fn program_subscribe(
+        &self,
+        program_id: Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcKeyedAccount>>> {
+        self.subscribe(move |client| {
+            async move { client.program_subscribe(&program_id, Some(config)).await }.boxed()
+        })
+    }
+}
+
+/// In-memory transport, e.g. to unit test the off-chain code using the client.
+///
+/// It serves the accounts set with [`Self::set_account`], records the transactions it's sent
+/// instead of executing them, and replays the logs pushed with [`Self::push_logs`] to the logs
+/// subscriptions. Setting an account notifies the account and program subscriptions watching it.
+///
+/// # Example
+///
+/// ```ignore
+/// let transport = Arc::new(MockTransport::new());
+/// transport.set_account(address, account);
+///
+/// let program = client.program_with_transport(my_program::ID, transport.clone())?;
+/// let my_account: MyAccount = program.account(address)?;
+///
+/// program.request().args(instruction::Initialize {}).send()?;
+/// assert_eq!(transport.sent_transactions().len(), 1);
+/// ```
+#[derive(Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    accounts: HashMap<Pubkey, Account>,
+    transactions: Vec<VersionedTransaction>,
+    history: Vec<MockTransaction>,
+    logs: Vec<RpcLogsResponse>,
+    simulation: Option<RpcSimulateTransactionResult>,
+    /// Whether the transactions sent are dropped instead of confirmed
+    drop_transactions: bool,
+    dropped: Vec<Signature>,
+    slot: u64,
+    next_subscription: u64,
+    subscriptions: HashMap<u64, MockSubscription>,
+}
+
+/// Confirmed transaction of the mock's history.
+struct MockTransaction {
+    signature: Signature,
+    account_keys: Vec<Pubkey>,
+    transaction: EncodedConfirmedTransactionWithStatusMeta,
+}
+
+enum MockSubscription {
+    Logs(
+        RpcTransactionLogsFilter,
+        mpsc::UnboundedSender<RpcResponse<RpcLogsResponse>>,
+    ),
+    Account(Pubkey, mpsc::UnboundedSender<RpcResponse<UiAccount>>),
+    Program(
+        Pubkey,
+        Vec<RpcFilterType>,
+        mpsc::UnboundedSender<RpcResponse<RpcKeyedAccount>>,
+    ),
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the account at `pubkey`, notifying the subscriptions watching it.
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        let mut state = self.state();
+        let context = state.context();
+        let ui_account =
+            || encode_ui_account(&pubkey, &account, UiAccountEncoding::Base64, None, None);
+        for subscription in state.subscriptions.values() {
+            match subscription {
+                MockSubscription::Account(address, tx) if *address == pubkey => {
+                    let _ = tx.send(RpcResponse {
+                        context: context.clone(),
+                        value: ui_account(),
+                    });
+                }
+                MockSubscription::Program(program_id, filters, tx)
+                    if *program_id == account.owner && filters_match(filters, &account) =>
+                {
+                    let _ = tx.send(RpcResponse {
+                        context: context.clone(),
+                        value: RpcKeyedAccount {
+                            pubkey: pubkey.to_string(),
+                            account: ui_account(),
+                        },
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        state.accounts.insert(pubkey, account);
+    }
+
+    /// Remove the account at `pubkey`, as if it was closed.
+    pub fn remove_account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.state().accounts.remove(pubkey)
+    }
+
+    /// End every subscription, as if the connection was lost.
+    pub fn disconnect(&self) {
+        self.state().subscriptions.clear();
+    }
+
+    /// Push the logs of a transaction, notifying the logs subscriptions that mention it.
+    ///
+    /// The logs are also replayed to the logs subscriptions made afterwards.
+    pub fn push_logs(&self, signature: Signature, logs: Vec<String>) {
+        self.state().push_logs(RpcLogsResponse {
+            signature: signature.to_string(),
+            err: None,
+            logs,
+        });
+    }
+
+    /// Push a confirmed transaction to the history, e.g. to replay the events of a program.
+    ///
+    /// The transaction is returned by `getTransaction` and `getSignaturesForAddress`, and its logs
+    /// are pushed as with [`MockTransport::push_logs`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transaction isn't encoded with a binary encoding.
+    pub fn push_transaction(&self, transaction: EncodedConfirmedTransactionWithStatusMeta) {
+        let decoded = transaction
+            .transaction
+            .transaction
+            .decode()
+            .expect("transaction must have a binary encoding");
+        let signature = decoded.signatures.first().copied().unwrap_or_default();
+        let mut account_keys = decoded.message.static_account_keys().to_vec();
+
+        let mut state = self.state();
+        if let Some(meta) = &transaction.transaction.meta {
+            let loaded_addresses: Option<&UiLoadedAddresses> =
+                meta.loaded_addresses.as_ref().into();
+            if let Some(loaded_addresses) = loaded_addresses {
+                account_keys.extend(
+                    loaded_addresses
+                        .writable
+                        .iter()
+                        .chain(&loaded_addresses.readonly)
+                        .filter_map(|key| key.parse::<Pubkey>().ok()),
+                );
+            }
+
+            let logs: Option<&Vec<String>> = meta.log_messages.as_ref().into();
+            if let Some(logs) = logs {
+                state.push_logs(RpcLogsResponse {
+                    signature: signature.to_string(),
+                    err: meta.err.clone(),
+                    logs: logs.clone(),
+                });
+            }
+        }
+
+        state.history.push(MockTransaction {
+            signature,
+            account_keys,
+            transaction,
+        });
+    }
+
+    /// Set the result of the transaction simulations.
+    ///
+    /// Simulations succeed without logs or consumed compute units by default.
+    pub fn set_simulation(&self, result: RpcSimulateTransactionResult) {
+        self.state().simulation = Some(result);
+    }
+
+    /// Drop the transactions sent afterwards instead of confirming them, e.g. to let them
+    /// expire.
+    pub fn set_drop_transactions(&self, drop_transactions: bool) {
+        self.state().drop_transactions = drop_transactions;
+    }
+
+    /// Set the slot of the cluster, which is zero by default.
+    pub fn set_slot(&self, slot: u64) {
+        self.state().slot = slot;
+    }
+
+    /// Transactions sent through the transport, in the order they were sent.
+    pub fn sent_transactions(&self) -> Vec<VersionedTransaction> {
+        self.state().transactions.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn error<T>(message: String) -> TransportFuture<'static, T> {
+        let kind = SolanaClientErrorKind::Custom(message);
+        let e = ClientError::SolanaClientError(Box::new(SolanaClientError::from(kind)));
+        async move { Err(e) }.boxed()
+    }
+
+    /// Register a subscription, whose notifications end once unsubscribed.
+    fn subscribe<T: Send + 'static>(
+        &self,
+        subscription: impl FnOnce(mpsc::UnboundedSender<T>) -> MockSubscription,
+    ) -> Subscription<T> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut state = self.state();
+        let id = state.next_subscription;
+        state.next_subscription += 1;
+        state.subscriptions.insert(id, subscription(tx));
+
+        let state = Arc::downgrade(&self.state);
+        let unsubscribe: UnsubscribeFn = Box::new(move || {
+            async move {
+                // Dropping the sender ends the notifications
+                if let Some(state) = state.upgrade() {
+                    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                    state.subscriptions.remove(&id);
+                }
+            }
+            .boxed()
+        });
+        let notifications = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|notification| (notification, rx))
+        });
+
+        (notifications.boxed(), unsubscribe)
+    }
+}
+
+impl MockState {
+    fn context(&self) -> RpcResponseContext {
+        RpcResponseContext::new(self.slot)
+    }
+
+    /// Notify the logs subscriptions that mention the logs, and keep them for the later ones.
+    fn push_logs(&mut self, logs: RpcLogsResponse) {
+        let context = self.context();
+        for subscription in self.subscriptions.values() {
+            if let MockSubscription::Logs(filter, tx) = subscription {
+                if logs_match(filter, &logs) {
+                    let _ = tx.send(RpcResponse {
+                        context: context.clone(),
+                        value: logs.clone(),
+                    });
+                }
+            }
+        }
+
+        self.logs.push(logs);
+    }
+}
+
+/// Whether the account passes all the `getProgramAccounts` filters.
+fn filters_match(filters: &[RpcFilterType], account: &Account) -> bool {
+    filters.iter().all(|filter| match filter {
+        RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+        RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(&account.data),
+        RpcFilterType::TokenAccountState => false,
+    })
+}
+
+/// Whether the logs pass the `logsSubscribe` filter.
+fn logs_match(filter: &RpcTransactionLogsFilter, logs: &RpcLogsResponse) -> bool {
+    match filter {
+        RpcTransactionLogsFilter::Mentions(addresses) => addresses
+            .iter()
+            .any(|address| logs.logs.iter().any(|log| log.contains(address.as_str()))),
+        _ => true,
+    }
+}
+
+impl Transport for MockTransport {
+    fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::default()
+    }
+
+    fn get_account<'a>(
+        &'a self,
+        pubkey: &'a Pubkey,
+        _commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Option<Account>> {
+        let account = self.state().accounts.get(pubkey).cloned();
+        async move { Ok(account) }.boxed()
+    }
+
+    fn get_multiple_accounts<'a>(
+        &'a self,
+        pubkeys: &'a [Pubkey],
+        _commitment: CommitmentConfig,
+    ) -> TransportFuture<'a, Vec<Option<Account>>> {
+        let state = self.state();
+        let accounts = pubkeys
+            .iter()
+            .map(|pubkey| state.accounts.get(pubkey).cloned())
+            .collect();
+        async move { Ok(accounts) }.boxed()
+    }
+
+    fn get_program_accounts<'a>(
+        &'a self,
+        program_id: &'a Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'a, Vec<(Pubkey, UiAccount)>> {
+        let filters = config.filters.unwrap_or_default();
+        let encoding = config
+            .account_config
+            .encoding
+            .unwrap_or(UiAccountEncoding::Base64);
+        let accounts = self
+            .state()
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *program_id)
+            .filter(|(_, account)| filters_match(&filters, account))
+            .map(|(pubkey, account)| {
+                let ui_account = encode_ui_account(pubkey, account, encoding, None, None);
+                (*pubkey, ui_account)
+            })
+            .collect();
+        async move { Ok(accounts) }.boxed()
+    }
+
+    fn get_latest_blockhash(&self) -> TransportFuture<'_, Hash> {
+        async move { Ok(Hash::default()) }.boxed()
+    }
+
+    fn get_latest_blockhash_with_commitment(
+        &self,
+        _commitment: CommitmentConfig,
+    ) -> TransportFuture<'_, (Hash, u64)> {
+        let last_valid_block_height = self.state().slot + MAX_PROCESSING_AGE;
+        async move { Ok((Hash::default(), last_valid_block_height)) }.boxed()
+    }
+
+    fn get_slot(&self, _commitment: CommitmentConfig) -> TransportFuture<'_, u64> {
+        let slot = self.state().slot;
+        async move { Ok(slot) }.boxed()
+    }
+
+    fn get_block_height(&self, _commitment: CommitmentConfig) -> TransportFuture<'_, u64> {
+        // Slots are never skipped by the mock
+        self.get_slot(CommitmentConfig::default())
+    }
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        _addresses: &'a [Pubkey],
+    ) -> TransportFuture<'a, Vec<RpcPrioritizationFee>> {
+        async move { Ok(vec![]) }.boxed()
+    }
+
+    /// Signatures of the pushed transactions that mention `address`, newest first.
+    fn get_signatures_for_address<'a>(
+        &'a self,
+        address: &'a Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> TransportFuture<'a, Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        let state = self.state();
+        let newest_first = state.history.iter().rev();
+        let signatures = match config.before {
+            Some(before) => newest_first
+                .skip_while(|tx| tx.signature != before)
+                .skip(1)
+                .collect::<Vec<_>>(),
+            None => newest_first.collect(),
+        }
+        .into_iter()
+        .take_while(|tx| Some(tx.signature) != config.until)
+        .filter(|tx| tx.account_keys.contains(address))
+        .take(config.limit.unwrap_or(1000))
+        .map(|tx| RpcConfirmedTransactionStatusWithSignature {
+            signature: tx.signature.to_string(),
+            slot: tx.transaction.slot,
+            err: tx
+                .transaction
+                .transaction
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.err.clone()),
+            memo: None,
+            block_time: tx.transaction.block_time,
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        })
+        .collect();
+        async move { Ok(signatures) }.boxed()
+    }
+
+    /// Pushed transaction with the given signature.
+    fn get_transaction<'a>(
+        &'a self,
+        signature: &'a Signature,
+        _config: RpcTransactionConfig,
+    ) -> TransportFuture<'a, EncodedConfirmedTransactionWithStatusMeta> {
+        let transaction = self
+            .state()
+            .history
+            .iter()
+            .find(|tx| tx.signature == *signature)
+            .map(|tx| EncodedConfirmedTransactionWithStatusMeta {
+                slot: tx.transaction.slot,
+                transaction: tx.transaction.transaction.clone(),
+                block_time: tx.transaction.block_time,
+            });
+        match transaction {
+            Some(transaction) => async move { Ok(transaction) }.boxed(),
+            None => Self::error(format!("Transaction {signature} not found")),
+        }
+    }
+
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> TransportFuture<'a, Vec<Option<TransactionStatus>>> {
+        let state = self.state();
+        let statuses = signatures
+            .iter()
+            .map(|signature| {
+                let is_sent = state
+                    .transactions
+                    .iter()
+                    .any(|tx| tx.signatures.first() == Some(signature));
+                (is_sent && !state.dropped.contains(signature)).then(|| TransactionStatus {
+                    slot: state.slot,
+                    confirmations: None,
+                    status: Ok(()),
+                    err: None,
+                    confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+                })
+            })
+            .collect();
+        async move { Ok(statuses) }.boxed()
+    }
+
+    /// Record the transaction, failing the preflight check if the simulation is set to fail.
+    fn send_transaction_with_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature> {
+        let mut state = self.state();
+        let failed_simulation = state
+            .simulation
+            .clone()
+            .filter(|simulation| !config.skip_preflight && simulation.err.is_some());
+        if let Some(simulation) = failed_simulation {
+            let kind = SolanaClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+                message: "Transaction simulation failed".into(),
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+            });
+            let e = ClientError::SolanaClientError(Box::new(SolanaClientError::from(kind)));
+            return async move { Err(e) }.boxed();
+        }
+
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        state.transactions.push(transaction.clone());
+        if state.drop_transactions {
+            state.dropped.push(signature);
+        }
+        async move { Ok(signature) }.boxed()
+    }
+
+    fn send_and_confirm_transaction<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+    ) -> TransportFuture<'a, Signature> {
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        self.state().transactions.push(transaction.clone());
+        async move { Ok(signature) }.boxed()
+    }
+
+    fn send_and_confirm_transaction_with_spinner_and_config<'a>(
+        &'a self,
+        transaction: &'a VersionedTransaction,
+        _commitment: CommitmentConfig,
+        _config: RpcSendTransactionConfig,
+    ) -> TransportFuture<'a, Signature> {
+        self.send_and_confirm_transaction(transaction)
+    }
+
+    fn simulate_transaction<'a>(
+        &'a self,
+        _transaction: &'a VersionedTransaction,
+        _config: RpcSimulateTransactionConfig,
+    ) -> TransportFuture<'a, RpcSimulateTransactionResult> {
+        let result = self
+            .state()
+            .simulation
+            .clone()
+            .unwrap_or(RpcSimulateTransactionResult {
+                err: None,
+                logs: Some(vec![]),
+                accounts: None,
+                units_consumed: Some(0),
+                loaded_accounts_data_size: None,
+                return_data: None,
+                inner_instructions: None,
+                replacement_blockhash: None,
+                fee: None,
+                pre_balances: None,
+                post_balances: None,
+                pre_token_balances: None,
+                post_token_balances: None,
+                loaded_addresses: None,
+            });
+        async move { Ok(result) }.boxed()
+    }
+
+    fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        _config: RpcTransactionLogsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcLogsResponse>>> {
+        // Replay the logs pushed so far, before the ones pushed afterwards
+        let replayed: Vec<_> = {
+            let state = self.state();
+            state
+                .logs
+                .iter()
+                .filter(|logs| logs_match(&filter, logs))
+                .map(|logs| RpcResponse {
+                    context: state.context(),
+                    value: logs.clone(),
+                })
+                .collect()
+        };
+        let (notifications, unsubscribe) = self.subscribe(|tx| MockSubscription::Logs(filter, tx));
+        let notifications = stream::iter(replayed).chain(notifications).boxed();
+        async move { Ok((notifications, unsubscribe)) }.boxed()
+    }
+
+    fn account_subscribe(
+        &self,
+        pubkey: Pubkey,
+        _config: RpcAccountInfoConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<UiAccount>>> {
+        let subscription = self.subscribe(|tx| MockSubscription::Account(pubkey, tx));
+        async move { Ok(subscription) }.boxed()
+    }
+
+    fn program_subscribe(
+        &self,
+        program_id: Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> TransportFuture<'_, Subscription<RpcResponse<RpcKeyedAccount>>> {
+        let filters = config.filters.unwrap_or_default();
+        let subscription = self.subscribe(|tx| MockSubscription::Program(program_id, filters, tx));
+        async move { Ok(subscription) }.boxed()
+    }
+}
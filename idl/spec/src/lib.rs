@@ -72,6 +72,7 @@ pub struct IdlInstruction {
 #[serde(untagged)]
 pub enum IdlInstructionAccountItem {
     Composite(IdlInstructionAccounts),
+    Remaining(IdlInstructionRemainingAccounts),
     Single(IdlInstructionAccount),
 }
 
@@ -100,6 +101,30 @@ pub struct IdlInstructionAccounts {
     pub accounts: Vec<IdlInstructionAccountItem>,
 }
 
+/// Variable-length list of accounts that takes the rest of the instruction's accounts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlInstructionRemainingAccounts {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub docs: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub writable: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub signer: bool,
+    /// Name of the account type of every account, if known
+    #[serde(skip_serializing_if = "is_default")]
+    pub account: Option<String>,
+    pub remaining: IdlRemainingBounds,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlRemainingBounds {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub min: u32,
+    #[serde(skip_serializing_if = "is_default")]
+    pub max: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct IdlPda {
     pub seeds: Vec<IdlSeed>,
@@ -0,0 +1,818 @@
+//! Encode and decode JSON values with the types of an IDL.
+//!
+//! Values are represented as follows:
+//!
+//! - Integers up to 64 bits, floats and booleans are JSON numbers and booleans. 128-bit integers
+//!   are strings, and are also accepted as numbers.
+//! - Public keys are base58 strings.
+//! - `Option`s are `null` or their value, vectors, arrays and bytes are arrays.
+//! - Structs are objects, or arrays for tuple structs. Objects must have a key for every field,
+//!   except `Option` fields, which are `null` if omitted, and can't have other keys.
+//! - Enum variants are objects with a single key, the variant name, whose value is an object of
+//!   the named fields, an array of the tuple fields, or an empty object. Variants without fields
+//!   are also accepted as strings.
+//!
+//! Types are Borsh serialized, unless they use the [`IdlSerialization::Bytemuck`] serialization of
+//! zero-copy types, in which case they're laid out in memory according to their [`IdlRepr`].
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+use crate::types::{
+    IdlArrayLen, IdlDefinedFields, IdlField, IdlGenericArg, IdlRepr, IdlSerialization, IdlType,
+    IdlTypeDef, IdlTypeDefGeneric, IdlTypeDefTy,
+};
+
+/// Encoder and decoder of the values of the types defined in an IDL.
+///
+/// # Example
+///
+/// ```ignore
+/// let coder = IdlCoder::new(&idl.types);
+/// let ty = IdlType::Defined { name: "Vault".into(), generics: vec![] };
+///
+/// let value = coder.decode(&ty, &mut &data[8..])?;
+/// assert_eq!(coder.encode(&ty, &value)?, data[8..]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct IdlCoder<'a> {
+    types: &'a [IdlTypeDef],
+}
+
+/// How values are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Borsh,
+    /// Memory layout of zero-copy types
+    Bytemuck,
+}
+
+/// Generic arguments of a defined type, by the name of their parameter.
+#[derive(Debug, Default)]
+struct Generics {
+    types: HashMap<String, IdlType>,
+    consts: HashMap<String, String>,
+}
+
+/// Memory layout of a zero-copy struct.
+struct StructLayout {
+    offsets: Vec<usize>,
+    size: usize,
+    align: usize,
+}
+
+impl<'a> IdlCoder<'a> {
+    pub fn new(types: &'a [IdlTypeDef]) -> Self {
+        Self { types }
+    }
+
+    /// Returns the type definition named `name`.
+    pub fn type_def(&self, name: &str) -> Result<&'a IdlTypeDef> {
+        self.types
+            .iter()
+            .find(|ty| ty.name == name)
+            .ok_or_else(|| anyhow!("Type `{name}` not found"))
+    }
+
+    /// Encode the value of type `ty`.
+    pub fn encode(&self, ty: &IdlType, value: &Value) -> Result<Vec<u8>> {
+        let mut out = vec![];
+        self.encode_into(ty, value, Layout::Borsh, &mut out)?;
+        Ok(out)
+    }
+
+    /// Decode a value of type `ty`, advancing `data` past it.
+    pub fn decode(&self, ty: &IdlType, data: &mut &[u8]) -> Result<Value> {
+        self.decode_from(ty, data, Layout::Borsh)
+    }
+
+    fn encode_into(
+        &self,
+        ty: &IdlType,
+        value: &Value,
+        layout: Layout,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match ty {
+            IdlType::Bool => {
+                let value = value.as_bool().context("Expected a boolean")?;
+                out.push(value.into());
+            }
+            IdlType::U8 => out.push(int(value)?),
+            IdlType::I8 => out.extend(int::<i8>(value)?.to_le_bytes()),
+            IdlType::U16 => out.extend(int::<u16>(value)?.to_le_bytes()),
+            IdlType::I16 => out.extend(int::<i16>(value)?.to_le_bytes()),
+            IdlType::U32 => out.extend(int::<u32>(value)?.to_le_bytes()),
+            IdlType::I32 => out.extend(int::<i32>(value)?.to_le_bytes()),
+            IdlType::U64 => out.extend(int::<u64>(value)?.to_le_bytes()),
+            IdlType::I64 => out.extend(int::<i64>(value)?.to_le_bytes()),
+            IdlType::U128 => out.extend(int::<u128>(value)?.to_le_bytes()),
+            IdlType::I128 => out.extend(int::<i128>(value)?.to_le_bytes()),
+            IdlType::F32 => out.extend((float(value)? as f32).to_le_bytes()),
+            IdlType::F64 => out.extend(float(value)?.to_le_bytes()),
+            IdlType::Pubkey => out.extend(pubkey(value)?),
+            IdlType::String | IdlType::Bytes | IdlType::Option(_) | IdlType::Vec(_)
+                if layout == Layout::Bytemuck =>
+            {
+                bail!("Type `{ty:?}` isn't zero-copy")
+            }
+            IdlType::String => {
+                let value = value.as_str().context("Expected a string")?;
+                out.extend(len_prefix(value.len())?);
+                out.extend(value.as_bytes());
+            }
+            IdlType::Bytes => {
+                let bytes = array(value)?
+                    .iter()
+                    .map(int::<u8>)
+                    .collect::<Result<Vec<_>>>()?;
+                out.extend(len_prefix(bytes.len())?);
+                out.extend(bytes);
+            }
+            IdlType::Option(ty) => match value {
+                Value::Null => out.push(0),
+                _ => {
+                    out.push(1);
+                    self.encode_into(ty, value, layout, out)?;
+                }
+            },
+            IdlType::Vec(ty) => {
+                let values = array(value)?;
+                out.extend(len_prefix(values.len())?);
+                for value in values {
+                    self.encode_into(ty, value, layout, out)?;
+                }
+            }
+            IdlType::Array(ty, len) => {
+                let len = array_len(len)?;
+                let values = array(value)?;
+                if values.len() != len {
+                    bail!("Expected an array of {len} elements");
+                }
+                for value in values {
+                    self.encode_into(ty, value, layout, out)?;
+                }
+            }
+            IdlType::Defined { name, generics } => {
+                let def = self.type_def(name)?;
+                let generics = Generics::new(def, generics)?;
+                self.encode_defined(def, &generics, value, layout, out)
+                    .with_context(|| format!("Unable to encode `{name}`"))?;
+            }
+            IdlType::Generic(name) => bail!("Unresolved generic `{name}`"),
+            _ => bail!("Type `{ty:?}` isn't supported"),
+        }
+
+        Ok(())
+    }
+
+    fn encode_defined(
+        &self,
+        def: &IdlTypeDef,
+        generics: &Generics,
+        value: &Value,
+        layout: Layout,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let layout = def_layout(def, layout)?;
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                let fields = resolve_fields(fields.as_ref(), generics)?;
+                let values = field_values(&fields, value)?;
+                if layout == Layout::Borsh {
+                    for ((name, ty), value) in fields.iter().zip(values) {
+                        self.encode_into(ty, value, layout, out)
+                            .with_context(|| format!("`{name}`"))?;
+                    }
+                    return Ok(());
+                }
+
+                let tys = fields.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>();
+                let struct_layout = self.struct_layout(def, &tys)?;
+                let start = out.len();
+                for (((name, ty), value), offset) in
+                    fields.iter().zip(values).zip(struct_layout.offsets)
+                {
+                    out.resize(start + offset, 0);
+                    self.encode_into(ty, value, layout, out)
+                        .with_context(|| format!("`{name}`"))?;
+                }
+                out.resize(start + struct_layout.size, 0);
+                Ok(())
+            }
+            IdlTypeDefTy::Enum { .. } if layout == Layout::Bytemuck => {
+                bail!("Zero-copy enums aren't supported")
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                let (name, fields_value) = match value {
+                    Value::String(name) => (name.as_str(), &Value::Null),
+                    Value::Object(object) if object.len() == 1 => {
+                        let (name, value) = object.iter().next().unwrap();
+                        (name.as_str(), value)
+                    }
+                    _ => bail!("Expected an enum variant"),
+                };
+                let (index, variant) = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, variant)| variant.name == name)
+                    .ok_or_else(|| anyhow!("Unknown variant `{name}`"))?;
+
+                let index = u8::try_from(index)
+                    .map_err(|_| anyhow!("Variant `{name}` has an index above 255"))?;
+                out.push(index);
+                let fields = resolve_fields(variant.fields.as_ref(), generics)?;
+                let values = match fields_value {
+                    // Variants without fields may be given as strings
+                    Value::Null if fields.is_empty() => vec![],
+                    value => field_values(&fields, value)?,
+                };
+                for ((name, ty), value) in fields.iter().zip(values) {
+                    self.encode_into(ty, value, layout, out)
+                        .with_context(|| format!("`{name}`"))?;
+                }
+                Ok(())
+            }
+            IdlTypeDefTy::Type { alias } => {
+                self.encode_into(&generics.resolve(alias)?, value, layout, out)
+            }
+        }
+    }
+
+    fn decode_from(&self, ty: &IdlType, data: &mut &[u8], layout: Layout) -> Result<Value> {
+        Ok(match ty {
+            IdlType::Bool => match take::<1>(data)? {
+                [0] => json!(false),
+                [1] => json!(true),
+                [b] => bail!("Invalid boolean {b}"),
+            },
+            IdlType::U8 => json!(u8::from_le_bytes(take(data)?)),
+            IdlType::I8 => json!(i8::from_le_bytes(take(data)?)),
+            IdlType::U16 => json!(u16::from_le_bytes(take(data)?)),
+            IdlType::I16 => json!(i16::from_le_bytes(take(data)?)),
+            IdlType::U32 => json!(u32::from_le_bytes(take(data)?)),
+            IdlType::I32 => json!(i32::from_le_bytes(take(data)?)),
+            IdlType::U64 => json!(u64::from_le_bytes(take(data)?)),
+            IdlType::I64 => json!(i64::from_le_bytes(take(data)?)),
+            IdlType::U128 => json!(u128::from_le_bytes(take(data)?).to_string()),
+            IdlType::I128 => json!(i128::from_le_bytes(take(data)?).to_string()),
+            IdlType::F32 => json!(f32::from_le_bytes(take(data)?)),
+            IdlType::F64 => json!(f64::from_le_bytes(take(data)?)),
+            IdlType::Pubkey => json!(bs58::encode(take::<32>(data)?).into_string()),
+            IdlType::String | IdlType::Bytes | IdlType::Option(_) | IdlType::Vec(_)
+                if layout == Layout::Bytemuck =>
+            {
+                bail!("Type `{ty:?}` isn't zero-copy")
+            }
+            IdlType::String => {
+                let len = u32::from_le_bytes(take(data)?) as usize;
+                let bytes = take_slice(data, len)?;
+                json!(String::from_utf8(bytes.to_vec())?)
+            }
+            IdlType::Bytes => {
+                let len = u32::from_le_bytes(take(data)?) as usize;
+                json!(take_slice(data, len)?)
+            }
+            IdlType::Option(ty) => match take::<1>(data)? {
+                [0] => Value::Null,
+                [1] => self.decode_from(ty, data, layout)?,
+                [b] => bail!("Invalid option tag {b}"),
+            },
+            IdlType::Vec(ty) => {
+                let len = u32::from_le_bytes(take(data)?);
+                (0..len)
+                    .map(|_| self.decode_from(ty, data, layout))
+                    .collect::<Result<_>>()?
+            }
+            IdlType::Array(ty, len) => (0..array_len(len)?)
+                .map(|_| self.decode_from(ty, data, layout))
+                .collect::<Result<_>>()?,
+            IdlType::Defined { name, generics } => {
+                let def = self.type_def(name)?;
+                let generics = Generics::new(def, generics)?;
+                self.decode_defined(def, &generics, data, layout)
+                    .with_context(|| format!("Unable to decode `{name}`"))?
+            }
+            IdlType::Generic(name) => bail!("Unresolved generic `{name}`"),
+            _ => bail!("Type `{ty:?}` isn't supported"),
+        })
+    }
+
+    fn decode_defined(
+        &self,
+        def: &IdlTypeDef,
+        generics: &Generics,
+        data: &mut &[u8],
+        layout: Layout,
+    ) -> Result<Value> {
+        let layout = def_layout(def, layout)?;
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                let resolved = resolve_fields(fields.as_ref(), generics)?;
+                let values = match layout {
+                    Layout::Borsh => resolved
+                        .iter()
+                        .map(|(name, ty)| {
+                            self.decode_from(ty, data, layout)
+                                .with_context(|| format!("`{name}`"))
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    Layout::Bytemuck => {
+                        let tys = resolved
+                            .iter()
+                            .map(|(_, ty)| ty.clone())
+                            .collect::<Vec<_>>();
+                        let struct_layout = self.struct_layout(def, &tys)?;
+                        let start = *data;
+                        let values = resolved
+                            .iter()
+                            .zip(struct_layout.offsets)
+                            .map(|((name, ty), offset)| {
+                                let mut field = start.get(offset..).context("Not enough data")?;
+                                self.decode_from(ty, &mut field, layout)
+                                    .with_context(|| format!("`{name}`"))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        take_slice(data, struct_layout.size)?;
+                        values
+                    }
+                };
+                Ok(fields_value(fields.as_ref(), &resolved, values))
+            }
+            IdlTypeDefTy::Enum { .. } if layout == Layout::Bytemuck => {
+                bail!("Zero-copy enums aren't supported")
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                let [index] = take::<1>(data)?;
+                let variant = variants
+                    .get(index as usize)
+                    .ok_or_else(|| anyhow!("Invalid variant index {index}"))?;
+                let resolved = resolve_fields(variant.fields.as_ref(), generics)?;
+                let values = resolved
+                    .iter()
+                    .map(|(name, ty)| {
+                        self.decode_from(ty, data, layout)
+                            .with_context(|| format!("`{name}`"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let fields = fields_value(variant.fields.as_ref(), &resolved, values);
+                Ok(json!({ variant.name.clone(): fields }))
+            }
+            IdlTypeDefTy::Type { alias } => {
+                self.decode_from(&generics.resolve(alias)?, data, layout)
+            }
+        }
+    }
+
+    /// Memory layout of a zero-copy struct with the given (resolved) field types.
+    fn struct_layout(&self, def: &IdlTypeDef, fields: &[IdlType]) -> Result<StructLayout> {
+        let (packed, min_align) = match &def.repr {
+            None | Some(IdlRepr::Transparent) => (false, None),
+            Some(IdlRepr::C(modifier)) => (modifier.packed, modifier.align),
+            Some(IdlRepr::Rust(modifier)) if modifier.packed => (true, modifier.align),
+            Some(_) => bail!("Layout of `{}` is unspecified", def.name),
+        };
+
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut size = 0usize;
+        let mut align = min_align.unwrap_or(1);
+        for ty in fields {
+            let (field_size, field_align) = self.size_align(ty)?;
+            let field_align = if packed { 1 } else { field_align };
+            size = size.next_multiple_of(field_align);
+            offsets.push(size);
+            size += field_size;
+            align = align.max(field_align);
+        }
+
+        Ok(StructLayout {
+            offsets,
+            size: size.next_multiple_of(align),
+            align,
+        })
+    }
+
+    /// Size and alignment of a zero-copy type.
+    fn size_align(&self, ty: &IdlType) -> Result<(usize, usize)> {
+        Ok(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => (1, 1),
+            IdlType::U16 | IdlType::I16 => (2, 2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => (4, 4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => (8, 8),
+            // 128-bit integers are 8-byte aligned on SBF
+            IdlType::U128 | IdlType::I128 => (16, 8),
+            IdlType::Pubkey => (32, 1),
+            IdlType::Array(ty, len) => {
+                let (size, align) = self.size_align(ty)?;
+                (size * array_len(len)?, align)
+            }
+            IdlType::Defined { name, generics } => {
+                let def = self.type_def(name)?;
+                let generics = Generics::new(def, generics)?;
+                match &def.ty {
+                    IdlTypeDefTy::Struct { fields } => {
+                        let tys = resolve_fields(fields.as_ref(), &generics)?
+                            .into_iter()
+                            .map(|(_, ty)| ty)
+                            .collect::<Vec<_>>();
+                        let layout = self.struct_layout(def, &tys)?;
+                        (layout.size, layout.align)
+                    }
+                    IdlTypeDefTy::Type { alias } => self.size_align(&generics.resolve(alias)?)?,
+                    IdlTypeDefTy::Enum { .. } => bail!("Zero-copy enums aren't supported"),
+                }
+            }
+            _ => bail!("Type `{ty:?}` isn't zero-copy"),
+        })
+    }
+}
+
+impl Generics {
+    /// Generic arguments of `def`, which must already be resolved.
+    fn new(def: &IdlTypeDef, args: &[IdlGenericArg]) -> Result<Self> {
+        if def.generics.len() != args.len() {
+            bail!(
+                "Expected {} generic arguments for `{}`, got {}",
+                def.generics.len(),
+                def.name,
+                args.len()
+            );
+        }
+
+        let mut generics = Self::default();
+        for (param, arg) in def.generics.iter().zip(args) {
+            match (param, arg) {
+                (IdlTypeDefGeneric::Type { name }, IdlGenericArg::Type { ty }) => {
+                    generics.types.insert(name.clone(), ty.clone());
+                }
+                (IdlTypeDefGeneric::Const { name, .. }, IdlGenericArg::Const { value }) => {
+                    generics.consts.insert(name.clone(), value.clone());
+                }
+                _ => bail!("Mismatched generic argument for `{}`", def.name),
+            }
+        }
+
+        Ok(generics)
+    }
+
+    /// Substitute the generic parameters of `ty` with their arguments.
+    fn resolve(&self, ty: &IdlType) -> Result<IdlType> {
+        Ok(match ty {
+            IdlType::Generic(name) => self
+                .types
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Unknown generic `{name}`"))?,
+            IdlType::Option(ty) => IdlType::Option(Box::new(self.resolve(ty)?)),
+            IdlType::Vec(ty) => IdlType::Vec(Box::new(self.resolve(ty)?)),
+            IdlType::Array(ty, len) => {
+                let len = match len {
+                    IdlArrayLen::Generic(name) => IdlArrayLen::Value(self.const_value(name)?),
+                    IdlArrayLen::Value(len) => IdlArrayLen::Value(*len),
+                };
+                IdlType::Array(Box::new(self.resolve(ty)?), len)
+            }
+            IdlType::Defined { name, generics } => IdlType::Defined {
+                name: name.clone(),
+                generics: generics
+                    .iter()
+                    .map(|arg| {
+                        Ok(match arg {
+                            IdlGenericArg::Type { ty } => IdlGenericArg::Type {
+                                ty: self.resolve(ty)?,
+                            },
+                            // Const arguments may be the const parameters of the outer type
+                            IdlGenericArg::Const { value } => IdlGenericArg::Const {
+                                value: self
+                                    .consts
+                                    .get(value)
+                                    .cloned()
+                                    .unwrap_or_else(|| value.clone()),
+                            },
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            _ => ty.clone(),
+        })
+    }
+
+    fn const_value(&self, name: &str) -> Result<usize> {
+        let value = self
+            .consts
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown generic `{name}`"))?;
+        value
+            .parse()
+            .map_err(|_| anyhow!("Invalid array length `{value}`"))
+    }
+}
+
+/// Layout of the values of `def`, where zero-copy types use their memory layout.
+fn def_layout(def: &IdlTypeDef, layout: Layout) -> Result<Layout> {
+    match &def.serialization {
+        IdlSerialization::Borsh => Ok(layout),
+        IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe => Ok(Layout::Bytemuck),
+        IdlSerialization::Custom(serialization) => bail!(
+            "Custom serialization `{serialization}` of `{}` isn't supported",
+            def.name
+        ),
+        _ => bail!("Unknown serialization of `{}`", def.name),
+    }
+}
+
+/// Names (the index for tuple fields) and resolved types of the fields.
+fn resolve_fields(
+    fields: Option<&IdlDefinedFields>,
+    generics: &Generics,
+) -> Result<Vec<(String, IdlType)>> {
+    match fields {
+        None => Ok(vec![]),
+        Some(IdlDefinedFields::Named(fields)) => fields
+            .iter()
+            .map(|IdlField { name, ty, .. }| Ok((name.clone(), generics.resolve(ty)?)))
+            .collect(),
+        Some(IdlDefinedFields::Tuple(tys)) => tys
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| Ok((i.to_string(), generics.resolve(ty)?)))
+            .collect(),
+    }
+}
+
+/// Values of the fields, from an object of the named fields or an array of the tuple fields.
+fn field_values<'v>(fields: &[(String, IdlType)], value: &'v Value) -> Result<Vec<&'v Value>> {
+    match value {
+        Value::Array(values) if values.len() == fields.len() => Ok(values.iter().collect()),
+        Value::Array(_) => bail!("Expected {} fields", fields.len()),
+        Value::Object(object) => {
+            if let Some(key) = object
+                .keys()
+                .find(|key| !fields.iter().any(|(name, _)| name == *key))
+            {
+                bail!("Unknown field `{key}`");
+            }
+            fields
+                .iter()
+                .map(|(name, ty)| match (object.get(name), ty) {
+                    (Some(value), _) => Ok(value),
+                    (None, IdlType::Option(_)) => Ok(&Value::Null),
+                    (None, _) => bail!("Missing field `{name}`"),
+                })
+                .collect()
+        }
+        _ => bail!("Expected an object or an array"),
+    }
+}
+
+/// Value of decoded fields: an object for named fields, an array for tuple fields.
+fn fields_value(
+    fields: Option<&IdlDefinedFields>,
+    resolved: &[(String, IdlType)],
+    values: Vec<Value>,
+) -> Value {
+    match fields {
+        Some(IdlDefinedFields::Tuple(_)) => Value::Array(values),
+        _ => Value::Object(
+            resolved
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(values)
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+fn array_len(len: &IdlArrayLen) -> Result<usize> {
+    match len {
+        IdlArrayLen::Value(len) => Ok(*len),
+        IdlArrayLen::Generic(name) => bail!("Unresolved generic array length `{name}`"),
+    }
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>> {
+    value.as_array().context("Expected an array")
+}
+
+fn len_prefix(len: usize) -> Result<[u8; 4]> {
+    Ok(u32::try_from(len)
+        .map_err(|_| anyhow!("Length {len} exceeds `u32::MAX`"))?
+        .to_le_bytes())
+}
+
+/// Integer of a JSON number, or of a string for the integers exceeding the JSON numbers.
+fn int<T: TryFrom<i128> + TryFrom<u128>>(value: &Value) -> Result<T> {
+    let out_of_range = || anyhow!("Integer {value} out of range");
+    match value {
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => T::try_from(n as u128).map_err(|_| out_of_range()),
+            (_, Some(n)) => T::try_from(n as i128).map_err(|_| out_of_range()),
+            _ => bail!("Expected an integer"),
+        },
+        Value::String(s) => match s.parse::<u128>() {
+            Ok(n) => T::try_from(n).map_err(|_| out_of_range()),
+            Err(_) => {
+                let n = s.parse::<i128>().context("Expected an integer")?;
+                T::try_from(n).map_err(|_| out_of_range())
+            }
+        },
+        _ => bail!("Expected an integer"),
+    }
+}
+
+fn float(value: &Value) -> Result<f64> {
+    value.as_f64().context("Expected a number")
+}
+
+fn pubkey(value: &Value) -> Result<[u8; 32]> {
+    let address = value.as_str().context("Expected a base58 address")?;
+    let mut pubkey = [0; 32];
+    let len = bs58::decode(address)
+        .onto(&mut pubkey)
+        .map_err(|e| anyhow!("Invalid address `{address}`: {e}"))?;
+    if len != pubkey.len() {
+        bail!("Invalid address `{address}`");
+    }
+
+    Ok(pubkey)
+}
+
+fn take<const N: usize>(data: &mut &[u8]) -> Result<[u8; N]> {
+    Ok(take_slice(data, N)?.try_into().unwrap())
+}
+
+fn take_slice<'d>(data: &mut &'d [u8], len: usize) -> Result<&'d [u8]> {
+    if data.len() < len {
+        bail!("Not enough data");
+    }
+    let (taken, rest) = data.split_at(len);
+    *data = rest;
+    Ok(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types() -> Vec<IdlTypeDef> {
+        serde_json::from_value(json!([
+            {
+                "name": "Wrapper",
+                "generics": [
+                    { "kind": "type", "name": "T" },
+                    { "kind": "const", "name": "N", "type": "usize" }
+                ],
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "items", "type": { "array": [{ "generic": "T" }, { "generic": "N" }] } },
+                        { "name": "inner", "type": { "defined": {
+                            "name": "Inner",
+                            "generics": [{ "kind": "type", "type": { "option": { "generic": "T" } } }]
+                        } } }
+                    ]
+                }
+            },
+            {
+                "name": "Inner",
+                "generics": [{ "kind": "type", "name": "U" }],
+                "type": { "kind": "struct", "fields": [{ "generic": "U" }] }
+            },
+            {
+                "name": "Alias",
+                "type": { "kind": "type", "alias": { "defined": {
+                    "name": "Wrapper",
+                    "generics": [
+                        { "kind": "type", "type": "u16" },
+                        { "kind": "const", "value": "2" }
+                    ]
+                } } }
+            },
+            {
+                "name": "ZeroCopy",
+                "serialization": "bytemuck",
+                "repr": { "kind": "c" },
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "flag", "type": "u8" },
+                        { "name": "amount", "type": "u64" },
+                        { "name": "big", "type": "u128" },
+                        { "name": "tail", "type": "u16" }
+                    ]
+                }
+            },
+            {
+                "name": "Packed",
+                "serialization": "bytemuckunsafe",
+                "repr": { "kind": "rust", "packed": true },
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "flag", "type": "u8" },
+                        { "name": "amount", "type": "u64" }
+                    ]
+                }
+            }
+        ]))
+        .unwrap()
+    }
+
+    fn defined(name: &str) -> IdlType {
+        IdlType::Defined {
+            name: name.into(),
+            generics: vec![],
+        }
+    }
+
+    #[test]
+    fn generics() {
+        let types = types();
+        let coder = IdlCoder::new(&types);
+        let value = json!({ "items": [1, 2], "inner": [3] });
+
+        let data = coder.encode(&defined("Alias"), &value).unwrap();
+        assert_eq!(data, [1, 0, 2, 0, 1, 3, 0]);
+        assert_eq!(
+            coder.decode(&defined("Alias"), &mut &data[..]).unwrap(),
+            value
+        );
+
+        // Generic arguments are required
+        assert!(coder.encode(&defined("Wrapper"), &value).is_err());
+    }
+
+    #[test]
+    fn bytemuck() {
+        let types = types();
+        let coder = IdlCoder::new(&types);
+        let value = json!({ "flag": 1, "amount": 2, "big": "3", "tail": 4 });
+
+        let data = coder.encode(&defined("ZeroCopy"), &value).unwrap();
+        let mut expected = vec![1, 0, 0, 0, 0, 0, 0, 0];
+        expected.extend(2u64.to_le_bytes());
+        expected.extend(3u128.to_le_bytes());
+        expected.extend([4, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(data, expected);
+        assert_eq!(
+            coder.decode(&defined("ZeroCopy"), &mut &data[..]).unwrap(),
+            value
+        );
+
+        let value = json!({ "flag": 1, "amount": 2 });
+        let data = coder.encode(&defined("Packed"), &value).unwrap();
+        assert_eq!(data, [1, 2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            coder.decode(&defined("Packed"), &mut &data[..]).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let types: Vec<IdlTypeDef> = serde_json::from_value(json!([
+            {
+                "name": "Account",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "amount", "type": "u64" },
+                        { "name": "memo", "type": { "option": "string" } }
+                    ]
+                }
+            },
+            {
+                "name": "Large",
+                "type": {
+                    "kind": "enum",
+                    "variants": (0..257).map(|i| json!({ "name": format!("V{i}") })).collect::<Vec<_>>()
+                }
+            }
+        ]))
+        .unwrap();
+        let coder = IdlCoder::new(&types);
+
+        // Omitted options are `None`
+        let data = coder
+            .encode(&defined("Account"), &json!({ "amount": 1 }))
+            .unwrap();
+        assert_eq!(data, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(coder
+            .encode(&defined("Account"), &json!({ "memo": "a" }))
+            .is_err());
+        assert!(coder
+            .encode(
+                &defined("Account"),
+                &json!({ "amount": 1, "memo": null, "other": 2 })
+            )
+            .is_err());
+
+        assert_eq!(
+            coder.encode(&defined("Large"), &json!("V255")).unwrap(),
+            [255]
+        );
+        assert!(coder.encode(&defined("Large"), &json!("V256")).is_err());
+    }
+}
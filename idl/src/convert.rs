@@ -27,6 +27,91 @@ pub fn convert_idl(idl: &[u8]) -> Result<Idl> {
     }
 }
 
+/// Key of the hash field that pinned IDL files embed at the top level.
+pub const PINNED_HASH_KEY: &str = "hash";
+
+/// Calculate the SHA-256 hash (hex) of the given [`Idl`].
+///
+/// The hash is based on the IDL's content rather than its formatting, i.e. re-formatting a pinned
+/// IDL file does not change its hash.
+pub fn hash_idl(idl: &Idl) -> Result<String> {
+    hash_idl_value(&serde_json::to_value(idl)?)
+}
+
+/// Calculate the SHA-256 hash (hex) of the JSON value of an IDL, with its object keys sorted.
+///
+/// Pinned IDL files are verified against the hash of their JSON value rather than of the [`Idl`]
+/// they convert to, so that changes to the serialization of [`Idl`] don't invalidate them.
+fn hash_idl_value(value: &serde_json::Value) -> Result<String> {
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+
+    fn canonical(value: &Value) -> Value {
+        match value {
+            Value::Object(obj) => {
+                let mut entries = obj.iter().collect::<Vec<_>>();
+                entries.sort_by_key(|(key, _)| *key);
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key.clone(), canonical(value)))
+                        .collect(),
+                )
+            }
+            Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+            value => value.clone(),
+        }
+    }
+
+    let buf = serde_json::to_vec(&canonical(value))?;
+    Ok(format!("{:x}", Sha256::digest(buf)))
+}
+
+/// Create a pinned IDL file content, which is the given [`Idl`] (pretty-printed JSON) with its
+/// hash embedded under the [`PINNED_HASH_KEY`] field.
+pub fn pin_idl(idl: &Idl) -> Result<String> {
+    #[derive(serde::Serialize)]
+    struct PinnedIdl<'a> {
+        hash: String,
+        #[serde(flatten)]
+        idl: &'a Idl,
+    }
+
+    let pinned = PinnedIdl {
+        hash: hash_idl(idl)?,
+        idl,
+    };
+    serde_json::to_string_pretty(&pinned).map_err(Into::into)
+}
+
+/// Same as [`convert_idl`], but if the IDL is pinned (see [`pin_idl`]), also verify that its
+/// content matches the embedded hash.
+pub fn convert_pinned_idl(idl: &[u8]) -> Result<Idl> {
+    let mut value = serde_json::from_slice::<serde_json::Value>(idl)?;
+    let hash = value
+        .as_object_mut()
+        .and_then(|obj| obj.remove(PINNED_HASH_KEY));
+    let idl = convert_idl(&serde_json::to_vec(&value)?)?;
+    match hash {
+        None => Ok(idl),
+        Some(hash) => {
+            let expected = hash
+                .as_str()
+                .ok_or_else(|| anyhow!("Pinned IDL hash must be a string"))?;
+            let actual = hash_idl_value(&value)?;
+            if actual == expected {
+                Ok(idl)
+            } else {
+                Err(anyhow!(
+                    "IDL does not match its pinned hash (expected `{expected}`, found \
+                    `{actual}`). If the change is intended, re-pin the IDL with \
+                    `anchor idl fetch --for-declare`"
+                ))
+            }
+        }
+    }
+}
+
 /// Legacy IDL spec (pre Anchor v0.30)
 mod legacy {
     use crate::types as t;
@@ -559,3 +644,38 @@ mod legacy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl() -> Idl {
+        serde_json::from_value(json!({
+            "address": "Externa111111111111111111111111111111111111",
+            "metadata": { "name": "external", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [],
+            "constants": [{ "name": "MAX", "type": "u8", "value": "3" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pinned_idl() {
+        let pinned = pin_idl(&idl()).unwrap();
+        assert_eq!(convert_pinned_idl(pinned.as_bytes()).unwrap(), idl());
+
+        // Neither the formatting nor the order of the keys change the hash
+        let mut value = serde_json::from_str::<serde_json::Value>(&pinned).unwrap();
+        let compact = serde_json::to_string(&value).unwrap();
+        assert!(convert_pinned_idl(compact.as_bytes()).is_ok());
+        let metadata = value["metadata"].as_object_mut().unwrap();
+        let name = metadata.remove("name").unwrap();
+        metadata.insert("name".into(), name);
+        assert!(convert_pinned_idl(&serde_json::to_vec(&value).unwrap()).is_ok());
+
+        let tampered = pinned.replace(r#""value": "3""#, r#""value": "4""#);
+        assert_ne!(tampered, pinned);
+        assert!(convert_pinned_idl(tampered.as_bytes()).is_err());
+    }
+}
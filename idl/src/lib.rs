@@ -3,6 +3,9 @@
 #[cfg(feature = "build")]
 pub mod build;
 
+#[cfg(feature = "coder")]
+pub mod coder;
+
 #[cfg(feature = "convert")]
 pub mod convert;
 
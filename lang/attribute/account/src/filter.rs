@@ -0,0 +1,147 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+
+/// Generate the off-chain `getProgramAccounts` filter builder of the account.
+///
+/// Field offsets are only known at compile time for the fields preceded by fixed-size fields, so
+/// Borsh accounts don't get a filter method for the fields after the first variable-size field.
+pub fn gen_filter(strct: &syn::ItemStruct, is_zero_copy: bool) -> TokenStream {
+    // Filters of generic accounts would depend on the generic arguments
+    if strct.generics.lt_token.is_some() {
+        return syn::Error::new_spanned(&strct.generics, "Generic accounts can't have a filter")
+            .to_compile_error();
+    }
+    let syn::Fields::Named(fields) = &strct.fields else {
+        return syn::Error::new_spanned(&strct.fields, "Filters require named fields")
+            .to_compile_error();
+    };
+
+    let ident = &strct.ident;
+    let vis = &strct.vis;
+    let filter_ident = format_ident!("{}Filter", ident);
+    let disc_len = quote! { <#ident as anchor_lang::Discriminator>::DISCRIMINATOR.len() };
+
+    let mut methods = vec![];
+    let mut offset = Some(disc_len.clone());
+    let mut skipped = vec![];
+    for field in &fields.named {
+        let field_ident = field.ident.as_ref().expect("Named fields have an ident");
+        let field_vis = &field.vis;
+        let ty = &field.ty;
+
+        if field_ident == "build" {
+            return syn::Error::new_spanned(
+                field_ident,
+                format!("The filter method of `build` would collide with `{filter_ident}::build`"),
+            )
+            .to_compile_error();
+        }
+
+        let (field_offset, bytes) = if is_zero_copy {
+            (
+                quote! { #disc_len + ::core::mem::offset_of!(#ident, #field_ident) },
+                quote! { anchor_lang::__private::bytemuck::bytes_of(&value).to_vec() },
+            )
+        } else {
+            // The fields after a variable-size field have no fixed offset
+            let Some(field_offset) = offset.take() else {
+                skipped.push(format!("`{field_ident}`"));
+                continue;
+            };
+            if is_fixed_size(ty) {
+                let size = quote_spanned! { ty.span() =>
+                    <#ty as anchor_lang::filter::FixedSize>::SIZE
+                };
+                offset = Some(quote! { #field_offset + #size });
+            }
+            (
+                field_offset,
+                quote! { anchor_lang::prelude::borsh::to_vec(&value).unwrap() },
+            )
+        };
+
+        let doc = format!("Filter the accounts by the value of `{field_ident}`.");
+        methods.push(quote! {
+            #[doc = #doc]
+            #[must_use]
+            #field_vis fn #field_ident(mut self, value: #ty) -> Self {
+                self.filters
+                    .push(anchor_lang::filter::Memcmp::new(#field_offset, #bytes));
+                self
+            }
+        });
+    }
+
+    let mut doc = format!("Builder of `getProgramAccounts` filters on the fields of [`{ident}`].");
+    if !skipped.is_empty() {
+        doc.push_str(&format!(
+            "\n\nThe offsets of {} vary with the size of the fields before them, so they have no \
+             filter method.",
+            skipped.join(", ")
+        ));
+    }
+    quote! {
+        #[doc = #doc]
+        #[cfg(not(target_os = "solana"))]
+        #[derive(Debug, Clone, Default)]
+        #vis struct #filter_ident {
+            filters: Vec<anchor_lang::filter::Memcmp>,
+        }
+
+        #[cfg(not(target_os = "solana"))]
+        #[automatically_derived]
+        impl #filter_ident {
+            #(#methods)*
+
+            /// Build the filters, which don't include the discriminator of the account.
+            pub fn build(self) -> Vec<anchor_lang::filter::Memcmp> {
+                self.filters
+            }
+        }
+
+        #[cfg(not(target_os = "solana"))]
+        #[automatically_derived]
+        impl #ident {
+            /// Start building `getProgramAccounts` filters on the fields of the account.
+            pub fn filter() -> #filter_ident {
+                #filter_ident::default()
+            }
+        }
+    }
+}
+
+/// Whether the type is one of the fixed-size types implementing `anchor_lang::filter::FixedSize`.
+///
+/// This only looks at the name of the type, so the size is read from the trait implementation,
+/// which fails to compile if a type of the same name that doesn't implement it shadows it.
+fn is_fixed_size(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(ty) if ty.qself.is_none() => {
+            let Some(segment) = ty.path.segments.last() else {
+                return false;
+            };
+            segment.arguments.is_empty()
+                && matches!(
+                    segment.ident.to_string().as_str(),
+                    "bool"
+                        | "u8"
+                        | "i8"
+                        | "u16"
+                        | "i16"
+                        | "u32"
+                        | "i32"
+                        | "f32"
+                        | "u64"
+                        | "i64"
+                        | "f64"
+                        | "u128"
+                        | "i128"
+                        | "Pubkey"
+                )
+        }
+        syn::Type::Array(ty) => is_fixed_size(&ty.elem),
+        syn::Type::Paren(ty) => is_fixed_size(&ty.elem),
+        _ => false,
+    }
+}
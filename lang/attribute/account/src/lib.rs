@@ -10,6 +10,7 @@ use syn::{
     Ident, LitStr,
 };
 
+mod filter;
 mod id;
 
 #[cfg(feature = "lazy-account")]
@@ -53,6 +54,30 @@ mod lazy;
 ///     - `discriminator = MY_DISC`
 ///     - `discriminator = get_disc(...)`
 ///
+/// - `filter`: Generate a builder of `getProgramAccounts` filters, see [Filters](#filters)
+///
+/// # Filters
+///
+/// Off-chain, `#[account(filter)]` generates a `<Account>Filter` builder of
+/// `getProgramAccounts` filters on the fields of the account, matching each field at its offset
+/// after the discriminator:
+///
+/// ```ignore
+/// #[account(filter)]
+/// pub struct MyAccount {
+///     pub authority: Pubkey,
+///     pub name: String,
+///     pub bump: u8,
+/// }
+///
+/// let filters = MyAccount::filter().authority(authority).build();
+/// ```
+///
+/// The offsets of Borsh accounts are only known for the fields that follow fixed-size fields
+/// (primitives, `Pubkey` and arrays of them), so the fields after the first variable-size field
+/// (`bump` above) have no filter method, which the documentation of the builder lists. A field
+/// named `build` can't be filtered, as it would collide with the method building the filters.
+///
 /// # Zero Copy Deserialization
 ///
 /// **WARNING**: Zero copy deserialization is an experimental feature. It's
@@ -105,6 +130,11 @@ pub fn account(
 
     let account_strct = parse_macro_input!(input as syn::ItemStruct);
     let account_name = &account_strct.ident;
+    let filter = if args.filter {
+        filter::gen_filter(&account_strct, is_zero_copy)
+    } else {
+        quote! {}
+    };
     let account_name_str = account_name.to_string();
     let (impl_gen, type_gen, where_clause) = account_strct.generics.split_for_impl();
 
@@ -208,6 +238,8 @@ pub fn account(
                 }
 
                 #owner_impl
+
+                #filter
             }
         } else {
             let lazy = {
@@ -266,6 +298,8 @@ pub fn account(
 
                 #owner_impl
 
+                #filter
+
                 #lazy
             }
         }
@@ -278,6 +312,8 @@ struct AccountArgs {
     zero_copy: Option<bool>,
     /// Account namespace override, `account` if not specified
     namespace: Option<String>,
+    /// Whether to generate the off-chain filter builder
+    filter: bool,
     /// Named overrides
     overrides: Option<Overrides>,
 }
@@ -294,6 +330,9 @@ impl Parse for AccountArgs {
                 AccountArg::Namespace(ns) => {
                     parsed.namespace.replace(ns);
                 }
+                AccountArg::Filter => {
+                    parsed.filter = true;
+                }
                 AccountArg::Overrides(ov) => {
                     parsed.overrides.replace(ov);
                 }
@@ -307,6 +346,7 @@ impl Parse for AccountArgs {
 enum AccountArg {
     ZeroCopy { is_unsafe: bool },
     Namespace(String),
+    Filter,
     Overrides(Overrides),
 }
 
@@ -341,6 +381,12 @@ impl Parse for AccountArg {
             return Ok(Self::ZeroCopy { is_unsafe });
         };
 
+        // Filter
+        if input.fork().parse::<Ident>()? == "filter" {
+            input.parse::<Ident>()?;
+            return Ok(Self::Filter);
+        }
+
         // Overrides
         input.parse::<Overrides>().map(Self::Overrides)
     }
@@ -262,7 +262,7 @@ pub fn convert_idl_type_def_to_ts(
             let alias = convert_idl_type_to_syn_type(alias);
             quote! {
                 #docs
-                pub type #name = #alias;
+                pub type #name #generics = #alias;
             }
         }
     }
@@ -1,9 +1,12 @@
 mod common;
 mod mods;
 
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use anchor_lang_idl::{convert::convert_idl, types::Idl};
+use anchor_lang_idl::{convert::convert_pinned_idl, types::Idl};
 use anyhow::anyhow;
 use quote::{quote, ToTokens};
 use syn::parse::{Parse, ParseStream};
@@ -18,41 +21,77 @@ use mods::{
 pub struct DeclareProgram {
     name: syn::Ident,
     idl: Idl,
+    idl_path: PathBuf,
 }
 
 impl Parse for DeclareProgram {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let name = input.parse()?;
-        let idl = get_idl(&name).map_err(|e| syn::Error::new(name.span(), e))?;
-        Ok(Self { name, idl })
+        let name = input.parse::<syn::Ident>()?;
+        let path = if input.peek(syn::Token![,]) {
+            input.parse::<syn::Token![,]>()?;
+            let key = input.parse::<syn::Ident>()?;
+            if key != "path" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("Unexpected argument `{key}`, expected `path`"),
+                ));
+            }
+            input.parse::<syn::Token![=]>()?;
+            Some(input.parse::<syn::LitStr>()?)
+        } else {
+            None
+        };
+
+        let span = path.as_ref().map(|p| p.span()).unwrap_or(name.span());
+        let (idl, idl_path) = get_idl(&name, path.map(|p| p.value()).as_deref())
+            .map_err(|e| syn::Error::new(span, e))?;
+        Ok(Self {
+            name,
+            idl,
+            idl_path,
+        })
     }
 }
 
 impl ToTokens for DeclareProgram {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let program = gen_program(&self.idl, &self.name);
+        let program = gen_program(&self.idl, &self.name, &self.idl_path);
         tokens.extend(program)
     }
 }
 
-fn get_idl(name: &syn::Ident) -> anyhow::Result<Idl> {
-    env::var("CARGO_MANIFEST_DIR")
+/// Get the IDL and its path.
+///
+/// If `path` is not specified, the IDL is searched as `<name>.json` in the closest `idls`
+/// directory, otherwise `path` is used (relative to the crate root).
+///
+/// Pinned IDLs (created with `anchor idl fetch --for-declare`) are verified against their hash.
+fn get_idl(name: &syn::Ident, path: Option<&str>) -> anyhow::Result<(Idl, PathBuf)> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR")
         .map(PathBuf::from)
-        .map_err(|e| anyhow!("Failed to get environment variable `CARGO_MANIFEST_DIR`: {e}"))?
-        .ancestors()
-        .find_map(|ancestor| {
-            let idl_dir = ancestor.join("idls");
-            idl_dir.exists().then_some(idl_dir)
-        })
-        .ok_or_else(|| anyhow!("`idls` directory not found"))
-        .map(|idl_dir| idl_dir.join(name.to_string()).with_extension("json"))
-        .map(fs::read)?
-        .map_err(|e| anyhow!("Failed to read IDL `{name}`: {e}"))
-        .map(|buf| convert_idl(&buf))?
+        .map_err(|e| anyhow!("Failed to get environment variable `CARGO_MANIFEST_DIR`: {e}"))?;
+    let path = match path {
+        Some(path) => manifest_dir.join(path),
+        None => manifest_dir
+            .ancestors()
+            .find_map(|ancestor| {
+                let idl_dir = ancestor.join("idls");
+                idl_dir.exists().then_some(idl_dir)
+            })
+            .ok_or_else(|| anyhow!("`idls` directory not found"))
+            .map(|idl_dir| idl_dir.join(name.to_string()).with_extension("json"))?,
+    };
+
+    let buf =
+        fs::read(&path).map_err(|e| anyhow!("Failed to read IDL `{}`: {e}", path.display()))?;
+    let idl =
+        convert_pinned_idl(&buf).map_err(|e| anyhow!("Invalid IDL `{}`: {e}", path.display()))?;
+    Ok((idl, path))
 }
 
-fn gen_program(idl: &Idl, name: &syn::Ident) -> proc_macro2::TokenStream {
+fn gen_program(idl: &Idl, name: &syn::Ident, idl_path: &Path) -> proc_macro2::TokenStream {
     let docs = gen_program_docs(idl);
+    let idl_path = idl_path.to_string_lossy();
     let id = gen_id(idl);
     let program_mod = gen_program_mod(&idl.metadata.name);
 
@@ -84,6 +123,9 @@ fn gen_program(idl: &Idl, name: &syn::Ident) -> proc_macro2::TokenStream {
             use events::*;
             use types::*;
 
+            // Recompile when the IDL changes
+            const _: &[u8] = include_bytes!(#idl_path);
+
             #id
             #program_mod
 
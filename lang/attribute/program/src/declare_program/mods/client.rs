@@ -1,11 +1,16 @@
-use anchor_lang_idl::types::Idl;
-use quote::quote;
+use anchor_lang_idl::types::{
+    Idl, IdlDefinedFields, IdlInstruction, IdlInstructionAccount, IdlInstructionAccountItem,
+    IdlInstructionAccounts, IdlSeed, IdlType, IdlTypeDefTy,
+};
+use heck::{CamelCase, SnakeCase};
+use quote::{format_ident, quote};
 
-use super::common::gen_accounts_common;
+use super::common::{gen_accounts_common, get_all_instruction_accounts};
 
 pub fn gen_client_mod(idl: &Idl) -> proc_macro2::TokenStream {
     let client_args_mod = gen_client_args_mod();
     let client_accounts_mod = gen_client_accounts_mod(idl);
+    let client_builders_mod = gen_client_builders_mod(idl);
 
     quote! {
         /// Off-chain client helpers.
@@ -14,6 +19,7 @@ pub fn gen_client_mod(idl: &Idl) -> proc_macro2::TokenStream {
 
             #client_args_mod
             #client_accounts_mod
+            #client_builders_mod
         }
     }
 }
@@ -30,3 +36,463 @@ fn gen_client_args_mod() -> proc_macro2::TokenStream {
 fn gen_client_accounts_mod(idl: &Idl) -> proc_macro2::TokenStream {
     gen_accounts_common(idl, "client")
 }
+
+fn gen_client_builders_mod(idl: &Idl) -> proc_macro2::TokenStream {
+    let all_ix_accs = get_all_instruction_accounts(idl);
+    let builders = idl
+        .instructions
+        .iter()
+        .map(|ix| gen_client_builder(idl, ix, &all_ix_accs));
+
+    quote! {
+        /// Error of the accounts builders, returned when an account is neither given nor
+        /// resolvable from the IDL.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct ResolveError {
+            /// Path of the account, e.g. `accounts.authority` for nested accounts
+            pub account: &'static str,
+        }
+
+        impl std::fmt::Display for ResolveError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "Account `{}` is not resolvable", self.account)
+            }
+        }
+
+        impl std::error::Error for ResolveError {}
+
+        /// Accounts builders, resolving the constant addresses, the PDAs and the relations of
+        /// the accounts that are not given.
+        pub mod builders {
+            use super::*;
+
+            #(#builders)*
+        }
+    }
+}
+
+/// Account of an instruction, flattened from the composite accounts.
+struct FlatAccount<'a> {
+    path: Vec<&'a str>,
+    acc: &'a IdlInstructionAccount,
+}
+
+impl FlatAccount<'_> {
+    fn field(&self) -> syn::Ident {
+        format_ident!("{}", self.path.join("_"))
+    }
+
+    fn local(&self) -> syn::Ident {
+        format_ident!("__{}", self.path.join("_"))
+    }
+}
+
+fn gen_client_builder(
+    idl: &Idl,
+    ix: &IdlInstruction,
+    all_ix_accs: &[IdlInstructionAccounts],
+) -> proc_macro2::TokenStream {
+    let ident = format_ident!("{}", ix.name.to_camel_case());
+    let docs = format!(
+        " Accounts builder of the `{}` instruction, see [`super::accounts::{ident}`].",
+        ix.name
+    );
+
+    let mut accounts = vec![];
+    let mut remaining = vec![];
+    flatten_accounts(&ix.accounts, &[], &mut accounts, &mut remaining);
+
+    let fields = accounts
+        .iter()
+        .map(|acc| {
+            let field = acc.field();
+            quote! { pub #field: Option<Pubkey> }
+        })
+        .chain(remaining.iter().map(|path| {
+            let field = format_ident!("{}", path.join("_"));
+            quote! { pub #field: Vec<Pubkey> }
+        }));
+    let setters = accounts
+        .iter()
+        .map(|acc| {
+            let field = acc.field();
+            quote! {
+                pub fn #field(mut self, #field: Pubkey) -> Self {
+                    self.#field = Some(#field);
+                    self
+                }
+            }
+        })
+        .chain(remaining.iter().map(|path| {
+            let field = format_ident!("{}", path.join("_"));
+            quote! {
+                pub fn #field(mut self, #field: Vec<Pubkey>) -> Self {
+                    self.#field = #field;
+                    self
+                }
+            }
+        }));
+
+    let resolutions = gen_resolutions(idl, ix, &accounts);
+    let accounts_value = gen_accounts_value(
+        quote!(super::accounts::#ident),
+        &ix.accounts,
+        &[],
+        all_ix_accs,
+    );
+
+    quote! {
+        #[doc = #docs]
+        #[derive(Debug, Clone, Default)]
+        pub struct #ident {
+            #(#fields,)*
+        }
+
+        impl #ident {
+            #(#setters)*
+
+            /// Build the accounts, resolving the accounts that don't require fetching other
+            /// accounts.
+            pub fn build(
+                self,
+                args: &super::args::#ident,
+            ) -> std::result::Result<super::accounts::#ident, super::ResolveError> {
+                self.build_with(args, |_| None)
+            }
+
+            /// Build the accounts, using `fetch` to get the data of the accounts that the
+            /// relations and the seeds of the other accounts depend on.
+            #[allow(unused_mut, unused_variables)]
+            pub fn build_with(
+                self,
+                args: &super::args::#ident,
+                mut fetch: impl FnMut(&Pubkey) -> Option<Vec<u8>>,
+            ) -> std::result::Result<super::accounts::#ident, super::ResolveError> {
+                #(#resolutions)*
+
+                Ok(#accounts_value)
+            }
+        }
+    }
+}
+
+fn flatten_accounts<'a>(
+    items: &'a [IdlInstructionAccountItem],
+    prefix: &[&'a str],
+    accounts: &mut Vec<FlatAccount<'a>>,
+    remaining: &mut Vec<Vec<&'a str>>,
+) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(acc) => accounts.push(FlatAccount {
+                path: [prefix, &[acc.name.as_str()]].concat(),
+                acc,
+            }),
+            IdlInstructionAccountItem::Remaining(acc) => {
+                remaining.push([prefix, &[acc.name.as_str()]].concat())
+            }
+            IdlInstructionAccountItem::Composite(accs) => flatten_accounts(
+                &accs.accounts,
+                &[prefix, &[accs.name.as_str()]].concat(),
+                accounts,
+                remaining,
+            ),
+        }
+    }
+}
+
+/// Statements resolving the accounts into `Option<Pubkey>` locals, ordered by their dependencies.
+fn gen_resolutions(
+    idl: &Idl,
+    ix: &IdlInstruction,
+    accounts: &[FlatAccount],
+) -> Vec<proc_macro2::TokenStream> {
+    let mut fallbacks = accounts
+        .iter()
+        .map(|acc| gen_fallback(idl, ix, accounts, acc))
+        .collect::<Vec<_>>();
+
+    let mut resolutions = vec![];
+    let mut resolved = vec![false; accounts.len()];
+    while let Some(i) = (0..accounts.len())
+        .find(|&i| {
+            !resolved[i]
+                && fallbacks[i]
+                    .as_ref()
+                    .map(|(_, deps)| deps.iter().all(|&dep| resolved[dep]))
+                    .unwrap_or(true)
+        })
+        .or_else(|| {
+            // Accounts with cyclic dependencies must be given
+            let i = resolved.iter().position(|resolved| !resolved)?;
+            fallbacks[i] = None;
+            Some(i)
+        })
+    {
+        let field = accounts[i].field();
+        let local = accounts[i].local();
+        let resolution = match &fallbacks[i] {
+            Some((fallback, _)) => quote! { self.#field.or_else(|| #fallback) },
+            None => quote! { self.#field },
+        };
+        resolutions.push(quote! { let #local: Option<Pubkey> = #resolution; });
+        resolved[i] = true;
+    }
+
+    resolutions
+}
+
+/// Expression resolving the account when it's not given, along with the indices of the accounts
+/// it depends on.
+fn gen_fallback(
+    idl: &Idl,
+    ix: &IdlInstruction,
+    accounts: &[FlatAccount],
+    acc: &FlatAccount,
+) -> Option<(proc_macro2::TokenStream, Vec<usize>)> {
+    // Optional accounts are `None` unless given
+    if acc.acc.optional {
+        return None;
+    }
+
+    if let Some(address) = &acc.acc.address {
+        return Some((quote! { Some(Pubkey::from_str_const(#address)) }, vec![]));
+    }
+
+    let prefix = &acc.path[..acc.path.len() - 1];
+    if let Some(pda) = &acc.acc.pda {
+        let mut deps = vec![];
+        let seeds = pda
+            .seeds
+            .iter()
+            .map(|seed| gen_seed(idl, ix, accounts, prefix, seed, &mut deps))
+            .collect::<Option<Vec<_>>>()?;
+        let program_id = match &pda.program {
+            Some(program) => {
+                let program = gen_seed(idl, ix, accounts, prefix, program, &mut deps)?;
+                quote! { Pubkey::try_from((#program).as_slice()).ok()? }
+            }
+            None => quote! { super::super::__ID },
+        };
+
+        return Some((
+            quote! {{
+                let seeds: Vec<Vec<u8>> = vec![#(#seeds),*];
+                let seeds = seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                Some(Pubkey::find_program_address(&seeds, &#program_id).0)
+            }},
+            deps,
+        ));
+    }
+
+    // Relations are the accounts that store the address of this account in their field of the
+    // same name, i.e. `has_one` constraints
+    let field = format_ident!("{}", acc.acc.name);
+    let account_tys = idl
+        .accounts
+        .iter()
+        .filter(|account| {
+            idl.types
+                .iter()
+                .find(|ty| ty.name == account.name)
+                .and_then(|ty| match &ty.ty {
+                    IdlTypeDefTy::Struct {
+                        fields: Some(IdlDefinedFields::Named(fields)),
+                    } => fields.iter().find(|field| field.name == acc.acc.name),
+                    _ => None,
+                })
+                .is_some_and(|field| field.ty == IdlType::Pubkey)
+        })
+        .map(|account| format_ident!("{}", account.name))
+        .collect::<Vec<_>>();
+    let (relations, deps): (Vec<_>, Vec<_>) = acc
+        .acc
+        .relations
+        .iter()
+        .filter(|_| !account_tys.is_empty())
+        .filter_map(|relation| match find_account(accounts, prefix, relation)? {
+            (i, fields) if fields.is_empty() => Some((i, accounts[i].local())),
+            _ => None,
+        })
+        .map(|(i, relation)| {
+            let relation = quote! {
+                if let Some(data) = #relation.and_then(|key| fetch(&key)) {
+                    #(
+                        if data.starts_with(super::super::accounts::#account_tys::DISCRIMINATOR) {
+                            let account = <super::super::accounts::#account_tys as anchor_lang::AccountDeserialize>::try_deserialize(
+                                &mut data.as_slice(),
+                            );
+                            if let Ok(account) = account {
+                                return Some(account.#field);
+                            }
+                        }
+                    )*
+                }
+            };
+            (relation, i)
+        })
+        .unzip();
+    if relations.is_empty() {
+        return None;
+    }
+
+    Some((
+        quote! {{
+            #(#relations)*
+            None
+        }},
+        deps,
+    ))
+}
+
+/// Expression of the bytes of the seed, which may return `None` from the enclosing closure.
+fn gen_seed(
+    idl: &Idl,
+    ix: &IdlInstruction,
+    accounts: &[FlatAccount],
+    prefix: &[&str],
+    seed: &IdlSeed,
+    deps: &mut Vec<usize>,
+) -> Option<proc_macro2::TokenStream> {
+    match seed {
+        IdlSeed::Const(seed) => {
+            let value = &seed.value;
+            Some(quote! { vec![#(#value),*] })
+        }
+        IdlSeed::Arg(seed) => {
+            let mut segments = seed.path.split('.');
+            let name = segments.next()?;
+            let ty = &ix.args.iter().find(|arg| arg.name == name)?.ty;
+            let segments = segments.collect::<Vec<_>>();
+            let ty = field_ty(idl, ty, &segments)?;
+            let path = std::iter::once(name)
+                .chain(segments)
+                .map(|segment| format_ident!("{}", segment));
+            Some(gen_seed_bytes(quote! { &args.#(#path).* }, ty))
+        }
+        IdlSeed::Account(seed) => {
+            let (i, fields) = find_account(accounts, prefix, &seed.path)?;
+            deps.push(i);
+            let local = accounts[i].local();
+            if fields.is_empty() {
+                return Some(quote! { #local?.to_bytes().to_vec() });
+            }
+
+            // Fields of accounts require fetching the account
+            let account_ty = seed.account.as_ref()?;
+            let ty = IdlType::Defined {
+                name: account_ty.to_owned(),
+                generics: vec![],
+            };
+            let bytes = gen_seed_bytes(quote! { &value }, field_ty(idl, &ty, &fields)?);
+            let account_ty = format_ident!("{}", account_ty);
+            let fields = fields.iter().map(|field| format_ident!("{}", field));
+            Some(quote! {{
+                let data = fetch(&#local?)?;
+                let account = <super::super::accounts::#account_ty as anchor_lang::AccountDeserialize>::try_deserialize(
+                    &mut data.as_slice(),
+                )
+                .ok()?;
+                let value = account.#(#fields).*;
+                #bytes
+            }})
+        }
+    }
+}
+
+/// Bytes of the value of the seed: strings and bytes are used as is, other types are Borsh
+/// serialized.
+fn gen_seed_bytes(value: proc_macro2::TokenStream, ty: &IdlType) -> proc_macro2::TokenStream {
+    match ty {
+        IdlType::String => quote! { (#value).as_bytes().to_vec() },
+        IdlType::Bytes => quote! { (#value).to_vec() },
+        _ => quote! { anchor_lang::prelude::borsh::to_vec(#value).ok()? },
+    }
+}
+
+/// Index of the account at `path`, relative to `prefix` or to the instruction, along with the
+/// path of the field of the account's data.
+fn find_account<'a>(
+    accounts: &[FlatAccount],
+    prefix: &[&str],
+    path: &'a str,
+) -> Option<(usize, Vec<&'a str>)> {
+    let segments = path.split('.').collect::<Vec<_>>();
+    [prefix, &[] as &[&str]].iter().find_map(|prefix| {
+        (1..=segments.len()).rev().find_map(|len| {
+            let path = [prefix, &segments[..len]].concat();
+            accounts
+                .iter()
+                .position(|acc| acc.path == path)
+                .map(|i| (i, segments[len..].to_vec()))
+        })
+    })
+}
+
+/// Type of the field at `path` of a value of type `ty`.
+fn field_ty<'a>(idl: &'a Idl, ty: &'a IdlType, path: &[&str]) -> Option<&'a IdlType> {
+    path.iter().try_fold(ty, |ty, segment| match ty {
+        IdlType::Defined { name, generics } if generics.is_empty() => {
+            match &idl.types.iter().find(|ty| &ty.name == name)?.ty {
+                IdlTypeDefTy::Struct {
+                    fields: Some(IdlDefinedFields::Named(fields)),
+                } => fields
+                    .iter()
+                    .find(|field| field.name == *segment)
+                    .map(|field| &field.ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Value of the client accounts struct `ty` from the resolved accounts.
+fn gen_accounts_value(
+    ty: proc_macro2::TokenStream,
+    items: &[IdlInstructionAccountItem],
+    prefix: &[&str],
+    all_ix_accs: &[IdlInstructionAccounts],
+) -> proc_macro2::TokenStream {
+    let fields = items.iter().map(|item| match item {
+        IdlInstructionAccountItem::Single(acc) => {
+            let name = format_ident!("{}", acc.name);
+            let path = [prefix, &[acc.name.as_str()]].concat();
+            let local = format_ident!("__{}", path.join("_"));
+            if acc.optional {
+                quote! { #name: #local }
+            } else {
+                let path = path.join(".");
+                quote! { #name: #local.ok_or(super::ResolveError { account: #path })? }
+            }
+        }
+        IdlInstructionAccountItem::Remaining(acc) => {
+            let name = format_ident!("{}", acc.name);
+            let field = format_ident!("{}", [prefix, &[acc.name.as_str()]].concat().join("_"));
+            quote! { #name: self.#field }
+        }
+        IdlInstructionAccountItem::Composite(accs) => {
+            let name = format_ident!("{}", accs.name);
+            let ty_name = all_ix_accs
+                .iter()
+                .find(|a| a.accounts == accs.accounts)
+                .map(|a| a.name.to_camel_case())
+                .expect("Accounts must exist");
+            let mod_name = format_ident!("__client_accounts_{}", ty_name.to_snake_case());
+            let ty_name = format_ident!("{}", ty_name);
+            let value = gen_accounts_value(
+                quote!(super::super::internal::#mod_name::#ty_name),
+                &accs.accounts,
+                &[prefix, &[accs.name.as_str()]].concat(),
+                all_ix_accs,
+            );
+            quote! { #name: #value }
+        }
+    });
+
+    quote! {
+        #ty {
+            #(#fields,)*
+        }
+    }
+}
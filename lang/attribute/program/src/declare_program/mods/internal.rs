@@ -145,6 +145,20 @@ fn gen_internal_accounts_common(
                         pub #name: #acc_expr
                     }
                 }
+                IdlInstructionAccountItem::Remaining(acc) => {
+                    let name = format_ident!("{}", acc.name);
+                    let attrs = acc.writable.then_some(quote!(mut));
+                    let acc_ty = if acc.signer {
+                        quote! { Signer #generics }
+                    } else {
+                        quote! { AccountInfo #generics }
+                    };
+
+                    quote! {
+                        #[account(#attrs)]
+                        pub #name: Remaining<'info, #acc_ty>
+                    }
+                }
                 IdlInstructionAccountItem::Composite(accs) => {
                     let name = format_ident!("{}", accs.name);
                     let ty_name = all_ix_accs
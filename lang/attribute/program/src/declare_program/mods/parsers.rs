@@ -170,6 +170,43 @@ fn gen_instruction(idl: &Idl) -> proc_macro2::TokenStream {
                         }
                     }
                 }
+                IdlInstructionAccountItem::Remaining(acc) => {
+                    let name = format_ident!("{}", acc.name);
+                    let signer = acc.signer;
+                    let writable = acc.writable;
+                    let min = acc.remaining.min as usize;
+                    let max = match acc.remaining.max {
+                        Some(max) => {
+                            let max = max as usize;
+                            quote! {
+                                if keys.len() > #max {
+                                    return Err(ProgramError::InvalidArgument.into());
+                                }
+                            }
+                        }
+                        None => quote! {},
+                    };
+                    quote! {
+                        #name: {
+                            let mut keys = Vec::new();
+                            for acc in accs.by_ref() {
+                                if acc.is_signer != #signer {
+                                    return Err(ProgramError::InvalidAccountData.into());
+                                }
+                                if acc.is_writable != #writable {
+                                    return Err(ProgramError::InvalidAccountData.into());
+                                }
+                                keys.push(acc.pubkey);
+                            }
+                            if keys.len() < #min {
+                                return Err(ProgramError::NotEnoughAccountKeys.into());
+                            }
+                            #max
+
+                            keys
+                        }
+                    }
+                }
                 IdlInstructionAccountItem::Composite(accs) => {
                     let name = format_ident!("{}", accs.name);
                     let accounts = all_ix_accs
@@ -45,6 +45,18 @@ pub fn program(
 /// This generates a module named `program_name` that can be used to interact with the program
 /// without having to add the program's crate as a dependency.
 ///
+/// The IDL can also be loaded from an arbitrary path, relative to the crate root:
+///
+/// ```rs
+/// declare_program!(program_name, path = "../idls/some_program.json");
+/// ```
+///
+/// # Pinned IDLs
+///
+/// `anchor idl fetch --for-declare <PROGRAM_ID>` saves the on-chain IDL to `idls/` with its hash
+/// embedded. Compilation fails if a pinned IDL is changed without updating its hash, so that IDL
+/// updates are always deliberate.
+///
 /// Both on-chain and off-chain usage is supported.
 ///
 /// Use `cargo doc --open` to see the generated modules and their documentation.
@@ -348,6 +348,51 @@ use syn::parse_macro_input;
 ///         </tr>
 ///         <tr>
 ///             <td>
+///                 <code>#[account(each::has_one = &lt;target_account&gt;)]</code><br><br>
+///                 <code>#[account(each::owner = &lt;expr&gt;)]</code><br><br>
+///                 <code>#[account(each::constraint = &lt;expr&gt;)]</code><br><br>
+///                 <code>#[account(each::unique)]</code>
+///             </td>
+///             <td>
+///                 Can only be used on <code>Remaining</code> accounts.<br>
+///                 Runs the <code>has_one</code>, <code>owner</code> and <code>constraint</code>
+///                 checks on every account of the collection. Inside <code>each::constraint</code>,
+///                 the field's name refers to the account being checked.<br>
+///                 <code>each::unique</code> checks that no account appears more than once.<br>
+///                 Errors name the failing account by its index, e.g. <code>positions[2]</code>.
+///                 Custom errors are supported via <code>@</code>, except for <code>each::unique</code>.<br><br>
+///                 Example:
+///                 <pre><code>
+/// pub user: Signer<'info>,
+/// #[account(
+///     mut,
+///     each::has_one = user,
+///     each::constraint = positions.amount > 0 @ MyError::EmptyPosition,
+///     each::unique,
+/// )]
+/// pub positions: Remaining<'info, Account<'info, Position>>
+///                 </code></pre>
+///             </td>
+///         </tr>
+///         <tr>
+///             <td>
+///                 <code>#[account(remaining::min = &lt;expr&gt;)]</code><br><br>
+///                 <code>#[account(remaining::max = &lt;expr&gt;)]</code>
+///             </td>
+///             <td>
+///                 Can only be used on <code>Remaining</code> accounts.<br>
+///                 Checks the number of accounts in the collection is within the given bounds
+///                 before they are deserialized. The bounds are written to the IDL so that
+///                 clients know how many accounts the instruction expects.<br><br>
+///                 Example:
+///                 <pre><code>
+/// #[account(mut, remaining::min = 1, remaining::max = MAX_POSITIONS)]
+/// pub positions: Remaining<'info, Account<'info, Position>>
+///                 </code></pre>
+///             </td>
+///         </tr>
+///         <tr>
+///             <td>
 ///                 <code>#[account(executable)]</code>
 ///             </td>
 ///             <td>
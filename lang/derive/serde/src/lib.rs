@@ -13,6 +13,12 @@ use proc_macro_crate::FoundCrate;
 use quote::quote;
 use syn::Ident;
 
+/// Package name of `anchor-lang`.
+const ANCHOR_LANG_PACKAGE: &str = "trixter-osec-anchor-lang";
+
+/// Library name of `anchor-lang`, which differs from its package name.
+const ANCHOR_LANG_LIB: &str = "anchor_lang";
+
 fn gen_borsh_serialize(input: TokenStream) -> TokenStream2 {
     let input = TokenStream2::from(input);
     let attrs = helper_attrs("BorshSerialize");
@@ -86,12 +92,16 @@ fn helper_attrs(mac: &str) -> TokenStream2 {
     // 3. This results in the trait implementations being produced, but the duplicate type definition being deleted
 
     let mac_path = Ident::new(mac, Span::call_site());
-    let anchor = proc_macro_crate::crate_name("trixter-osec-anchor-lang")
-        .expect("`anchor-derive-serde` must be used via `trixter-osec-anchor-lang`");
+    let anchor = proc_macro_crate::crate_name(ANCHOR_LANG_PACKAGE).unwrap_or_else(|_| {
+        panic!("`anchor-derive-serde` must be used via `{ANCHOR_LANG_PACKAGE}`")
+    });
 
     let anchor_path = Ident::new(
         match &anchor {
             FoundCrate::Itself => "crate",
+            // Dependencies that aren't renamed, and the integration tests of the package, refer to
+            // the crate by its library name, while `proc-macro-crate` returns the package name
+            FoundCrate::Name(cr) if *cr == ANCHOR_LANG_PACKAGE.replace('-', "_") => ANCHOR_LANG_LIB,
             FoundCrate::Name(cr) => cr.as_str(),
         },
         Span::call_site(),
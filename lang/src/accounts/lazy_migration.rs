@@ -0,0 +1,497 @@
+//! Like [`Migration`](crate::accounts::migration::Migration), but reads the old
+//! account layout on-demand.
+
+use std::{collections::BTreeSet, fmt, ops::Deref};
+
+use crate::{
+    bpf_writer::BpfWriter, error::ErrorCode, AccountInfo, AccountMeta, AccountSerialize, Accounts,
+    AccountsExit, Discriminator, Key, Owner, Pubkey, Result, ToAccountInfos, ToAccountMetas,
+};
+
+use super::lazy_account::LazyAccount;
+
+/// Migrate an account from one type to another without deserializing the old
+/// layout up front.
+///
+/// [`Migration`] deserializes the whole `From` value before the instruction
+/// handler runs, which can be expensive for large accounts. [`LazyMigration`]
+/// wraps a [`LazyAccount`] instead: the old layout is read field by field from
+/// the account data with the generated `load_<field>` methods, and only the new
+/// `To` value is serialized when the instruction exits.
+///
+/// # Table of contents
+///
+/// - [Features](#features)
+/// - [Resizing](#resizing)
+/// - [Example](#example)
+/// - [Safety](#safety)
+///
+/// # Features
+///
+/// - Checks the account owner and the `From` discriminator.
+/// - Rejects accounts that are already in the `To` format.
+/// - Dereferences to [`LazyAccount<From>`](LazyAccount), so all of its `load` methods can be used
+///   to read the old data.
+/// - Only the `To` value is serialized on exit, and the account must have been migrated by then.
+/// - Works with the `realloc` constraint to grow the account in the same instruction.
+///
+/// # Resizing
+///
+/// Constraints run before the instruction handler, so the `realloc` constraint resizes the account
+/// before any of the old data is read. It must therefore only be used to grow the account:
+/// shrinking it would truncate the `From` data that the `load_<field>` methods read.
+///
+/// To shrink an account, resize it in the handler once the old data is no longer needed instead,
+/// as in the example below.
+///
+/// # Example
+///
+/// ```ignore
+/// use anchor_lang::prelude::*;
+///
+/// declare_id!("LazyMigration111111111111111111111111111111");
+///
+/// #[program]
+/// pub mod lazy_migration {
+///     use super::*;
+///
+///     pub fn migrate(ctx: Context<MigrateAccount>) -> Result<()> {
+///         // Only `authority` is deserialized, `history` is never read
+///         let authority = *ctx.accounts.my_account.load_authority()?;
+///         ctx.accounts.my_account.migrate(AccountV2 {
+///             authority,
+///             version: 2,
+///         })?;
+///
+///         // `AccountV2` can be smaller than `AccountV1`, so only shrink the account once the old
+///         // data has been read
+///         ctx.accounts
+///             .my_account
+///             .to_account_info()
+///             .resize(8 + AccountV2::INIT_SPACE)?;
+///         Ok(())
+///     }
+/// }
+///
+/// #[account]
+/// pub struct AccountV1 {
+///     pub authority: Pubkey,
+///     pub history: Vec<u64>,
+/// }
+///
+/// #[account]
+/// #[derive(InitSpace)]
+/// pub struct AccountV2 {
+///     pub authority: Pubkey,
+///     pub version: u8,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct MigrateAccount<'info> {
+///     #[account(mut)]
+///     pub my_account: LazyMigration<'info, AccountV1, AccountV2>,
+/// }
+/// ```
+///
+/// # Safety
+///
+/// Same as [`LazyAccount`], the old layout is not validated beyond its discriminator. Make sure the
+/// stored data always matches the `From` type identically.
+///
+/// [`Migration`]: crate::accounts::migration::Migration
+pub struct LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    /// Lazily loaded old account data
+    from: LazyAccount<'info, From>,
+    /// Migrated account data, if any
+    to: Option<To>,
+}
+
+impl<From, To> fmt::Debug for LazyMigration<'_, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone + fmt::Debug,
+    To: AccountSerialize + Owner + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyMigration")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+impl<'info, From, To> LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    /// Returns `true` if the account has been migrated.
+    #[inline(always)]
+    pub fn is_migrated(&self) -> bool {
+        self.to.is_some()
+    }
+
+    /// Migrates the account by providing the new data.
+    ///
+    /// The data will be serialized to the account when the instruction exits.
+    ///
+    /// # Errors
+    /// Returns an error if the account has already been migrated.
+    pub fn migrate(&mut self, new_data: To) -> Result<()> {
+        if self.is_migrated() {
+            return Err(ErrorCode::AccountAlreadyMigrated.into());
+        }
+
+        self.to = Some(new_data);
+        Ok(())
+    }
+
+    /// Gets a reference to the migrated value, or migrates it with the provided data.
+    pub fn into_inner(&mut self, new_data: To) -> &To {
+        self.to.get_or_insert(new_data)
+    }
+
+    /// Gets a mutable reference to the migrated value, or migrates it with the provided data.
+    pub fn into_inner_mut(&mut self, new_data: To) -> &mut To {
+        self.to.get_or_insert(new_data)
+    }
+
+    /// Returns a reference to the migrated value if the account has been migrated.
+    pub fn try_as_to(&self) -> Result<&To> {
+        self.to
+            .as_ref()
+            .ok_or_else(|| ErrorCode::AccountNotMigrated.into())
+    }
+
+    /// Returns a mutable reference to the migrated value if the account has been migrated.
+    pub fn try_as_to_mut(&mut self) -> Result<&mut To> {
+        self.to
+            .as_mut()
+            .ok_or_else(|| ErrorCode::AccountNotMigrated.into())
+    }
+
+    /// Check the owner and the `From` discriminator.
+    ///
+    /// Only accepts accounts in the `From` format, none of the account data is
+    /// deserialized.
+    #[inline(never)]
+    pub fn try_from(info: &'info AccountInfo<'info>) -> Result<Self> {
+        if info.owner == &crate::solana_program::system_program::ID && info.lamports() == 0 {
+            return Err(ErrorCode::AccountNotInitialized.into());
+        }
+
+        Ok(Self {
+            from: LazyAccount::try_from(info)?,
+            to: None,
+        })
+    }
+}
+
+impl<'info, B, From, To> Accounts<'info, B> for LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    #[inline(never)]
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &'info [AccountInfo<'info>],
+        _ix_data: &[u8],
+        _bumps: &mut B,
+        _reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        let account = &accounts[0];
+        *accounts = &accounts[1..];
+        Self::try_from(account)
+    }
+}
+
+impl<'info, From, To> AccountsExit<'info> for LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    fn exit(&self, program_id: &Pubkey) -> Result<()> {
+        let info = self.from.as_ref();
+
+        // Check if account is closed
+        if crate::common::is_closed(info) {
+            return Ok(());
+        }
+
+        let Some(to) = &self.to else {
+            return Err(ErrorCode::AccountNotMigrated.into());
+        };
+
+        // Only persist if the owner is the current program
+        if &To::owner() != program_id {
+            return Ok(());
+        }
+
+        // Only the new value is serialized, the old layout is never written back
+        let mut data = info.try_borrow_mut_data()?;
+        let dst: &mut [u8] = &mut data;
+        let mut writer = BpfWriter::new(dst);
+        to.try_serialize(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+impl<From, To> ToAccountMetas for LazyMigration<'_, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        self.from.to_account_metas(is_signer)
+    }
+}
+
+impl<'info, From, To> ToAccountInfos<'info> for LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        self.from.to_account_infos()
+    }
+}
+
+impl<'info, From, To> AsRef<AccountInfo<'info>> for LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        self.from.as_ref()
+    }
+}
+
+impl<From, To> Key for LazyMigration<'_, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    fn key(&self) -> Pubkey {
+        self.from.key()
+    }
+}
+
+// Deref to the lazy `From` account in order to use the generated `load` methods
+impl<'info, From, To> Deref for LazyMigration<'info, From, To>
+where
+    From: AccountSerialize + Discriminator + Owner + Clone,
+    To: AccountSerialize + Owner,
+{
+    type Target = LazyAccount<'info, From>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.from
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AccountDeserialize, AnchorDeserialize, AnchorSerialize};
+
+    const TEST_DISCRIMINATOR_V1: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+    const TEST_DISCRIMINATOR_V2: [u8; 8] = [8, 7, 6, 5, 4, 3, 2, 1];
+    const TEST_OWNER: Pubkey = Pubkey::new_from_array([1u8; 32]);
+
+    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
+    struct AccountV1 {
+        pub data: u64,
+        pub history: Vec<u64>,
+    }
+
+    impl Discriminator for AccountV1 {
+        const DISCRIMINATOR: &'static [u8] = &TEST_DISCRIMINATOR_V1;
+    }
+
+    impl Owner for AccountV1 {
+        fn owner() -> Pubkey {
+            TEST_OWNER
+        }
+    }
+
+    impl AccountSerialize for AccountV1 {
+        fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+            writer.write_all(&TEST_DISCRIMINATOR_V1)?;
+            AnchorSerialize::serialize(self, writer)?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
+    struct AccountV2 {
+        pub data: u64,
+        pub new_field: u64,
+    }
+
+    impl Discriminator for AccountV2 {
+        const DISCRIMINATOR: &'static [u8] = &TEST_DISCRIMINATOR_V2;
+    }
+
+    impl Owner for AccountV2 {
+        fn owner() -> Pubkey {
+            TEST_OWNER
+        }
+    }
+
+    impl AccountSerialize for AccountV2 {
+        fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+            writer.write_all(&TEST_DISCRIMINATOR_V2)?;
+            AnchorSerialize::serialize(self, writer)?;
+            Ok(())
+        }
+    }
+
+    impl AccountDeserialize for AccountV2 {
+        fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+            let mut data = &buf[8..];
+            AnchorDeserialize::deserialize(&mut data)
+                .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+        }
+    }
+
+    fn serialize_v1() -> Vec<u8> {
+        let mut data = vec![0u8; 100];
+        AccountV1 {
+            data: 42,
+            history: vec![1, 2, 3],
+        }
+        .try_serialize(&mut &mut data[..])
+        .unwrap();
+        data
+    }
+
+    fn create_account_info<'a>(
+        key: &'a Pubkey,
+        owner: &'a Pubkey,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, false, true, lamports, data, owner, false)
+    }
+
+    // Verifies that a freshly created LazyMigration starts unmigrated.
+    #[test]
+    fn test_is_migrated_returns_false_initially() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_v1();
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let migration: LazyMigration<AccountV1, AccountV2> =
+            LazyMigration::try_from(&info).unwrap();
+
+        assert!(!migration.is_migrated());
+        assert!(migration.try_as_to().is_err());
+    }
+
+    // Verifies that calling migrate() twice returns an error.
+    #[test]
+    fn test_migrate_fails_if_already_migrated() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_v1();
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let mut migration: LazyMigration<AccountV1, AccountV2> =
+            LazyMigration::try_from(&info).unwrap();
+
+        let new_data = AccountV2 {
+            data: 42,
+            new_field: 1,
+        };
+        migration.migrate(new_data.clone()).unwrap();
+
+        assert!(migration.migrate(new_data).is_err());
+    }
+
+    // Verifies that exit only writes the new value to the account.
+    #[test]
+    fn test_exit_serializes_to() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_v1();
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let mut migration: LazyMigration<AccountV1, AccountV2> =
+            LazyMigration::try_from(&info).unwrap();
+
+        migration.into_inner_mut(AccountV2 {
+            data: 42,
+            new_field: 0,
+        });
+        migration.try_as_to_mut().unwrap().new_field = 7;
+        migration.exit(&TEST_OWNER).unwrap();
+
+        let stored = info.try_borrow_data().unwrap();
+        let mut stored: &[u8] = &stored;
+        assert_eq!(&stored[..8], &TEST_DISCRIMINATOR_V2);
+        assert_eq!(
+            AccountV2::try_deserialize_unchecked(&mut stored).unwrap(),
+            AccountV2 {
+                data: 42,
+                new_field: 7,
+            }
+        );
+    }
+
+    // Verifies that exiting without migrating returns an error.
+    #[test]
+    fn test_exit_fails_if_not_migrated() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_v1();
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let migration: LazyMigration<AccountV1, AccountV2> =
+            LazyMigration::try_from(&info).unwrap();
+
+        assert!(migration.exit(&TEST_OWNER).is_err());
+    }
+
+    // Verifies that accounts already in the `To` format are rejected.
+    #[test]
+    fn test_try_from_fails_with_to_format() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = vec![0u8; 100];
+        AccountV2 {
+            data: 42,
+            new_field: 1,
+        }
+        .try_serialize(&mut &mut data[..])
+        .unwrap();
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let result: Result<LazyMigration<AccountV1, AccountV2>> = LazyMigration::try_from(&info);
+
+        assert!(result.is_err());
+    }
+
+    // Verifies that deserialization fails when the account owner doesn't
+    // match the expected program.
+    #[test]
+    fn test_try_from_fails_with_wrong_owner() {
+        let key = Pubkey::default();
+        let wrong_owner = Pubkey::new_from_array([99u8; 32]);
+        let mut lamports = 100;
+        let mut data = serialize_v1();
+
+        let info = create_account_info(&key, &wrong_owner, &mut lamports, &mut data);
+        let result: Result<LazyMigration<AccountV1, AccountV2>> = LazyMigration::try_from(&info);
+
+        assert!(result.is_err());
+    }
+}
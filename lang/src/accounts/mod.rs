@@ -9,10 +9,14 @@ pub mod interface_account;
 pub mod migration;
 pub mod option;
 pub mod program;
+pub mod remaining;
 pub mod signer;
 pub mod system_account;
 pub mod sysvar;
 pub mod unchecked_account;
+pub mod versioned_migration;
 
 #[cfg(feature = "lazy-account")]
 pub mod lazy_account;
+#[cfg(feature = "lazy-account")]
+pub mod lazy_migration;
@@ -0,0 +1,212 @@
+//! Type deserializing every remaining account of the instruction as `T`
+
+use crate::*;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// Variable-length collection of typed accounts, consuming the rest of the
+/// instruction's accounts.
+///
+/// Every account is deserialized and validated by `T`, in the same way a
+/// field of type `T` would be. Because it consumes all accounts that are left,
+/// it must be the last field of an accounts struct.
+///
+/// Relations between the accounts of the collection and the rest of the struct
+/// can be validated with the `each::` constraints, which run once per account:
+///
+/// ```ignore
+/// #[derive(Accounts)]
+/// pub struct Settle<'info> {
+///     pub user: Signer<'info>,
+///     #[account(mut, each::has_one = user, each::unique)]
+///     pub positions: Remaining<'info, Account<'info, Position>>,
+/// }
+/// ```
+///
+/// Composite fields ending with `Remaining` accounts must also be the last
+/// field of their struct, which fails to compile otherwise:
+///
+/// ```ignore
+/// #[derive(Accounts)]
+/// pub struct Signers<'info> {
+///     pub signers: Remaining<'info, Signer<'info>>,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct Transfer<'info> {
+///     pub signers: Signers<'info>,
+///     // The accounts of `payer` would be consumed by `signers`
+///     pub payer: Signer<'info>,
+/// }
+/// ```
+///
+/// Checks:
+///
+/// - The checks of `T`, for every account
+#[derive(Debug, Clone)]
+pub struct Remaining<'info, T> {
+    accounts: Vec<T>,
+    _lifetime: PhantomData<&'info ()>,
+}
+
+impl<T> Remaining<'_, T> {
+    /// Creates a collection from already deserialized accounts.
+    pub fn new(accounts: Vec<T>) -> Self {
+        Self {
+            accounts,
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// Returns the accounts of the collection.
+    pub fn into_inner(self) -> Vec<T> {
+        self.accounts
+    }
+}
+
+impl<'info, B, T: Accounts<'info, B>> Accounts<'info, B> for Remaining<'info, T> {
+    #[inline(never)]
+    fn try_accounts(
+        program_id: &Pubkey,
+        accounts: &mut &'info [AccountInfo<'info>],
+        ix_data: &[u8],
+        bumps: &mut B,
+        reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        let mut items = Vec::with_capacity(accounts.len());
+        while !accounts.is_empty() {
+            items.push(T::try_accounts(
+                program_id, accounts, ix_data, bumps, reallocs,
+            )?);
+        }
+        Ok(Self::new(items))
+    }
+}
+
+impl<'info, T: AccountsExit<'info>> AccountsExit<'info> for Remaining<'info, T> {
+    fn exit(&self, program_id: &Pubkey) -> Result<()> {
+        self.accounts
+            .iter()
+            .try_for_each(|account| account.exit(program_id))
+    }
+}
+
+impl<T: ToAccountMetas> ToAccountMetas for Remaining<'_, T> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        self.accounts.to_account_metas(is_signer)
+    }
+}
+
+impl<'info, T: ToAccountInfos<'info>> ToAccountInfos<'info> for Remaining<'info, T> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        self.accounts.to_account_infos()
+    }
+}
+
+impl<T> Deref for Remaining<'_, T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.accounts
+    }
+}
+
+impl<T> DerefMut for Remaining<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.accounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::signer::Signer;
+    use crate::error::ErrorCode;
+
+    fn info<'a>(
+        key: &'a Pubkey,
+        is_signer: bool,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+        owner: &'a Pubkey,
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, is_signer, false, lamports, data, owner, false)
+    }
+
+    #[test]
+    fn consumes_every_account() {
+        let (key1, key2, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+        );
+        let (mut lamports1, mut lamports2) = (0, 0);
+        let (mut data1, mut data2) = ([0; 0], [0; 0]);
+        let infos = [
+            info(&key1, true, &mut lamports1, &mut data1, &owner),
+            info(&key2, true, &mut lamports2, &mut data2, &owner),
+        ];
+        let mut accounts = &infos[..];
+        let remaining = Remaining::<Signer>::try_accounts(
+            &Pubkey::default(),
+            &mut accounts,
+            &[],
+            &mut (),
+            &mut BTreeSet::new(),
+        )
+        .unwrap();
+
+        assert!(accounts.is_empty());
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[1].key(), key2);
+        assert_eq!(
+            remaining
+                .to_account_metas(None)
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect::<Vec<_>>(),
+            vec![key1, key2]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let mut accounts: &[AccountInfo] = &[];
+        let remaining = Remaining::<Signer>::try_accounts(
+            &Pubkey::default(),
+            &mut accounts,
+            &[],
+            &mut (),
+            &mut BTreeSet::new(),
+        )
+        .unwrap();
+
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn fails_on_invalid_account() {
+        let (key1, key2, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::default(),
+        );
+        let (mut lamports1, mut lamports2) = (0, 0);
+        let (mut data1, mut data2) = ([0; 0], [0; 0]);
+        let infos = [
+            info(&key1, true, &mut lamports1, &mut data1, &owner),
+            info(&key2, false, &mut lamports2, &mut data2, &owner),
+        ];
+        let mut accounts = &infos[..];
+        let err = Remaining::<Signer>::try_accounts(
+            &Pubkey::default(),
+            &mut accounts,
+            &[],
+            &mut (),
+            &mut BTreeSet::new(),
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ErrorCode::AccountNotSigner.into());
+    }
+}
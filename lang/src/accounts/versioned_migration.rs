@@ -0,0 +1,620 @@
+//! Account container for migrating through a chain of account versions.
+
+use crate::bpf_writer::BpfWriter;
+use crate::error::{Error, ErrorCode};
+use crate::solana_program::account_info::AccountInfo;
+use crate::solana_program::instruction::AccountMeta;
+use crate::solana_program::pubkey::Pubkey;
+use crate::solana_program::system_program;
+use crate::{
+    AccountDeserialize, AccountSerialize, Accounts, AccountsExit, Discriminator, Key, Owner,
+    Result, ToAccountInfos, ToAccountMetas,
+};
+use std::collections::BTreeSet;
+use std::ops::{Deref, DerefMut};
+
+/// A single version of an account layout that knows how to migrate from the
+/// version before it.
+///
+/// Versions form a chain through [`AccountVersion::Previous`]. The oldest
+/// version in the chain uses [`Initial`] as its previous version.
+///
+/// # Example
+/// ```ignore
+/// impl AccountVersion for AccountV1 {
+///     type Previous = Initial;
+///
+///     fn migrate(previous: Initial) -> Result<Self> {
+///         match previous {}
+///     }
+/// }
+///
+/// impl AccountVersion for AccountV2 {
+///     type Previous = AccountV1;
+///
+///     fn migrate(previous: AccountV1) -> Result<Self> {
+///         Ok(Self {
+///             data: previous.data,
+///             new_field: 0,
+///         })
+///     }
+/// }
+/// ```
+pub trait AccountVersion:
+    AccountSerialize + AccountDeserialize + Discriminator + Owner + VersionIdlBuild
+{
+    /// The version this one is migrated from.
+    type Previous: VersionChain;
+
+    /// Builds this version from the previous one.
+    fn migrate(previous: Self::Previous) -> Result<Self>;
+}
+
+/// Marker for the start of a version chain.
+///
+/// This type cannot be constructed, so [`AccountVersion::migrate`] of the
+/// oldest version is never called.
+#[derive(Debug)]
+pub enum Initial {}
+
+/// A chain of account versions, ending in `Self`.
+///
+/// This trait is implemented for every [`AccountVersion`] and for [`Initial`],
+/// and should not be implemented manually.
+pub trait VersionChain: Sized {
+    /// 1-based version number of `Self` in the chain (`0` for [`Initial`]).
+    const VERSION: u8;
+
+    /// Decodes `data` as any version of the chain and applies each migration
+    /// step up to `Self`, oldest first.
+    ///
+    /// Returns the migrated value along with the version the data was stored
+    /// in, or `None` if the discriminator doesn't match any version.
+    fn try_upgrade(data: &[u8]) -> Result<Option<(Self, u8)>>;
+
+    /// Inserts every version of the chain into the IDL accounts and types.
+    #[cfg(feature = "idl-build")]
+    fn insert_idl_versions(
+        accounts: &mut std::collections::BTreeMap<String, crate::idl::types::IdlAccount>,
+        types: &mut std::collections::BTreeMap<String, crate::idl::types::IdlTypeDef>,
+    );
+}
+
+impl VersionChain for Initial {
+    const VERSION: u8 = 0;
+
+    fn try_upgrade(_data: &[u8]) -> Result<Option<(Self, u8)>> {
+        Ok(None)
+    }
+
+    #[cfg(feature = "idl-build")]
+    fn insert_idl_versions(
+        _accounts: &mut std::collections::BTreeMap<String, crate::idl::types::IdlAccount>,
+        _types: &mut std::collections::BTreeMap<String, crate::idl::types::IdlTypeDef>,
+    ) {
+    }
+}
+
+impl<T: AccountVersion> VersionChain for T {
+    const VERSION: u8 = T::Previous::VERSION + 1;
+
+    fn try_upgrade(data: &[u8]) -> Result<Option<(Self, u8)>> {
+        if data.starts_with(T::DISCRIMINATOR) {
+            let mut data = data;
+            return Ok(Some((T::try_deserialize(&mut data)?, Self::VERSION)));
+        }
+
+        match T::Previous::try_upgrade(data)? {
+            Some((previous, version)) => Ok(Some((T::migrate(previous)?, version))),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "idl-build")]
+    fn insert_idl_versions(
+        accounts: &mut std::collections::BTreeMap<String, crate::idl::types::IdlAccount>,
+        types: &mut std::collections::BTreeMap<String, crate::idl::types::IdlTypeDef>,
+    ) {
+        T::Previous::insert_idl_versions(accounts, types);
+
+        if let Some(ty) = T::create_type() {
+            let account = crate::idl::types::IdlAccount {
+                name: ty.name.clone(),
+                discriminator: T::DISCRIMINATOR.into(),
+            };
+            accounts.insert(account.name.clone(), account);
+            types.insert(ty.name.clone(), ty);
+            T::insert_types(types);
+        }
+    }
+}
+
+/// Bound that requires [`IdlBuild`](crate::IdlBuild) for account versions
+/// only when the `idl-build` feature is enabled.
+#[cfg(feature = "idl-build")]
+pub trait VersionIdlBuild: crate::IdlBuild {}
+
+#[cfg(feature = "idl-build")]
+impl<T: crate::IdlBuild> VersionIdlBuild for T {}
+
+/// Bound that requires [`IdlBuild`](crate::IdlBuild) for account versions
+/// only when the `idl-build` feature is enabled.
+#[cfg(not(feature = "idl-build"))]
+pub trait VersionIdlBuild {}
+
+#[cfg(not(feature = "idl-build"))]
+impl<T> VersionIdlBuild for T {}
+
+/// Wrapper around [`AccountInfo`](crate::solana_program::account_info::AccountInfo)
+/// that migrates an account stored in any version of a chain to the latest one.
+///
+/// # Table of Contents
+/// - [Basic Functionality](#basic-functionality)
+/// - [Resizing](#resizing)
+/// - [Example](#example)
+///
+/// # Basic Functionality
+///
+/// Unlike [`Migration`](crate::accounts::migration::Migration), which handles a
+/// single `From -> To` step, `VersionedMigration` handles any number of steps.
+/// The stored version is detected from the account discriminator, and each
+/// [`AccountVersion::migrate`] step is applied in order (e.g. V1 -> V2 -> V3)
+/// during deserialization. Accounts that are already in the latest version are
+/// accepted as is.
+///
+/// The latest version is serialized to the account when the instruction exits.
+///
+/// Checks:
+///
+/// - `Account.info.owner == T::owner()`
+/// - `!(Account.info.owner == SystemProgram && Account.info.lamports() == 0)`
+/// - Account discriminator must match one of the versions in the chain
+///
+/// # Resizing
+///
+/// Newer versions are usually larger than older ones. Use the `realloc`
+/// constraint to resize the account in the same instruction. The account is
+/// resized after the stored data has been read, so the old layout is always
+/// decoded from the original buffer.
+///
+/// # Example
+/// ```ignore
+/// use anchor_lang::prelude::*;
+/// use anchor_lang::accounts::versioned_migration::{AccountVersion, Initial};
+///
+/// declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+///
+/// #[program]
+/// pub mod my_program {
+///     use super::*;
+///
+///     pub fn migrate(ctx: Context<MigrateAccount>) -> Result<()> {
+///         msg!("Stored version: {}", ctx.accounts.my_account.stored_version());
+///         ctx.accounts.my_account.extra = 42;
+///         Ok(())
+///     }
+/// }
+///
+/// #[account]
+/// pub struct AccountV1 {
+///     pub data: u64,
+/// }
+///
+/// #[account]
+/// #[derive(InitSpace)]
+/// pub struct AccountV2 {
+///     pub data: u64,
+///     pub extra: u64,
+/// }
+///
+/// impl AccountVersion for AccountV1 {
+///     type Previous = Initial;
+///
+///     fn migrate(previous: Initial) -> Result<Self> {
+///         match previous {}
+///     }
+/// }
+///
+/// impl AccountVersion for AccountV2 {
+///     type Previous = AccountV1;
+///
+///     fn migrate(previous: AccountV1) -> Result<Self> {
+///         Ok(Self {
+///             data: previous.data,
+///             extra: 0,
+///         })
+///     }
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct MigrateAccount<'info> {
+///     #[account(mut)]
+///     pub payer: Signer<'info>,
+///     #[account(
+///         mut,
+///         realloc = 8 + AccountV2::INIT_SPACE,
+///         realloc::payer = payer,
+///         realloc::zero = false
+///     )]
+///     pub my_account: VersionedMigration<'info, AccountV2>,
+///     pub system_program: Program<'info, System>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VersionedMigration<'info, T: AccountVersion> {
+    /// Account info reference
+    info: &'info AccountInfo<'info>,
+    /// Account data in the latest version
+    account: T,
+    /// Version the account was stored in before migration
+    stored_version: u8,
+}
+
+impl<'info, T: AccountVersion> VersionedMigration<'info, T> {
+    /// Returns the version the account was stored in, where the oldest
+    /// version of the chain is `1`.
+    #[inline(always)]
+    pub fn stored_version(&self) -> u8 {
+        self.stored_version
+    }
+
+    /// Returns `true` if the account was stored in an older version and has
+    /// been migrated to the latest one.
+    #[inline(always)]
+    pub fn is_migrated(&self) -> bool {
+        self.stored_version != T::VERSION
+    }
+
+    /// Returns the account data in the latest version.
+    pub fn into_inner(self) -> T {
+        self.account
+    }
+
+    /// Deserializes the given `info` into a `VersionedMigration`, migrating it
+    /// to the latest version if needed.
+    #[inline(never)]
+    pub fn try_from(info: &'info AccountInfo<'info>) -> Result<Self> {
+        if info.owner == &system_program::ID && info.lamports() == 0 {
+            return Err(ErrorCode::AccountNotInitialized.into());
+        }
+
+        if info.owner != &T::owner() {
+            return Err(Error::from(ErrorCode::AccountOwnedByWrongProgram)
+                .with_pubkeys((*info.owner, T::owner())));
+        }
+
+        let data: &[u8] = &info.try_borrow_data()?;
+        match T::try_upgrade(data)? {
+            Some((account, stored_version)) => Ok(Self {
+                info,
+                account,
+                stored_version,
+            }),
+            None if data.len() < T::DISCRIMINATOR.len() => {
+                Err(ErrorCode::AccountDiscriminatorNotFound.into())
+            }
+            None => Err(ErrorCode::AccountDiscriminatorMismatch.into()),
+        }
+    }
+}
+
+impl<'info, B, T: AccountVersion> Accounts<'info, B> for VersionedMigration<'info, T> {
+    #[inline(never)]
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &'info [AccountInfo<'info>],
+        _ix_data: &[u8],
+        _bumps: &mut B,
+        _reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        let account = &accounts[0];
+        *accounts = &accounts[1..];
+        Self::try_from(account)
+    }
+}
+
+impl<'info, T: AccountVersion> AccountsExit<'info> for VersionedMigration<'info, T> {
+    fn exit(&self, program_id: &Pubkey) -> Result<()> {
+        // Check if account is closed
+        if crate::common::is_closed(self.info) {
+            return Ok(());
+        }
+
+        // Only persist if the owner is the current program
+        if &T::owner() != program_id {
+            return Ok(());
+        }
+
+        let mut data = self.info.try_borrow_mut_data()?;
+        let dst: &mut [u8] = &mut data;
+        let mut writer = BpfWriter::new(dst);
+        self.account.try_serialize(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+impl<T: AccountVersion> ToAccountMetas for VersionedMigration<'_, T> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let is_signer = is_signer.unwrap_or(self.info.is_signer);
+        let meta = match self.info.is_writable {
+            false => AccountMeta::new_readonly(*self.info.key, is_signer),
+            true => AccountMeta::new(*self.info.key, is_signer),
+        };
+        vec![meta]
+    }
+}
+
+impl<'info, T: AccountVersion> ToAccountInfos<'info> for VersionedMigration<'info, T> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![self.info.clone()]
+    }
+}
+
+impl<'info, T: AccountVersion> AsRef<AccountInfo<'info>> for VersionedMigration<'info, T> {
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        self.info
+    }
+}
+
+impl<T: AccountVersion> Key for VersionedMigration<'_, T> {
+    fn key(&self) -> Pubkey {
+        *self.info.key
+    }
+}
+
+impl<T: AccountVersion> Deref for VersionedMigration<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.account
+    }
+}
+
+impl<T: AccountVersion> DerefMut for VersionedMigration<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.account
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnchorDeserialize, AnchorSerialize};
+
+    const TEST_OWNER: Pubkey = Pubkey::new_from_array([1u8; 32]);
+
+    macro_rules! test_account {
+        ($name:ident, $disc:expr) => {
+            impl Discriminator for $name {
+                const DISCRIMINATOR: &'static [u8] = &$disc;
+            }
+
+            impl Owner for $name {
+                fn owner() -> Pubkey {
+                    TEST_OWNER
+                }
+            }
+
+            impl AccountSerialize for $name {
+                fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+                    writer.write_all(Self::DISCRIMINATOR)?;
+                    AnchorSerialize::serialize(self, writer)?;
+                    Ok(())
+                }
+            }
+
+            impl AccountDeserialize for $name {
+                fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+                    if !buf.starts_with(Self::DISCRIMINATOR) {
+                        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+                    }
+                    Self::try_deserialize_unchecked(buf)
+                }
+
+                fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+                    let mut data = &buf[8..];
+                    AnchorDeserialize::deserialize(&mut data)
+                        .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+                }
+            }
+        };
+    }
+
+    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
+    struct AccountV1 {
+        pub data: u64,
+    }
+    test_account!(AccountV1, [1, 0, 0, 0, 0, 0, 0, 0]);
+
+    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
+    struct AccountV2 {
+        pub data: u64,
+        pub doubled: u64,
+    }
+    test_account!(AccountV2, [2, 0, 0, 0, 0, 0, 0, 0]);
+
+    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
+    struct AccountV3 {
+        pub data: u64,
+        pub doubled: u64,
+        pub flag: bool,
+    }
+    test_account!(AccountV3, [3, 0, 0, 0, 0, 0, 0, 0]);
+
+    impl AccountVersion for AccountV1 {
+        type Previous = Initial;
+
+        fn migrate(previous: Initial) -> Result<Self> {
+            match previous {}
+        }
+    }
+
+    impl AccountVersion for AccountV2 {
+        type Previous = AccountV1;
+
+        fn migrate(previous: AccountV1) -> Result<Self> {
+            Ok(Self {
+                data: previous.data,
+                doubled: previous.data * 2,
+            })
+        }
+    }
+
+    impl AccountVersion for AccountV3 {
+        type Previous = AccountV2;
+
+        fn migrate(previous: AccountV2) -> Result<Self> {
+            Ok(Self {
+                data: previous.data,
+                doubled: previous.doubled,
+                flag: true,
+            })
+        }
+    }
+
+    fn serialize_account<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = vec![0u8; 100];
+        account.try_serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    fn create_account_info<'a>(
+        key: &'a Pubkey,
+        owner: &'a Pubkey,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, false, true, lamports, data, owner, false)
+    }
+
+    // Verifies that version numbers follow the chain order.
+    #[test]
+    fn test_versions_are_numbered_in_chain_order() {
+        assert_eq!(<AccountV1 as VersionChain>::VERSION, 1);
+        assert_eq!(<AccountV2 as VersionChain>::VERSION, 2);
+        assert_eq!(<AccountV3 as VersionChain>::VERSION, 3);
+    }
+
+    // Verifies that an account stored in the oldest version goes through
+    // every migration step in order.
+    #[test]
+    fn test_try_from_migrates_through_every_step() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_account(&AccountV1 { data: 21 });
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let migration: VersionedMigration<AccountV3> = VersionedMigration::try_from(&info).unwrap();
+
+        assert_eq!(migration.stored_version(), 1);
+        assert!(migration.is_migrated());
+        assert_eq!(
+            *migration,
+            AccountV3 {
+                data: 21,
+                doubled: 42,
+                flag: true,
+            }
+        );
+    }
+
+    // Verifies that an account stored in an intermediate version only goes
+    // through the remaining steps.
+    #[test]
+    fn test_try_from_migrates_from_intermediate_version() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_account(&AccountV2 {
+            data: 1,
+            doubled: 7,
+        });
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let migration: VersionedMigration<AccountV3> = VersionedMigration::try_from(&info).unwrap();
+
+        assert_eq!(migration.stored_version(), 2);
+        assert_eq!(migration.doubled, 7);
+        assert!(migration.flag);
+    }
+
+    // Verifies that accounts already in the latest version are accepted
+    // without migration.
+    #[test]
+    fn test_try_from_accepts_latest_version() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_account(&AccountV3 {
+            data: 1,
+            doubled: 2,
+            flag: false,
+        });
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let migration: VersionedMigration<AccountV3> = VersionedMigration::try_from(&info).unwrap();
+
+        assert_eq!(migration.stored_version(), 3);
+        assert!(!migration.is_migrated());
+        assert!(!migration.flag);
+    }
+
+    // Verifies that exit serializes the latest version into the account.
+    #[test]
+    fn test_exit_writes_latest_version() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = serialize_account(&AccountV1 { data: 5 });
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let mut migration: VersionedMigration<AccountV3> =
+            VersionedMigration::try_from(&info).unwrap();
+        migration.data = 6;
+        migration.exit(&TEST_OWNER).unwrap();
+
+        let stored = info.try_borrow_data().unwrap();
+        let mut stored: &[u8] = &stored;
+        let account = AccountV3::try_deserialize(&mut stored).unwrap();
+        assert_eq!(
+            account,
+            AccountV3 {
+                data: 6,
+                doubled: 10,
+                flag: true,
+            }
+        );
+    }
+
+    // Verifies that discriminators outside of the chain are rejected.
+    #[test]
+    fn test_try_from_fails_with_unknown_discriminator() {
+        let key = Pubkey::default();
+        let mut lamports = 100;
+        let mut data = vec![9u8; 100];
+
+        let info = create_account_info(&key, &TEST_OWNER, &mut lamports, &mut data);
+        let result: Result<VersionedMigration<AccountV3>> = VersionedMigration::try_from(&info);
+
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    // Verifies that deserialization fails when the account owner doesn't
+    // match the expected program.
+    #[test]
+    fn test_try_from_fails_with_wrong_owner() {
+        let key = Pubkey::default();
+        let wrong_owner = Pubkey::new_from_array([99u8; 32]);
+        let mut lamports = 100;
+        let mut data = serialize_account(&AccountV1 { data: 1 });
+
+        let info = create_account_info(&key, &wrong_owner, &mut lamports, &mut data);
+        let result: Result<VersionedMigration<AccountV3>> = VersionedMigration::try_from(&info);
+
+        assert!(result.is_err());
+    }
+}
@@ -7,6 +7,13 @@ use std::num::TryFromIntError;
 /// The starting point for user defined error codes.
 pub const ERROR_CODE_OFFSET: u32 = 6000;
 
+/// Error enums generated by [`error_code`], which can be decoded from their error number, e.g.
+/// the custom error of a failed instruction.
+pub trait FromErrorCode: Sized {
+    /// Returns the error with the given error number, `None` if it's not one of the enum's.
+    fn from_error_code(code: u32) -> Option<Self>;
+}
+
 /// Error codes that can be returned by internal framework code.
 ///
 /// - &gt;= 100 Instruction error codes
@@ -180,6 +187,14 @@ pub enum ErrorCode {
     #[msg("Account must be migrated before exiting")]
     AccountNotMigrated,
 
+    // Remaining accounts errors
+    /// 2043 - An account appears more than once in a collection
+    #[msg("An account appears more than once in a collection")]
+    ConstraintUnique,
+    /// 2044 - The number of remaining accounts is out of bounds
+    #[msg("The number of remaining accounts is out of bounds")]
+    ConstraintRemainingLen,
+
     // Require
     /// 2500 - A require expression was violated
     #[msg("A require expression was violated")]
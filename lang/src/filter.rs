@@ -0,0 +1,55 @@
+//! `getProgramAccounts` filters, built off-chain with the filter builder `#[account(filter)]`
+//! generates.
+
+use crate::solana_program::pubkey::Pubkey;
+
+/// Filter matching the accounts whose data at `offset` equals `bytes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memcmp {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl Memcmp {
+    pub fn new(offset: usize, bytes: Vec<u8>) -> Self {
+        Self { offset, bytes }
+    }
+}
+
+/// Types with a fixed Borsh serialized size, used by the filter builder to compute the offsets
+/// of the fields following them.
+pub trait FixedSize {
+    /// Size of the serialized value in bytes
+    const SIZE: usize;
+}
+
+macro_rules! impl_fixed_size {
+    ($($ty:ty => $size:expr),* $(,)?) => {
+        $(
+            impl FixedSize for $ty {
+                const SIZE: usize = $size;
+            }
+        )*
+    };
+}
+
+impl_fixed_size!(
+    bool => 1,
+    u8 => 1,
+    i8 => 1,
+    u16 => 2,
+    i16 => 2,
+    u32 => 4,
+    i32 => 4,
+    f32 => 4,
+    u64 => 8,
+    i64 => 8,
+    f64 => 8,
+    u128 => 16,
+    i128 => 16,
+    Pubkey => 32,
+);
+
+impl<T: FixedSize, const N: usize> FixedSize for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+}
@@ -41,6 +41,7 @@ pub mod context;
 pub mod error;
 #[doc(hidden)]
 pub mod event;
+pub mod filter;
 #[doc(hidden)]
 pub mod idl;
 pub mod system_program;
@@ -494,9 +495,10 @@ pub mod prelude {
         access_control, account, accounts::account::Account,
         accounts::account_loader::AccountLoader, accounts::interface::Interface,
         accounts::interface_account::InterfaceAccount, accounts::migration::Migration,
-        accounts::program::Program, accounts::signer::Signer,
+        accounts::program::Program, accounts::remaining::Remaining, accounts::signer::Signer,
         accounts::system_account::SystemAccount, accounts::sysvar::Sysvar,
-        accounts::unchecked_account::UncheckedAccount, constant, context::Context,
+        accounts::unchecked_account::UncheckedAccount,
+        accounts::versioned_migration::VersionedMigration, constant, context::Context,
         context::CpiContext, declare_id, declare_program, emit, err, error, event, instruction,
         program, pubkey, require, require_eq, require_gt, require_gte, require_keys_eq,
         require_keys_neq, require_neq,
@@ -534,7 +536,7 @@ pub mod prelude {
     pub use super::idl::IdlBuild;
 
     #[cfg(feature = "lazy-account")]
-    pub use super::accounts::lazy_account::LazyAccount;
+    pub use super::accounts::{lazy_account::LazyAccount, lazy_migration::LazyMigration};
 }
 
 /// Internal module used by macros and unstable apis.
@@ -588,6 +590,24 @@ pub mod __private {
     pub trait IsSameType<T> {}
 
     impl<T> IsSameType<T> for T {}
+
+    /// Trait of the accounts structs that don't end with `Remaining` accounts, directly or through
+    /// their last composite field.
+    /// Used to enforce that composite fields consuming every account left are the last field.
+    #[doc(hidden)]
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` ends with `Remaining` accounts, which consume every account left",
+        label = "composite field followed by other fields",
+        note = "composite fields ending with `Remaining` accounts must be the last field of the struct"
+    )]
+    pub trait BoundedAccounts {}
+
+    impl<T: BoundedAccounts> BoundedAccounts for Box<T> {}
+
+    /// Fails to compile if `T` ends with `Remaining` accounts.
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn assert_bounded_accounts<T: BoundedAccounts>() {}
 }
 
 /// Ensures a condition is true, otherwise returns with the given error.
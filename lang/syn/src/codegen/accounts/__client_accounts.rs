@@ -67,6 +67,11 @@ pub fn generate(
                         #docs
                         pub #name: Option<Pubkey>
                     }
+                } else if matches!(f.ty, Ty::Remaining(_)) {
+                    quote! {
+                        #docs
+                        pub #name: Vec<Pubkey>
+                    }
                 } else {
                     quote! {
                         #docs
@@ -88,8 +93,9 @@ pub fn generate(
                 }
             }
             AccountField::Field(f) => {
-                let is_signer = match f.ty {
+                let is_signer = match &f.ty {
                     Ty::Signer => true,
+                    Ty::Remaining(ty) => matches!(*ty.ty, Ty::Signer),
                     _ => f.constraints.is_signer(),
                 };
                 let is_signer = match is_signer {
@@ -109,6 +115,10 @@ pub fn generate(
                             account_metas.push(anchor_lang::solana_program::instruction::AccountMeta::new_readonly(#program_id, false));
                         }
                     }
+                } else if matches!(f.ty, Ty::Remaining(_)) {
+                    quote! {
+                        account_metas.extend(self.#name.iter().map(|key| #meta(*key, #is_signer)));
+                    }
                 } else {
                     quote! {
                         account_metas.push(#meta(self.#name, #is_signer));
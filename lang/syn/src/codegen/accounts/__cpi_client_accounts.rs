@@ -68,6 +68,11 @@ pub fn generate(
                         #docs
                         pub #name: Option<anchor_lang::solana_program::account_info::AccountInfo<'info>>
                     }
+                } else if matches!(f.ty, Ty::Remaining(_)) {
+                    quote! {
+                        #docs
+                        pub #name: Vec<anchor_lang::solana_program::account_info::AccountInfo<'info>>
+                    }
                 } else {
                     quote! {
                         #docs
@@ -89,8 +94,9 @@ pub fn generate(
                 }
             }
             AccountField::Field(f) => {
-                let is_signer = match f.ty {
+                let is_signer = match &f.ty {
                     Ty::Signer => true,
+                    Ty::Remaining(ty) => matches!(*ty.ty, Ty::Signer),
                     _ => f.constraints.is_signer(),
                 };
                 let is_signer = match is_signer {
@@ -110,6 +116,10 @@ pub fn generate(
                             account_metas.push(anchor_lang::solana_program::instruction::AccountMeta::new_readonly(#program_id, false));
                         }
                     }
+                } else if matches!(f.ty, Ty::Remaining(_)) {
+                    quote! {
+                        account_metas.extend(self.#name.iter().map(|info| #meta(anchor_lang::Key::key(info), #is_signer)));
+                    }
                 } else {
                     quote! {
                         account_metas.push(#meta(anchor_lang::Key::key(&self.#name), #is_signer));
@@ -4,6 +4,10 @@ use std::collections::HashSet;
 use crate::*;
 
 pub fn generate(f: &Field, accs: &AccountsStruct) -> proc_macro2::TokenStream {
+    if matches!(f.ty, Ty::Remaining(_)) {
+        return generate_constraint_remaining(f, accs);
+    }
+
     let constraints = linearize(&f.constraints);
 
     let rent = if constraints
@@ -90,6 +94,9 @@ pub fn linearize(c_group: &ConstraintGroup) -> Vec<Constraint> {
         token_account,
         mint,
         realloc,
+        each,
+        // Checked while deserializing the `Remaining` accounts.
+        remaining: _,
     } = c_group.clone();
 
     let mut constraints = Vec::new();
@@ -141,6 +148,9 @@ pub fn linearize(c_group: &ConstraintGroup) -> Vec<Constraint> {
     if let Some(c) = mint {
         constraints.push(Constraint::Mint(c));
     }
+    if let Some(c) = each {
+        constraints.push(Constraint::Each(c));
+    }
     constraints
 }
 
@@ -167,6 +177,9 @@ fn generate_constraint(
         Constraint::TokenAccount(c) => generate_constraint_token_account(f, c, accs),
         Constraint::Mint(c) => generate_constraint_mint(f, c, accs),
         Constraint::Realloc(c) => generate_constraint_realloc(f, c, accs),
+        Constraint::Each(_) => unreachable!(
+            "each:: constraints are only parsed on Remaining fields, which are generated separately"
+        ),
     }
 }
 
@@ -177,6 +190,102 @@ fn generate_constraint_composite(f: &CompositeField, c: &Constraint) -> proc_mac
     }
 }
 
+// Runs the constraints of a `Remaining` field on every account of the collection. Inside the
+// loop, the field's identifier refers to the current account, and errors are reported as
+// `<field>[<index>]`.
+fn generate_constraint_remaining(f: &Field, accs: &AccountsStruct) -> proc_macro2::TokenStream {
+    let ident = &f.ident;
+    let name_str = ident.to_string();
+    let mut optional_check_scope = OptionalCheckScope::new_with_field(accs, ident);
+    let c = f.constraints.each.clone().unwrap_or_default();
+
+    let mutable = f.constraints.mutable.as_ref().map(|c| {
+        let error = generate_custom_error(ident, &c.error, quote! { ConstraintMut }, &None);
+        quote! {
+            if !AsRef::<AccountInfo>::as_ref(#ident).is_writable {
+                return #error;
+            }
+        }
+    });
+    let (keys, unique) = if c.unique {
+        let error = generate_custom_error(ident, &None, quote! { ConstraintUnique }, &None);
+        (
+            quote! { let mut __anchor_keys = std::collections::BTreeSet::new(); },
+            quote! {
+                if !__anchor_keys.insert(#ident.key()) {
+                    return #error;
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let has_one = c.has_one.iter().map(|c| {
+        let target = &c.join_target;
+        let target_optional_check = optional_check_scope.generate_check(target);
+        let error = generate_custom_error(
+            ident,
+            &c.error,
+            quote! { ConstraintHasOne },
+            &Some(&(quote! { my_key }, quote! { target_key })),
+        );
+        quote! {
+            {
+                #target_optional_check
+                let my_key = #ident.#target;
+                let target_key = #target.key();
+                if my_key != target_key {
+                    return #error;
+                }
+            }
+        }
+    });
+    let owner = c.owner.as_ref().map(|c| {
+        let owner_address = &c.owner_address;
+        let error = generate_custom_error(
+            ident,
+            &c.error,
+            quote! { ConstraintOwner },
+            &Some(&(quote! { *my_owner }, quote! { owner_address })),
+        );
+        quote! {
+            {
+                let my_owner = AsRef::<AccountInfo>::as_ref(#ident).owner;
+                let owner_address = #owner_address;
+                if my_owner != &owner_address {
+                    return #error;
+                }
+            }
+        }
+    });
+    let raw = c
+        .raw
+        .iter()
+        .map(|c| generate_constraint_raw(ident, c))
+        .collect::<Vec<_>>();
+    if mutable.is_none() && !c.unique && c.has_one.is_empty() && c.owner.is_none() && raw.is_empty()
+    {
+        return quote! {};
+    }
+
+    quote! {
+        {
+            #keys
+            for (__anchor_index, #ident) in #ident.iter().enumerate() {
+                (|| -> anchor_lang::Result<()> {
+                    #mutable
+                    #unique
+                    #(#has_one)*
+                    #owner
+                    #(#raw)*
+                    Ok(())
+                })()
+                .map_err(|e| e.with_account_name(format!("{}[{}]", #name_str, __anchor_index)))?;
+            }
+        }
+    }
+}
+
 fn generate_constraint_address(f: &Field, c: &ConstraintAddress) -> proc_macro2::TokenStream {
     let field = &f.ident;
     let addr = &c.address;
@@ -89,7 +89,9 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                     crate::Ty::Account(_)
                     | crate::Ty::LazyAccount(_)
                     | crate::Ty::InterfaceAccount(_)
-                    | crate::Ty::Migration(_) => {
+                    | crate::Ty::Migration(_)
+                    | crate::Ty::LazyMigration(_)
+                    | crate::Ty::VersionedMigration(_) => {
                         let field_name = &f.ident;
                         // Optional accounts need an if-let guard.
                         if f.is_optional {
@@ -104,6 +106,22 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                             })
                         }
                     }
+                    crate::Ty::Remaining(ty)
+                        if matches!(
+                            *ty.ty,
+                            crate::Ty::Account(_)
+                                | crate::Ty::LazyAccount(_)
+                                | crate::Ty::InterfaceAccount(_)
+                                | crate::Ty::Migration(_)
+                                | crate::Ty::LazyMigration(_)
+                                | crate::Ty::VersionedMigration(_)
+                        ) =>
+                    {
+                        let field_name = &f.ident;
+                        Some(quote! {
+                            keys.extend(self.#field_name.iter().map(|account| account.key()));
+                        })
+                    }
                     // Non-serializing types (e.g. AccountLoader) — skip.
                     _ => None,
                 }
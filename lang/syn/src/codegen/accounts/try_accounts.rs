@@ -1,6 +1,7 @@
 use crate::codegen::accounts::{bumps, constraints, generics, ParsedGenerics};
 use crate::{AccountField, AccountsStruct, Ty};
 use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
 use syn::Expr;
 
 // Generates the `Accounts` trait implementation.
@@ -17,14 +18,22 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
     let deser_fields: Vec<proc_macro2::TokenStream> = accs
         .fields
         .iter()
-        .map(|af: &AccountField| {
+        .enumerate()
+        .map(|(i, af)| {
             match af {
                 AccountField::CompositeField(s) => {
                     let name = &s.ident;
                     let ty = &s.raw_field.ty;
+                    // Only the last field can consume every account left
+                    let bounded_check = (i != accs.fields.len() - 1).then(|| {
+                        quote_spanned! { ty.span() =>
+                            anchor_lang::__private::assert_bounded_accounts::<#ty>();
+                        }
+                    });
                     quote! {
                         #[cfg(feature = "anchor-debug")]
                         ::anchor_lang::solana_program::log::sol_log(stringify!(#name));
+                        #bounded_check
                         let #name: #ty = anchor_lang::Accounts::try_accounts(__program_id, __accounts, __ix_data, &mut __bumps.#name, __reallocs)?;
                     }
                 }
@@ -65,6 +74,38 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                                 *__accounts = &__accounts[1..];
                             }
                         }
+                    } else if matches!(f.ty, Ty::Remaining(_)) {
+                        // Deserialize the accounts one by one in order to report the failing index.
+                        let ident = &f.ident;
+                        let name = ident.to_string();
+                        let typed_name = f.typed_ident();
+                        let container_ty = f.container_ty();
+                        let account_ty = f.account_ty();
+                        let bounds = f.constraints.remaining.iter().flat_map(|c| {
+                            let min = c.min.as_ref().map(|min| (quote! { < }, min));
+                            let max = c.max.as_ref().map(|max| (quote! { > }, max));
+                            min.into_iter().chain(max)
+                        }).map(|(op, bound)| quote! {
+                            if __accounts.len() #op (#bound) as usize {
+                                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintRemainingLen)
+                                    .with_account_name(#name)
+                                    .with_values((__accounts.len(), #bound)));
+                            }
+                        });
+                        quote! {
+                            #[cfg(feature = "anchor-debug")]
+                            ::anchor_lang::solana_program::log::sol_log(stringify!(#typed_name));
+                            #(#bounds)*
+                            let #typed_name = {
+                                let mut #ident: Vec<#account_ty> = Vec::with_capacity(__accounts.len());
+                                while !__accounts.is_empty() {
+                                    let account = anchor_lang::Accounts::try_accounts(__program_id, __accounts, __ix_data, __bumps, __reallocs)
+                                        .map_err(|e| e.with_account_name(format!("{}[{}]", #name, #ident.len())))?;
+                                    #ident.push(account);
+                                }
+                                #container_ty::new(#ident)
+                            };
+                        }
                     } else {
                         let name = f.ident.to_string();
                         let typed_name = f.typed_ident();
@@ -226,8 +267,27 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         }
     };
 
+    // Structs ending with `Remaining` accounts consume every account left
+    let impl_bounded_accounts = match accs.fields.last() {
+        Some(AccountField::Field(f)) if matches!(f.ty, Ty::Remaining(_)) => quote! {},
+        last => {
+            let mut where_clause = where_clause.clone();
+            if let Some(AccountField::CompositeField(s)) = last {
+                let ty = &s.raw_field.ty;
+                where_clause
+                    .predicates
+                    .push(syn::parse_quote! { #ty: anchor_lang::__private::BoundedAccounts });
+            }
+            quote! {
+                #[automatically_derived]
+                impl<#combined_generics> anchor_lang::__private::BoundedAccounts for #name<#struct_generics> #where_clause {}
+            }
+        }
+    };
+
     quote! {
         #param_count_const
+        #impl_bounded_accounts
         #[automatically_derived]
         impl<#combined_generics> anchor_lang::Accounts<#trait_generics, #bumps_struct_name> for #name<#struct_generics> #where_clause {
             #[inline(never)]
@@ -326,6 +386,17 @@ fn generate_duplicate_mutable_checks(accs: &AccountsStruct) -> proc_macro2::Toke
     // Only types that serialize on exit are included, as duplicate mutable accounts
     // are problematic due to double serialization (the second write overwrites the first).
     // Types like UncheckedAccount, Signer, SystemAccount, AccountLoader, etc. don't serialize on exit
+    let serializes_on_exit = |ty: &Ty| {
+        matches!(
+            ty,
+            Ty::Account(_)
+                | Ty::LazyAccount(_)
+                | Ty::InterfaceAccount(_)
+                | Ty::Migration(_)
+                | Ty::LazyMigration(_)
+                | Ty::VersionedMigration(_)
+        )
+    };
     let candidates: Vec<_> = accs
         .fields
         .iter()
@@ -336,11 +407,10 @@ fn generate_duplicate_mutable_checks(accs: &AccountsStruct) -> proc_macro2::Toke
                     && !f.constraints.is_pure_init() =>
             {
                 match &f.ty {
+                    // `Remaining` accounts are included if their type serializes on exit
+                    Ty::Remaining(ty) if serializes_on_exit(&ty.ty) => Some(f),
                     // Only include types that serialize on exit
-                    crate::Ty::Account(_)
-                    | crate::Ty::LazyAccount(_)
-                    | crate::Ty::InterfaceAccount(_)
-                    | crate::Ty::Migration(_) => Some(f),
+                    ty if serializes_on_exit(ty) => Some(f),
                     _ => None,
                 }
             }
@@ -364,10 +434,24 @@ fn generate_duplicate_mutable_checks(accs: &AccountsStruct) -> proc_macro2::Toke
 
     let mut field_keys = Vec::with_capacity(candidates.len());
     let mut field_name_strs = Vec::with_capacity(candidates.len());
+    let mut remaining_checks = Vec::new();
 
     for f in candidates.iter() {
         let name = &f.ident;
 
+        if matches!(f.ty, Ty::Remaining(_)) {
+            remaining_checks.push(quote! {
+                for (index, account) in #name.iter().enumerate() {
+                    if !__mutable_accounts.insert(account.key()) {
+                        return Err(anchor_lang::error::Error::from(
+                            anchor_lang::error::ErrorCode::ConstraintDuplicateMutableAccount
+                        ).with_account_name(format!("{}[{}]", stringify!(#name), index)));
+                    }
+                }
+            });
+            continue;
+        }
+
         if f.is_optional {
             field_keys.push(quote! { #name.as_ref().map(|f| f.key()) });
         } else {
@@ -411,6 +495,9 @@ fn generate_duplicate_mutable_checks(accs: &AccountsStruct) -> proc_macro2::Toke
                 }
             )*
 
+            // Check every `Remaining` account for duplicates
+            #(#remaining_checks)*
+
             // Check composite (nested) account struct keys for duplicates
             #(#composite_checks)*
         }
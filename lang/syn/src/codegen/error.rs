@@ -47,6 +47,21 @@ pub fn generate(error: Error) -> proc_macro2::TokenStream {
         })
         .collect();
 
+    // Each check of the `from_error_code` function on the user defined error code.
+    let from_code_variant_dispatch: Vec<proc_macro2::TokenStream> = error
+        .raw_enum
+        .variants
+        .iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            quote! {
+                if code == u32::from(#enum_name::#ident) {
+                    return Some(#enum_name::#ident);
+                }
+            }
+        })
+        .collect();
+
     let offset = match &error.args {
         None => quote! { anchor_lang::error::ERROR_CODE_OFFSET},
         Some(args) => {
@@ -75,6 +90,13 @@ pub fn generate(error: Error) -> proc_macro2::TokenStream {
             }
         }
 
+        impl anchor_lang::error::FromErrorCode for #enum_name {
+            fn from_error_code(code: u32) -> Option<Self> {
+                #(#from_code_variant_dispatch)*
+                None
+            }
+        }
+
         impl From<#enum_name> for anchor_lang::error::Error {
             fn from(error_code: #enum_name) -> anchor_lang::error::Error {
                 anchor_lang::error::Error::from(
@@ -16,14 +16,32 @@ pub fn gen_idl_build_impl_accounts_struct(accounts: &AccountsStruct) -> TokenStr
     let ident = &accounts.ident;
     let (impl_generics, ty_generics, where_clause) = accounts.generics.split_for_impl();
 
+    // Versioned migrations insert every version of their chain
+    let versioned = accounts
+        .fields
+        .iter()
+        .filter_map(|acc| match acc {
+            AccountField::Field(acc) => match &acc.ty {
+                Ty::VersionedMigration(ty) => Some(&ty.account_type_path),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
     let (accounts, defined) = accounts
         .fields
         .iter()
         .map(|acc| match acc {
             AccountField::Field(acc) => {
                 let name = acc.ident.to_string();
+                // `Remaining` accounts are described by the type of every account
+                let acc_ty = match &acc.ty {
+                    Ty::Remaining(ty) => &*ty.ty,
+                    ty => ty,
+                };
                 let writable = acc.constraints.is_mutable();
-                let signer = match acc.ty {
+                let signer = match acc_ty {
                     Ty::Signer => true,
                     _ => acc.constraints.is_signer(),
                 };
@@ -43,7 +61,7 @@ pub fn gen_idl_build_impl_accounts_struct(accounts: &AccountsStruct) -> TokenStr
                     (quote! { None }, quote! { None }, quote! { vec![] })
                 };
 
-                let defined = match &acc.ty {
+                let defined = match acc_ty {
                     Ty::Account(ty)
                     // Skip `UpgradeableLoaderState` type for now until `bincode` serialization
                     // is supported.
@@ -75,6 +93,42 @@ pub fn gen_idl_build_impl_accounts_struct(accounts: &AccountsStruct) -> TokenStr
                     _ => None,
                 };
 
+                if let Ty::Remaining(_) = &acc.ty {
+                    let account = match &defined {
+                        Some((defined, _)) => quote! { <#defined>::create_type().map(|ty| ty.name) },
+                        None => quote! { None },
+                    };
+                    let (min, max) = acc
+                        .constraints
+                        .remaining
+                        .as_ref()
+                        .map(|c| (c.min.as_ref(), c.max.as_ref()))
+                        .unwrap_or_default();
+                    let min = min
+                        .map(|min| quote! { (#min) as u32 })
+                        .unwrap_or_else(|| quote! { 0 });
+                    let max = max
+                        .map(|max| quote! { Some((#max) as u32) })
+                        .unwrap_or_else(|| quote! { None });
+
+                    return (
+                        quote! {
+                            #idl::IdlInstructionAccountItem::Remaining(#idl::IdlInstructionRemainingAccounts {
+                                name: #name.into(),
+                                docs: #docs,
+                                writable: #writable,
+                                signer: #signer,
+                                account: #account,
+                                remaining: #idl::IdlRemainingBounds {
+                                    min: #min,
+                                    max: #max,
+                                },
+                            })
+                        },
+                        defined,
+                    );
+                }
+
                 (
                     quote! {
                         #idl::IdlInstructionAccountItem::Single(#idl::IdlInstructionAccount {
@@ -152,6 +206,12 @@ pub fn gen_idl_build_impl_accounts_struct(accounts: &AccountsStruct) -> TokenStr
                     }
                 );*
 
+                #(
+                    if <#versioned>::owner() == crate::ID {
+                        <#versioned as anchor_lang::accounts::versioned_migration::VersionChain>::insert_idl_versions(accounts, types);
+                    }
+                );*
+
                 vec![#(#accounts),*]
             }
         }
@@ -1,5 +1,7 @@
-use proc_macro2::TokenStream;
-use quote::quote;
+use std::collections::HashMap;
+
+use proc_macro2::{Group, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
 use syn::{spanned::Spanned, Result};
 
 use super::common::{get_idl_module_path, get_no_docs};
@@ -192,7 +194,7 @@ fn gen_idl_type_def<F>(
     create_fields: F,
 ) -> Result<(TokenStream, Vec<syn::TypePath>)>
 where
-    F: Fn(&[syn::Ident]) -> Result<(TokenStream, Vec<syn::TypePath>)>,
+    F: Fn(&[syn::GenericParam]) -> Result<(TokenStream, Vec<syn::TypePath>)>,
 {
     let no_docs = get_no_docs();
     let idl = get_idl_module_path();
@@ -251,11 +253,8 @@ where
     let generic_params = generics
         .params
         .iter()
-        .filter_map(|p| match p {
-            syn::GenericParam::Type(ty) => Some(ty.ident.clone()),
-            syn::GenericParam::Const(c) => Some(c.ident.clone()),
-            _ => None,
-        })
+        .filter(|p| generic_param_ident(p).is_some())
+        .cloned()
         .collect::<Vec<_>>();
     let (ty, defined) = create_fields(&generic_params)?;
 
@@ -325,7 +324,7 @@ fn get_attr_str(name: impl AsRef<str>, attrs: &[syn::Attribute]) -> Option<Strin
 
 fn gen_idl_field(
     field: &syn::Field,
-    generic_params: &[syn::Ident],
+    generic_params: &[syn::GenericParam],
     no_docs: bool,
 ) -> Result<(TokenStream, Vec<syn::TypePath>)> {
     let idl = get_idl_module_path();
@@ -351,7 +350,7 @@ fn gen_idl_field(
 
 pub fn gen_idl_type(
     ty: &syn::Type,
-    generic_params: &[syn::Ident],
+    generic_params: &[syn::GenericParam],
 ) -> Result<(TokenStream, Vec<syn::TypePath>)> {
     let idl = get_idl_module_path();
 
@@ -458,10 +457,13 @@ pub fn gen_idl_type(
         }
         syn::Type::Array(arr) => {
             let len = &arr.len;
-            let is_generic = generic_params.iter().any(|param| match len {
-                syn::Expr::Path(path) => path.path.is_ident(param),
-                _ => false,
-            });
+            let is_generic = generic_params
+                .iter()
+                .filter_map(generic_param_ident)
+                .any(|param| match len {
+                    syn::Expr::Path(path) => path.path.is_ident(param),
+                    _ => false,
+                });
 
             let len = if is_generic {
                 match len {
@@ -483,7 +485,10 @@ pub fn gen_idl_type(
         }
         // Defined
         syn::Type::Path(path) => {
-            let is_generic_param = generic_params.iter().any(|param| path.path.is_ident(param));
+            let is_generic_param = generic_params.iter().any(|param| match param {
+                syn::GenericParam::Type(param) => path.path.is_ident(&param.ident),
+                _ => false,
+            });
             if is_generic_param {
                 let generic = get_first_segment(path).ident.to_string();
                 return Ok((quote! { #idl::IdlType::Generic(#generic.into()) }, vec![]));
@@ -493,7 +498,6 @@ pub fn gen_idl_type(
             {
                 use super::{common::find_path, external::get_external_type};
                 use crate::parser::context::CrateContext;
-                use quote::ToTokens;
 
                 // If no path was found, just return an empty path and let the find_path function handle it
                 let source_path = proc_macro2::Span::call_site()
@@ -504,62 +508,12 @@ pub fn gen_idl_type(
                     let name = path.path.segments.last().unwrap().ident.to_string();
                     let alias = ctx.type_aliases().find(|ty| ty.ident == name);
                     if let Some(alias) = alias {
-                        if let Some(segment) = path.path.segments.last() {
-                            if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                                let inners = args
-                                    .args
-                                    .iter()
-                                    .map(|arg| match arg {
-                                        syn::GenericArgument::Type(ty) => match ty {
-                                            syn::Type::Path(inner_ty) => {
-                                                inner_ty.path.to_token_stream().to_string()
-                                            }
-                                            _ => {
-                                                unimplemented!("Inner type not implemented: {ty:?}")
-                                            }
-                                        },
-                                        syn::GenericArgument::Const(c) => {
-                                            c.to_token_stream().to_string()
-                                        }
-                                        _ => unimplemented!("Arg not implemented: {arg:?}"),
-                                    })
-                                    .collect::<Vec<_>>();
-
-                                let outer = match &*alias.ty {
-                                    syn::Type::Path(outer_ty) => outer_ty.path.to_token_stream(),
-                                    syn::Type::Array(outer_ty) => outer_ty.to_token_stream(),
-                                    _ => unimplemented!("Type not implemented: {:?}", alias.ty),
-                                }
-                                .to_string();
-
-                                let resolved_alias = alias
-                                    .generics
-                                    .params
-                                    .iter()
-                                    .map(|param| match param {
-                                        syn::GenericParam::Const(param) => param.ident.to_string(),
-                                        syn::GenericParam::Type(param) => param.ident.to_string(),
-                                        _ => panic!("Lifetime parameters are not allowed"),
-                                    })
-                                    .enumerate()
-                                    .fold(outer, |acc, (i, cur)| {
-                                        let inner = &inners[i];
-                                        // The spacing of the `outer` variable can differ between
-                                        // versions, e.g. `[T; N]` and `[T ; N]`
-                                        acc.replace(&format!(" {cur} "), &format!(" {inner} "))
-                                            .replace(&format!(" {cur},"), &format!(" {inner},"))
-                                            .replace(&format!("[{cur} "), &format!("[{inner} "))
-                                            .replace(&format!("[{cur};"), &format!("[{inner};"))
-                                            .replace(&format!(" {cur}]"), &format!(" {inner}]"))
-                                    });
-                                if let Ok(ty) = syn::parse_str(&resolved_alias) {
-                                    return gen_idl_type(&ty, generic_params);
-                                }
-                            }
+                        let args = match &path.path.segments.last().unwrap().arguments {
+                            syn::PathArguments::AngleBracketed(args) => args.args.iter().collect(),
+                            _ => vec![],
                         };
-
-                        // Non-generic type alias e.g. `type UnixTimestamp = i64`
-                        return gen_idl_type(&*alias.ty, generic_params);
+                        let ty = substitute_generics(&alias.ty, &alias.generics, &args)?;
+                        return gen_idl_type(&ty, generic_params);
                     }
 
                     // Handle external types
@@ -585,6 +539,18 @@ pub fn gen_idl_type(
                 if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
                     for arg in &args.args {
                         let generic = match arg {
+                            // Const generic parameters of the type being defined
+                            syn::GenericArgument::Type(syn::Type::Path(p))
+                                if generic_params.iter().any(|param| match param {
+                                    syn::GenericParam::Const(param) => {
+                                        p.path.is_ident(&param.ident)
+                                    }
+                                    _ => false,
+                                }) =>
+                            {
+                                let name = p.path.get_ident().unwrap().to_string();
+                                quote! { #idl::IdlGenericArg::Const { value: #name.into() } }
+                            }
                             syn::GenericArgument::Const(c) => {
                                 quote! { #idl::IdlGenericArg::Const { value: #c.to_string() } }
                             }
@@ -643,6 +609,73 @@ pub fn gen_idl_type(
     }
 }
 
+/// Substitute the generic parameters of a type alias with the given arguments, e.g. the alias
+/// type `[Option<T>; N]` becomes `[Option<u8>; 4]` for `Alias<u8, 4>`.
+///
+/// Parameters without an argument or a default are kept as is.
+fn substitute_generics(
+    ty: &syn::Type,
+    generics: &syn::Generics,
+    args: &[&syn::GenericArgument],
+) -> Result<syn::Type> {
+    let args = args
+        .iter()
+        .filter(|arg| !matches!(arg, syn::GenericArgument::Lifetime(_)))
+        .map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Ok(ty.to_token_stream()),
+            syn::GenericArgument::Const(c) => Ok(c.to_token_stream()),
+            _ => Err(syn::Error::new(arg.span(), "Unsupported generic argument")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let substitutions = generics
+        .params
+        .iter()
+        .filter(|param| generic_param_ident(param).is_some())
+        .enumerate()
+        .filter_map(|(i, param)| {
+            let value = args.get(i).cloned().or_else(|| match param {
+                syn::GenericParam::Type(param) => param.default.as_ref().map(|ty| quote!(#ty)),
+                syn::GenericParam::Const(param) => param.default.as_ref().map(|c| quote!(#c)),
+                _ => None,
+            })?;
+            Some((generic_param_ident(param)?.to_string(), value))
+        })
+        .collect::<HashMap<_, _>>();
+
+    fn substitute(
+        tokens: TokenStream,
+        substitutions: &HashMap<String, TokenStream>,
+    ) -> TokenStream {
+        tokens
+            .into_iter()
+            .flat_map(|tt| match tt {
+                TokenTree::Ident(ident) => substitutions
+                    .get(&ident.to_string())
+                    .cloned()
+                    .unwrap_or_else(|| TokenTree::Ident(ident).into()),
+                TokenTree::Group(group) => {
+                    let mut substituted =
+                        Group::new(group.delimiter(), substitute(group.stream(), substitutions));
+                    substituted.set_span(group.span());
+                    TokenTree::Group(substituted).into()
+                }
+                tt => tt.into(),
+            })
+            .collect()
+    }
+
+    syn::parse2(substitute(ty.to_token_stream(), &substitutions))
+}
+
+/// Identifier of a type or const generic parameter.
+fn generic_param_ident(param: &syn::GenericParam) -> Option<&syn::Ident> {
+    match param {
+        syn::GenericParam::Type(param) => Some(&param.ident),
+        syn::GenericParam::Const(param) => Some(&param.ident),
+        syn::GenericParam::Lifetime(_) => None,
+    }
+}
+
 fn get_first_segment(type_path: &syn::TypePath) -> &syn::PathSegment {
     type_path.path.segments.first().unwrap()
 }
@@ -355,13 +355,16 @@ impl Field {
                     }
                 }
             }
-            Ty::Migration(ty) => {
+            Ty::Migration(ty) | Ty::LazyMigration(ty) => {
                 let from = &ty.from_type_path;
                 let to = &ty.to_type_path;
                 quote! {
                     #container_ty<'info, #from, #to>
                 }
             }
+            Ty::Remaining(_) => quote! {
+                #container_ty<'info, #account_ty>
+            },
             _ => quote! {
                 #container_ty<#account_ty>
             },
@@ -494,6 +497,15 @@ impl Field {
             Ty::Migration(_) => quote! {
                 anchor_lang::accounts::migration::Migration
             },
+            Ty::LazyMigration(_) => quote! {
+                anchor_lang::accounts::lazy_migration::LazyMigration
+            },
+            Ty::VersionedMigration(_) => quote! {
+                anchor_lang::accounts::versioned_migration::VersionedMigration
+            },
+            Ty::Remaining(_) => quote! {
+                anchor_lang::accounts::remaining::Remaining
+            },
             Ty::Sysvar(_) => quote! { anchor_lang::accounts::sysvar::Sysvar },
             Ty::Program(_) => quote! { anchor_lang::accounts::program::Program },
             Ty::Interface(_) => quote! { anchor_lang::accounts::interface::Interface },
@@ -550,13 +562,25 @@ impl Field {
                     #ident
                 }
             }
-            Ty::Migration(ty) => {
+            Ty::Migration(ty) | Ty::LazyMigration(ty) => {
                 // Return just the From type for IDL and other uses
                 let from = &ty.from_type_path;
                 quote! {
                     #from
                 }
             }
+            Ty::VersionedMigration(ty) => {
+                let ident = &ty.account_type_path;
+                quote! {
+                    #ident
+                }
+            }
+            Ty::Remaining(ty) => {
+                let ident = &ty.account_type_path;
+                quote! {
+                    #ident
+                }
+            }
             Ty::Sysvar(ty) => match ty {
                 SysvarTy::Clock => quote! {Clock},
                 SysvarTy::Rent => quote! {Rent},
@@ -611,6 +635,9 @@ pub enum Ty {
     Account(AccountTy),
     LazyAccount(LazyAccountTy),
     Migration(MigrationTy),
+    LazyMigration(MigrationTy),
+    VersionedMigration(VersionedMigrationTy),
+    Remaining(RemainingTy),
     Program(ProgramTy),
     Interface(InterfaceTy),
     InterfaceAccount(InterfaceAccountTy),
@@ -655,11 +682,25 @@ pub struct LazyAccountTy {
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct MigrationTy {
-    // Migration<'info, From, To> - we need both From and To types
+    // Migration<'info, From, To> or LazyMigration<'info, From, To> - we need both From and To
+    // types
     pub from_type_path: TypePath,
     pub to_type_path: TypePath,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct VersionedMigrationTy {
+    // The latest version of the account.
+    pub account_type_path: TypePath,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemainingTy {
+    // The type of every account of the collection e.g. `Account<'info, T>`.
+    pub account_type_path: TypePath,
+    pub ty: Box<Ty>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct InterfaceAccountTy {
     // The struct type of the account.
@@ -734,6 +775,8 @@ pub struct ConstraintGroup {
     pub token_account: Option<ConstraintTokenAccountGroup>,
     pub mint: Option<ConstraintTokenMintGroup>,
     pub realloc: Option<ConstraintReallocGroup>,
+    pub each: Option<ConstraintEachGroup>,
+    pub remaining: Option<ConstraintRemainingGroup>,
 }
 
 impl ConstraintGroup {
@@ -787,6 +830,7 @@ pub enum Constraint {
     TokenAccount(ConstraintTokenAccountGroup),
     Mint(ConstraintTokenMintGroup),
     Realloc(ConstraintReallocGroup),
+    Each(ConstraintEachGroup),
 }
 
 // Constraint token is a single keyword in a `#[account(<TOKEN>)]` attribute.
@@ -823,6 +867,12 @@ pub enum ConstraintToken {
     Realloc(Context<ConstraintRealloc>),
     ReallocPayer(Context<ConstraintReallocPayer>),
     ReallocZero(Context<ConstraintReallocZero>),
+    EachHasOne(Context<ConstraintHasOne>),
+    EachRaw(Context<ConstraintRaw>),
+    EachOwner(Context<ConstraintOwner>),
+    EachUnique(Context<ConstraintEachUnique>),
+    RemainingMin(Context<ConstraintRemainingMin>),
+    RemainingMax(Context<ConstraintRemainingMax>),
     // extensions
     ExtensionGroupPointerAuthority(Context<ConstraintExtensionAuthority>),
     ExtensionGroupPointerGroupAddress(Context<ConstraintExtensionGroupPointerGroupAddress>),
@@ -910,6 +960,35 @@ pub struct ConstraintOwner {
     pub error: Option<Expr>,
 }
 
+// Constraints applied to every account of a `Remaining` collection.
+#[derive(Debug, Default, Clone)]
+pub struct ConstraintEachGroup {
+    pub has_one: Vec<ConstraintHasOne>,
+    pub raw: Vec<ConstraintRaw>,
+    pub owner: Option<ConstraintOwner>,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintEachUnique {}
+
+// Bounds on the number of accounts of a `Remaining` collection.
+#[derive(Debug, Clone)]
+pub struct ConstraintRemainingGroup {
+    pub min: Option<Expr>,
+    pub max: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintRemainingMin {
+    pub min: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintRemainingMax {
+    pub max: Expr,
+}
+
 #[derive(Debug, Clone)]
 pub struct ConstraintAddress {
     pub address: Expr,
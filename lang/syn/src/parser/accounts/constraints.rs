@@ -6,6 +6,24 @@ pub fn parse(f: &syn::Field, f_ty: Option<&Ty>) -> ParseResult<ConstraintGroup>
     let mut constraints = ConstraintGroupBuilder::new(f_ty);
     for attr in f.attrs.iter().filter(is_account) {
         for c in attr.parse_args_with(Punctuated::<ConstraintToken, Comma>::parse_terminated)? {
+            // `Remaining` accounts are validated one by one
+            if matches!(f_ty, Some(Ty::Remaining(_)))
+                && !matches!(
+                    c,
+                    ConstraintToken::Mut(_)
+                        | ConstraintToken::EachHasOne(_)
+                        | ConstraintToken::EachRaw(_)
+                        | ConstraintToken::EachOwner(_)
+                        | ConstraintToken::EachUnique(_)
+                        | ConstraintToken::RemainingMin(_)
+                        | ConstraintToken::RemainingMax(_)
+                )
+            {
+                return Err(ParseError::new(
+                    attr.span(),
+                    "Remaining only supports the mut, each:: and remaining:: constraints",
+                ));
+            }
             constraints.add(c)?;
         }
     }
@@ -411,6 +429,78 @@ pub fn parse_token(stream: ParseStream) -> ParseResult<ConstraintToken> {
                 }
             }
         }
+        "each" => {
+            stream.parse::<Token![:]>()?;
+            stream.parse::<Token![:]>()?;
+            let kw = stream.call(Ident::parse_any)?.to_string();
+
+            if kw == "unique" {
+                let span = ident
+                    .span()
+                    .join(stream.span())
+                    .unwrap_or_else(|| ident.span());
+                ConstraintToken::EachUnique(Context::new(span, ConstraintEachUnique {}))
+            } else {
+                stream.parse::<Token![=]>()?;
+
+                let span = ident
+                    .span()
+                    .join(stream.span())
+                    .unwrap_or_else(|| ident.span());
+
+                match kw.as_str() {
+                    "has_one" => ConstraintToken::EachHasOne(Context::new(
+                        span,
+                        ConstraintHasOne {
+                            join_target: stream.parse()?,
+                            error: parse_optional_custom_error(&stream)?,
+                        },
+                    )),
+                    "owner" => ConstraintToken::EachOwner(Context::new(
+                        span,
+                        ConstraintOwner {
+                            owner_address: stream.parse()?,
+                            error: parse_optional_custom_error(&stream)?,
+                        },
+                    )),
+                    "constraint" => ConstraintToken::EachRaw(Context::new(
+                        span,
+                        ConstraintRaw {
+                            raw: stream.parse()?,
+                            error: parse_optional_custom_error(&stream)?,
+                        },
+                    )),
+                    _ => return Err(ParseError::new(ident.span(), "Invalid attribute. each::has_one, each::owner, each::constraint and each::unique are the only valid attributes")),
+                }
+            }
+        }
+        "remaining" => {
+            stream.parse::<Token![:]>()?;
+            stream.parse::<Token![:]>()?;
+            let kw = stream.call(Ident::parse_any)?.to_string();
+            stream.parse::<Token![=]>()?;
+
+            let span = ident
+                .span()
+                .join(stream.span())
+                .unwrap_or_else(|| ident.span());
+
+            match kw.as_str() {
+                "min" => ConstraintToken::RemainingMin(Context::new(
+                    span,
+                    ConstraintRemainingMin {
+                        min: stream.parse()?,
+                    },
+                )),
+                "max" => ConstraintToken::RemainingMax(Context::new(
+                    span,
+                    ConstraintRemainingMax {
+                        max: stream.parse()?,
+                    },
+                )),
+                _ => return Err(ParseError::new(ident.span(), "Invalid attribute. remaining::min and remaining::max are the only valid attributes")),
+            }
+        }
         _ => {
             stream.parse::<Token![=]>()?;
             let span = ident
@@ -540,6 +630,12 @@ pub struct ConstraintGroupBuilder<'ty> {
     pub realloc_payer: Option<Context<ConstraintReallocPayer>>,
     pub realloc_zero: Option<Context<ConstraintReallocZero>>,
     pub dup: Option<Context<ConstraintDup>>,
+    pub each_has_one: Vec<Context<ConstraintHasOne>>,
+    pub each_raw: Vec<Context<ConstraintRaw>>,
+    pub each_owner: Option<Context<ConstraintOwner>>,
+    pub each_unique: Option<Context<ConstraintEachUnique>>,
+    pub remaining_min: Option<Context<ConstraintRemainingMin>>,
+    pub remaining_max: Option<Context<ConstraintRemainingMax>>,
 }
 
 impl<'ty> ConstraintGroupBuilder<'ty> {
@@ -586,6 +682,12 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             realloc_payer: None,
             realloc_zero: None,
             dup: None,
+            each_has_one: Vec::new(),
+            each_raw: Vec::new(),
+            each_owner: None,
+            each_unique: None,
+            remaining_min: None,
+            remaining_max: None,
         }
     }
 
@@ -799,6 +901,12 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             realloc_payer,
             realloc_zero,
             dup,
+            each_has_one,
+            each_raw,
+            each_owner,
+            each_unique,
+            remaining_min,
+            remaining_max,
         } = self;
 
         // Converts Option<Context<T>> -> Option<T>.
@@ -817,6 +925,22 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             };
         }
 
+        let each = (!each_has_one.is_empty()
+            || !each_raw.is_empty()
+            || each_owner.is_some()
+            || each_unique.is_some())
+        .then(|| ConstraintEachGroup {
+            has_one: into_inner_vec!(each_has_one),
+            raw: into_inner_vec!(each_raw),
+            owner: into_inner!(each_owner),
+            unique: each_unique.is_some(),
+        });
+        let remaining = (remaining_min.is_some() || remaining_max.is_some()).then(|| {
+            ConstraintRemainingGroup {
+                min: into_inner!(remaining_min).map(|c| c.min),
+                max: into_inner!(remaining_max).map(|c| c.max),
+            }
+        });
         let is_init = init.is_some();
         let seeds = seeds.map(|c| ConstraintSeedsGroup {
             is_init,
@@ -1031,6 +1155,8 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             token_account: if !is_init {token_account} else {None},
             mint: if !is_init {mint} else {None},
             dup: into_inner!(dup),
+            each,
+            remaining,
         })
     }
 
@@ -1094,6 +1220,12 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
                 self.add_extension_permanent_delegate(c)
             }
             ConstraintToken::Dup(c) => self.add_dup(c),
+            ConstraintToken::EachHasOne(c) => self.add_each_has_one(c),
+            ConstraintToken::EachRaw(c) => self.add_each_raw(c),
+            ConstraintToken::EachOwner(c) => self.add_each_owner(c),
+            ConstraintToken::EachUnique(c) => self.add_each_unique(c),
+            ConstraintToken::RemainingMin(c) => self.add_remaining_min(c),
+            ConstraintToken::RemainingMax(c) => self.add_remaining_max(c),
         }
     }
 
@@ -1196,10 +1328,12 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             && !matches!(self.f_ty, Some(Ty::LazyAccount(_)))
             && !matches!(self.f_ty, Some(Ty::AccountLoader(_)))
             && !matches!(self.f_ty, Some(Ty::Migration(_)))
+            && !matches!(self.f_ty, Some(Ty::LazyMigration(_)))
+            && !matches!(self.f_ty, Some(Ty::VersionedMigration(_)))
         {
             return Err(ParseError::new(
                 c.span(),
-                "realloc must be on an Account, LazyAccount, AccountLoader, or Migration",
+                "realloc must be on an Account, LazyAccount, AccountLoader, Migration, LazyMigration, or VersionedMigration",
             ));
         }
         if self.mutable.is_none() {
@@ -1685,4 +1819,82 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
         self.dup.replace(c);
         Ok(())
     }
+
+    fn check_each(&self, span: Span) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Remaining(_))) {
+            return Err(ParseError::new(
+                span,
+                "each:: constraints can only be used on Remaining accounts",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_remaining(&self, span: Span) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Remaining(_))) {
+            return Err(ParseError::new(
+                span,
+                "remaining:: constraints can only be used on Remaining accounts",
+            ));
+        }
+        Ok(())
+    }
+
+    fn add_each_has_one(&mut self, c: Context<ConstraintHasOne>) -> ParseResult<()> {
+        self.check_each(c.span())?;
+        if self
+            .each_has_one
+            .iter()
+            .any(|item| item.join_target == c.join_target)
+        {
+            return Err(ParseError::new(
+                c.span(),
+                "each::has_one target already provided",
+            ));
+        }
+        self.each_has_one.push(c);
+        Ok(())
+    }
+
+    fn add_each_raw(&mut self, c: Context<ConstraintRaw>) -> ParseResult<()> {
+        self.check_each(c.span())?;
+        self.each_raw.push(c);
+        Ok(())
+    }
+
+    fn add_each_owner(&mut self, c: Context<ConstraintOwner>) -> ParseResult<()> {
+        self.check_each(c.span())?;
+        if self.each_owner.is_some() {
+            return Err(ParseError::new(c.span(), "each::owner already provided"));
+        }
+        self.each_owner.replace(c);
+        Ok(())
+    }
+
+    fn add_each_unique(&mut self, c: Context<ConstraintEachUnique>) -> ParseResult<()> {
+        self.check_each(c.span())?;
+        if self.each_unique.is_some() {
+            return Err(ParseError::new(c.span(), "each::unique already provided"));
+        }
+        self.each_unique.replace(c);
+        Ok(())
+    }
+
+    fn add_remaining_min(&mut self, c: Context<ConstraintRemainingMin>) -> ParseResult<()> {
+        self.check_remaining(c.span())?;
+        if self.remaining_min.is_some() {
+            return Err(ParseError::new(c.span(), "remaining::min already provided"));
+        }
+        self.remaining_min.replace(c);
+        Ok(())
+    }
+
+    fn add_remaining_max(&mut self, c: Context<ConstraintRemainingMax>) -> ParseResult<()> {
+        self.check_remaining(c.span())?;
+        if self.remaining_max.is_some() {
+            return Err(ParseError::new(c.span(), "remaining::max already provided"));
+        }
+        self.remaining_max.replace(c);
+        Ok(())
+    }
 }
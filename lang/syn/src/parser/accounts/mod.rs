@@ -50,6 +50,7 @@ pub fn parse(accounts_struct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
     };
 
     constraints_cross_checks(&fields)?;
+    remaining_checks(&fields)?;
 
     Ok(AccountsStruct::new(
         accounts_struct,
@@ -58,6 +59,31 @@ pub fn parse(accounts_struct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
     ))
 }
 
+fn remaining_checks(fields: &[AccountField]) -> ParseResult<()> {
+    // `Remaining` consumes every account that is left, so it must be the last field
+    for (i, field) in fields.iter().enumerate() {
+        if let AccountField::Field(f) = field {
+            if !matches!(f.ty, Ty::Remaining(_)) {
+                continue;
+            }
+            if f.is_optional {
+                return Err(ParseError::new(
+                    f.ty_span,
+                    "Remaining accounts cannot be optional",
+                ));
+            }
+            if i != fields.len() - 1 {
+                return Err(ParseError::new(
+                    f.ty_span,
+                    "Remaining accounts must be the last field of the struct",
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn constraints_cross_checks(fields: &[AccountField]) -> ParseResult<()> {
     // COMMON ERROR MESSAGE
     let message = |constraint: &str, field: &str, required: bool| {
@@ -350,6 +376,9 @@ fn is_field_primitive(f: &syn::Field) -> ParseResult<bool> {
             | "Account"
             | "LazyAccount"
             | "Migration"
+            | "LazyMigration"
+            | "VersionedMigration"
+            | "Remaining"
             | "Program"
             | "Interface"
             | "InterfaceAccount"
@@ -370,6 +399,9 @@ fn parse_ty(f: &syn::Field) -> ParseResult<(Ty, bool)> {
         "Account" => Ty::Account(parse_account_ty(&path)?),
         "LazyAccount" => Ty::LazyAccount(parse_lazy_account_ty(&path)?),
         "Migration" => Ty::Migration(parse_migration_ty(&path)?),
+        "LazyMigration" => Ty::LazyMigration(parse_migration_ty(&path)?),
+        "VersionedMigration" => Ty::VersionedMigration(parse_versioned_migration_ty(&path)?),
+        "Remaining" => Ty::Remaining(parse_remaining_ty(f, &path)?),
         "Program" => Ty::Program(parse_program_ty(&path)?),
         "Interface" => Ty::Interface(parse_interface_ty(&path)?),
         "InterfaceAccount" => Ty::InterfaceAccount(parse_interface_account_ty(&path)?),
@@ -468,7 +500,7 @@ fn parse_lazy_account_ty(path: &syn::Path) -> ParseResult<LazyAccountTy> {
 }
 
 fn parse_migration_ty(path: &syn::Path) -> ParseResult<MigrationTy> {
-    // Migration<'info, From, To>
+    // Migration<'info, From, To> or LazyMigration<'info, From, To>
     let segments = &path.segments[0];
     match &segments.arguments {
         syn::PathArguments::AngleBracketed(args) => {
@@ -510,6 +542,39 @@ fn parse_migration_ty(path: &syn::Path) -> ParseResult<MigrationTy> {
     }
 }
 
+fn parse_versioned_migration_ty(path: &syn::Path) -> ParseResult<VersionedMigrationTy> {
+    let account_type_path = parse_account(path)?;
+    Ok(VersionedMigrationTy { account_type_path })
+}
+
+fn parse_remaining_ty(f: &syn::Field, path: &syn::Path) -> ParseResult<RemainingTy> {
+    let account_type_path = parse_account(path)?;
+    // Parse the type of the accounts as if it was the type of the field
+    let account_field = syn::Field {
+        ty: syn::Type::Path(account_type_path.clone()),
+        ..f.clone()
+    };
+    if !is_field_primitive(&account_field)? {
+        return Err(ParseError::new(
+            account_type_path.span(),
+            "Remaining requires an account type e.g. Account<'info, T>",
+        ));
+    }
+    let ty = match parse_ty(&account_field)? {
+        (_, true) | (Ty::Remaining(_), _) => {
+            return Err(ParseError::new(
+                account_type_path.span(),
+                "Remaining accounts cannot be optional or nested",
+            ))
+        }
+        (ty, false) => ty,
+    };
+    Ok(RemainingTy {
+        account_type_path,
+        ty: Box::new(ty),
+    })
+}
+
 fn parse_interface_account_ty(path: &syn::Path) -> ParseResult<InterfaceAccountTy> {
     let account_type_path = parse_account(path)?;
     let boxed = parser::tts_to_string(path)
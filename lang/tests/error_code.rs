@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum MyError {
+    #[msg("Not enough funds")]
+    InsufficientFunds,
+    Unauthorized,
+}
+
+#[error_code(offset = 100)]
+pub enum OffsetError {
+    First,
+}
+
+#[test]
+fn test_from_error_code() {
+    assert!(matches!(
+        MyError::from_error_code(6000),
+        Some(MyError::InsufficientFunds)
+    ));
+    assert!(matches!(
+        MyError::from_error_code(6001),
+        Some(MyError::Unauthorized)
+    ));
+    assert!(MyError::from_error_code(6002).is_none());
+    assert!(MyError::from_error_code(0).is_none());
+
+    assert!(matches!(
+        OffsetError::from_error_code(100),
+        Some(OffsetError::First)
+    ));
+    assert!(matches!(
+        ErrorCode::from_error_code(2000),
+        Some(ErrorCode::ConstraintMut)
+    ));
+}
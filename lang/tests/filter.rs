@@ -0,0 +1,69 @@
+use anchor_lang::filter::Memcmp;
+use anchor_lang::prelude::*;
+
+// Needed to declare accounts.
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account(filter)]
+pub struct Data {
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub flags: [u8; 4],
+    pub name: String,
+    pub after_name: u64,
+}
+
+#[account(zero_copy, filter)]
+pub struct ZeroCopyData {
+    pub amount: u64,
+    pub authority: Pubkey,
+    pub flags: [u8; 8],
+}
+
+// Accounts without the `filter` argument leave the names of the builder free
+#[account]
+pub struct Plain {
+    pub build: u64,
+}
+
+pub struct PlainFilter;
+
+impl Plain {
+    pub fn filter() -> PlainFilter {
+        PlainFilter
+    }
+}
+
+#[test]
+fn test_filter() {
+    let authority = Pubkey::new_unique();
+    let filters = Data::filter()
+        .authority(authority)
+        .amount(5)
+        .flags([1, 2, 3, 4])
+        .build();
+
+    assert_eq!(filters.len(), 3);
+    assert_eq!(filters[0], Memcmp::new(8, authority.to_bytes().to_vec()));
+    assert_eq!(filters[1], Memcmp::new(40, 5u64.to_le_bytes().to_vec()));
+    assert_eq!(filters[2], Memcmp::new(48, vec![1, 2, 3, 4]));
+
+    // The first variable-size field is still at a fixed offset
+    let filters = Data::filter().name("name".into()).build();
+    assert_eq!(filters, vec![Memcmp::new(52, b"\x04\0\0\0name".to_vec())]);
+}
+
+#[test]
+fn test_filter_zero_copy() {
+    let authority = Pubkey::new_unique();
+    let filters = ZeroCopyData::filter()
+        .amount(5)
+        .authority(authority)
+        .flags([1; 8])
+        .build();
+
+    assert_eq!(filters.len(), 3);
+    assert_eq!(filters[0], Memcmp::new(8, 5u64.to_le_bytes().to_vec()));
+    assert_eq!(filters[1], Memcmp::new(16, authority.to_bytes().to_vec()));
+    assert_eq!(filters[2], Memcmp::new(48, vec![1; 8]));
+}
@@ -0,0 +1,198 @@
+use anchor_lang::error::{Error, ErrorCode, ErrorOrigin};
+use anchor_lang::prelude::*;
+use std::collections::BTreeSet;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account]
+pub struct Position {
+    user: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    user: Signer<'info>,
+    #[account(each::has_one = user, each::unique)]
+    positions: Remaining<'info, Account<'info, Position>>,
+}
+
+#[derive(Accounts)]
+pub struct Bounded<'info> {
+    #[account(remaining::min = 1, remaining::max = 2)]
+    signers: Remaining<'info, Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct Nested<'info> {
+    payer: Signer<'info>,
+    bounded: Bounded<'info>,
+}
+
+fn info<'a>(
+    key: &'a Pubkey,
+    is_signer: bool,
+    lamports: &'a mut u64,
+    data: &'a mut [u8],
+    owner: &'a Pubkey,
+) -> AccountInfo<'a> {
+    AccountInfo::new(key, is_signer, false, lamports, data, owner, false)
+}
+
+/// Runs `Settle` with `positions` given as `(key, user)` pairs.
+fn settle(user: Pubkey, positions: &[(Pubkey, Pubkey)]) -> Result<()> {
+    let system = Pubkey::default();
+    let (mut user_lamports, mut user_data) = (0, [0; 0]);
+    let mut lamports = vec![1; positions.len()];
+    let mut data = positions
+        .iter()
+        .map(|(_, user)| {
+            let mut data = vec![];
+            Position { user: *user }.try_serialize(&mut data).unwrap();
+            data
+        })
+        .collect::<Vec<_>>();
+    let mut infos = vec![info(
+        &user,
+        true,
+        &mut user_lamports,
+        &mut user_data,
+        &system,
+    )];
+    infos.extend(
+        positions
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|(((key, _), lamports), data)| info(key, false, lamports, data, &ID)),
+    );
+
+    let mut accounts = &infos[..];
+    Settle::try_accounts(
+        &ID,
+        &mut accounts,
+        &[],
+        &mut SettleBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .map(|_| ())
+}
+
+fn account_name(err: Error) -> Option<String> {
+    match err {
+        Error::AnchorError(err) => match err.error_origin {
+            Some(ErrorOrigin::AccountName(name)) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn each_constraints() {
+    let user = Pubkey::new_unique();
+    let positions = [(Pubkey::new_unique(), user), (Pubkey::new_unique(), user)];
+
+    assert!(settle(user, &positions).is_ok());
+    assert!(settle(user, &[]).is_ok());
+}
+
+#[test]
+fn each_has_one_names_failing_index() {
+    let user = Pubkey::new_unique();
+    let positions = [
+        (Pubkey::new_unique(), user),
+        (Pubkey::new_unique(), Pubkey::new_unique()),
+    ];
+
+    let err = settle(user, &positions).unwrap_err();
+    assert_eq!(err, ErrorCode::ConstraintHasOne.into());
+    assert_eq!(account_name(err).as_deref(), Some("positions[1]"));
+}
+
+#[test]
+fn each_unique() {
+    let user = Pubkey::new_unique();
+    let key = Pubkey::new_unique();
+    let positions = [(Pubkey::new_unique(), user), (key, user), (key, user)];
+
+    let err = settle(user, &positions).unwrap_err();
+    assert_eq!(err, ErrorCode::ConstraintUnique.into());
+    assert_eq!(account_name(err).as_deref(), Some("positions[2]"));
+}
+
+#[test]
+fn remaining_bounds() {
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let owner = Pubkey::default();
+    let mut lamports = [0; 3];
+    let mut data = [[0; 0]; 3];
+    let infos = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| info(key, true, lamports, data, &owner))
+        .collect::<Vec<_>>();
+
+    let bounded = |len: usize| {
+        let mut accounts = &infos[..len];
+        Bounded::try_accounts(
+            &ID,
+            &mut accounts,
+            &[],
+            &mut BoundedBumps::default(),
+            &mut BTreeSet::new(),
+        )
+        .map(|_| ())
+    };
+
+    assert!(bounded(1).is_ok());
+    assert!(bounded(2).is_ok());
+    for len in [0, 3] {
+        let err = bounded(len).unwrap_err();
+        assert_eq!(err, ErrorCode::ConstraintRemainingLen.into());
+        assert_eq!(account_name(err).as_deref(), Some("signers"));
+    }
+}
+
+#[test]
+fn nested_remaining() {
+    let keys = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let owner = Pubkey::default();
+    let mut lamports = [0; 3];
+    let mut data = [[0; 0]; 3];
+    let infos = keys
+        .iter()
+        .zip(lamports.iter_mut())
+        .zip(data.iter_mut())
+        .map(|((key, lamports), data)| info(key, true, lamports, data, &owner))
+        .collect::<Vec<_>>();
+
+    let mut accounts = &infos[..];
+    let nested = Nested::try_accounts(
+        &ID,
+        &mut accounts,
+        &[],
+        &mut NestedBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .unwrap();
+    assert!(accounts.is_empty());
+    assert_eq!(nested.payer.key(), keys[0]);
+    assert_eq!(
+        nested
+            .bounded
+            .signers
+            .iter()
+            .map(|signer| signer.key())
+            .collect::<Vec<_>>(),
+        keys[1..]
+    );
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+declare_program!(external);
+
+use external::client::{args, builders, ResolveError};
+
+#[test]
+pub fn test_resolve_address_and_pda() {
+    let authority = Pubkey::new_unique();
+    let accounts = builders::Init::default()
+        .authority(authority)
+        .build(&args::Init)
+        .unwrap();
+
+    assert_eq!(accounts.authority, authority);
+    assert_eq!(
+        accounts.my_account,
+        Pubkey::find_program_address(&[authority.as_ref()], &external::ID).0
+    );
+    assert_eq!(accounts.system_program, anchor_lang::system_program::ID);
+
+    // Given accounts are not resolved
+    let my_account = Pubkey::new_unique();
+    let accounts = builders::Init::default()
+        .authority(authority)
+        .my_account(my_account)
+        .build(&args::Init)
+        .unwrap();
+    assert_eq!(accounts.my_account, my_account);
+
+    // Signers can't be resolved
+    assert_eq!(
+        builders::Init::default().build(&args::Init).err(),
+        Some(ResolveError {
+            account: "authority"
+        })
+    );
+}
+
+#[test]
+pub fn test_resolve_composite_and_optional() {
+    let authority = Pubkey::new_unique();
+    let accounts = builders::UpdateComposite::default()
+        .update_authority(authority)
+        .build(&args::UpdateComposite { value: 1 })
+        .unwrap();
+
+    assert_eq!(accounts.update.authority, authority);
+    assert_eq!(
+        accounts.update.my_account,
+        Pubkey::find_program_address(&[authority.as_ref()], &external::ID).0
+    );
+
+    let accounts = builders::UpdateWithOptional::default()
+        .authority(authority)
+        .build(&args::UpdateWithOptional { value: 1 })
+        .unwrap();
+    assert_eq!(accounts.optional_account, None);
+}
+
+#[test]
+pub fn test_resolve_relations_and_account_fields() {
+    let authority = Pubkey::new_unique();
+    let args = args::UpdateVault {
+        name: "main".into(),
+        value: 1,
+    };
+    let vault = Pubkey::find_program_address(&[b"vault", b"main"], &external::ID).0;
+    let vault_data = [external::accounts::Vault::DISCRIMINATOR, authority.as_ref()].concat();
+
+    // Relations and account fields require fetching the accounts
+    assert_eq!(
+        builders::UpdateVault::default().build(&args).err(),
+        Some(ResolveError {
+            account: "authority"
+        })
+    );
+
+    let accounts = builders::UpdateVault::default()
+        .build_with(&args, |key| (*key == vault).then(|| vault_data.clone()))
+        .unwrap();
+    assert_eq!(accounts.authority, authority);
+    assert_eq!(accounts.vault, vault);
+    assert_eq!(
+        accounts.my_account,
+        Pubkey::find_program_address(&[authority.as_ref()], &external::ID).0
+    );
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+
+declare_program!(generics);
+
+use generics::types::{GenericEnum, GenericNested, GenericType, MyStruct, WrappedU8Array};
+
+fn nested<V, Z>(gen1: V, gen2: Z) -> GenericNested<V, Z> {
+    GenericNested { gen1, gen2 }
+}
+
+#[test]
+pub fn test_generic_type() {
+    let value: GenericType<u32, u64, 2> = GenericType {
+        gen1: 1,
+        gen2: 2,
+        gen3: nested(3, 4),
+        gen4: nested(5, MyStruct { some_field: 6 }),
+        gen5: nested(7, 8),
+        gen6: nested(9, 10),
+        gen7: nested(11, nested(12, 13)),
+        arr: [14, 15],
+        warr: WrappedU8Array(16),
+        warrval: WrappedU8Array(17),
+        enm1: GenericEnum::Arr([18, 19]),
+        enm2: GenericEnum::Named {
+            gen1: nested(20, 21),
+            gen2: 22,
+        },
+        // Nested generic aliases
+        alias1: nested(23, 24),
+        alias2: [nested(25, 26), nested(27, 28)],
+        alias3: [
+            nested([29, 30], [31, 32]),
+            nested([33, 34], [35, 36]),
+            nested([37, 38], [39, 40]),
+        ],
+        alias4: Some(nested(None, Some(nested(41, 42)))),
+    };
+
+    let data = anchor_lang::prelude::borsh::to_vec(&value).unwrap();
+    let decoded = GenericType::<u32, u64, 2>::deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(format!("{decoded:?}"), format!("{value:?}"));
+}
+
+#[test]
+pub fn test_generic_account() {
+    let account = generics::accounts::GenericAccount {
+        data: GenericType {
+            gen1: 1,
+            gen2: 2,
+            gen3: nested(3, 4),
+            gen4: nested(5, MyStruct { some_field: 6 }),
+            gen5: nested(7, 8),
+            gen6: nested(9, 10),
+            gen7: nested(11, nested(12, 13)),
+            arr: [0; 10],
+            warr: WrappedU8Array(14),
+            warrval: WrappedU8Array(15),
+            enm1: GenericEnum::Unnamed(16, 17),
+            enm2: GenericEnum::Struct(nested(nested(18, 19), 20)),
+            alias1: nested(21, 22),
+            alias2: std::array::from_fn(|i| nested(i as u64, i as u64)),
+            alias3: std::array::from_fn(|_| nested([23, 24], [25, 26])),
+            alias4: None,
+        },
+    };
+
+    let mut data = vec![];
+    account.try_serialize(&mut data).unwrap();
+    let decoded =
+        generics::accounts::GenericAccount::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(format!("{decoded:?}"), format!("{account:?}"));
+}
@@ -16,6 +16,7 @@ pub fn test_account_parser() {
     // Correct discriminator and valid data
     match Account::parse(&[DISC, &[1, 0, 0, 0]].concat()) {
         Ok(Account::MyAccount(my_account)) => assert_eq!(my_account.field, 1),
+        Ok(_) => panic!("Expected `MyAccount`"),
         Err(e) => panic!("Expected Ok result, got error: {:?}", e),
     }
 }
@@ -0,0 +1,14 @@
+use anchor_lang::prelude::*;
+
+declare_program!(pinned_external, path = "../../pinned/external.json");
+
+#[test]
+pub fn test_pinned_idl_from_path() {
+    assert_eq!(
+        pinned_external::ID,
+        pubkey!("Externa111111111111111111111111111111111111")
+    );
+
+    let my_account = pinned_external::accounts::MyAccount { field: 1 };
+    assert_eq!(my_account.field, 1);
+}
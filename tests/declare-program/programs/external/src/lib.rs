@@ -112,6 +112,12 @@ pub mod external {
         Ok(())
     }
 
+    // Test account resolution from arguments, relations and account fields
+    pub fn update_vault(ctx: Context<UpdateVault>, name: String, value: u32) -> Result<()> {
+        ctx.accounts.my_account.field = value;
+        Ok(())
+    }
+
     // Test optional accounts parsing
     pub fn update_with_optional(ctx: Context<UpdateWithOptional>, value: u32) -> Result<()> {
         ctx.accounts.my_account.field = value;
@@ -198,11 +204,26 @@ pub struct UpdateWithOptional<'info> {
     pub optional_account: Option<AccountInfo<'info>>,
 }
 
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct UpdateVault<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority, seeds = [b"vault", name.as_bytes()], bump)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut, seeds = [vault.authority.as_ref()], bump)]
+    pub my_account: Account<'info, MyAccount>,
+}
+
 #[account]
 pub struct MyAccount {
     pub field: u32,
 }
 
+#[account]
+pub struct Vault {
+    pub authority: Pubkey,
+}
+
 #[event]
 pub struct MyEvent {
     pub value: u32,
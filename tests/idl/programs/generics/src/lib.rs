@@ -47,8 +47,16 @@ where
     pub warrval: WrappedU8Array<10>,
     pub enm1: GenericEnum<T, U, N>,
     pub enm2: GenericEnum<GenericNested<T, u64>, u32, 30>,
+    pub alias1: Pair<T>,
+    pub alias2: PairArray<U, N>,
+    pub alias3: PairArray<[T; 2], 3>,
+    pub alias4: OptionalPair<GenericNested<T, u64>>,
 }
 
+pub type Pair<T> = GenericNested<T, T>;
+pub type PairArray<T, const N: usize> = [Pair<T>; N];
+pub type OptionalPair<T> = Option<Pair<Option<T>>>;
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
 pub struct GenericNested<V, Z>
 where
@@ -356,6 +356,7 @@ class InstructionFormatter {
     prefix?: string
   ): IdlAccount[] {
     return accounts
+      .filter((account) => !account.hasOwnProperty("remaining"))
       .map((account) => {
         const accName = sentenceCase(account.name);
         if (account.hasOwnProperty("accounts")) {
@@ -49,7 +49,8 @@ export type IdlInstruction = {
 
 export type IdlInstructionAccountItem =
   | IdlInstructionAccount
-  | IdlInstructionAccounts;
+  | IdlInstructionAccounts
+  | IdlInstructionRemainingAccounts;
 
 export type IdlInstructionAccount = {
   name: string;
@@ -67,6 +68,20 @@ export type IdlInstructionAccounts = {
   accounts: IdlInstructionAccount[];
 };
 
+export type IdlInstructionRemainingAccounts = {
+  name: string;
+  docs?: string[];
+  writable?: boolean;
+  signer?: boolean;
+  account?: string;
+  remaining: IdlRemainingBounds;
+};
+
+export type IdlRemainingBounds = {
+  min?: number;
+  max?: number;
+};
+
 export type IdlPda = {
   seeds: IdlSeed[];
   program?: IdlSeed;
@@ -271,6 +286,12 @@ export function isCompositeAccounts(
   return "accounts" in accountItem;
 }
 
+export function isRemainingAccounts(
+  accountItem: IdlInstructionAccountItem
+): accountItem is IdlInstructionRemainingAccounts {
+  return "remaining" in accountItem;
+}
+
 // Deterministic IDL address as a function of the program id.
 export async function idlAddress(programId: PublicKey): Promise<PublicKey> {
   const base = (await PublicKey.findProgramAddress([], programId))[0];
@@ -9,6 +9,7 @@ import {
   IdlTypeDefTyStruct,
   IdlType,
   isCompositeAccounts,
+  isRemainingAccounts,
   IdlSeedConst,
   IdlSeedArg,
   IdlSeedAccount,
@@ -28,13 +29,13 @@ import {
 } from "./namespace/methods";
 
 export type AccountsGeneric = {
-  [name: string]: PublicKey | AccountsGeneric;
+  [name: string]: PublicKey | PublicKey[] | AccountsGeneric;
 };
 
 export function isAccountsGeneric(
-  accounts: PublicKey | AccountsGeneric
+  accounts: PublicKey | PublicKey[] | AccountsGeneric
 ): accounts is AccountsGeneric {
-  return !(accounts instanceof PublicKey);
+  return !(accounts instanceof PublicKey) && !Array.isArray(accounts);
 }
 
 export type CustomAccountResolver<IDL extends Idl> = (params: {
@@ -86,7 +87,8 @@ export class AccountsResolver<IDL extends Idl> {
     ) {
       depth++;
       if (depth === 16) {
-        const isResolvable = (acc: IdlInstructionAccountItem) => {
+        const isResolvable = (acc: IdlInstructionAccountItem): boolean => {
+          if (isRemainingAccounts(acc)) return false;
           if (!isCompositeAccounts(acc)) {
             return !!(acc.address || acc.pda || acc.relations);
           }
@@ -172,6 +174,13 @@ export class AccountsResolver<IDL extends Idl> {
       // Skip if the account isn't included (thus would be undefined)
       if (partialAccount === undefined) continue;
 
+      if (isRemainingAccounts(accountItem)) {
+        nestedAccountsGeneric[accountName] = (
+          partialAccount as unknown as Address[]
+        ).map(translateAddress);
+        continue;
+      }
+
       if (isPartialAccounts(partialAccount)) {
         // is compound accounts, recurse one level deeper
         if (isCompositeAccounts(accountItem)) {
@@ -273,7 +282,7 @@ export class AccountsResolver<IDL extends Idl> {
       const name = accountOrAccounts.name;
       if (isCompositeAccounts(accountOrAccounts)) {
         this.resolveConst(accountOrAccounts.accounts, [...path, name]);
-      } else {
+      } else if (!isRemainingAccounts(accountOrAccounts)) {
         const account = accountOrAccounts;
 
         if ((account.signer || account.address) && !this.get([...path, name])) {
@@ -308,7 +317,7 @@ export class AccountsResolver<IDL extends Idl> {
           accountOrAccounts.accounts,
           [...path, name]
         );
-      } else {
+      } else if (!isRemainingAccounts(accountOrAccounts)) {
         const account = accountOrAccounts;
         if ((account.pda || account.relations) && !this.get([...path, name])) {
           found++;
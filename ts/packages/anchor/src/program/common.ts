@@ -5,6 +5,7 @@ import {
   IdlInstruction,
   IdlInstructionAccountItem,
   isCompositeAccounts,
+  isRemainingAccounts,
 } from "../idl.js";
 import { Accounts } from "./context.js";
 
@@ -46,6 +47,19 @@ export function validateAccounts(
   ixAccounts.forEach((acc) => {
     if (isCompositeAccounts(acc)) {
       validateAccounts(acc.accounts, accounts[acc.name] as Accounts);
+    } else if (isRemainingAccounts(acc)) {
+      const remaining = accounts[acc.name] ?? [];
+      if (!Array.isArray(remaining)) {
+        throw new Error(`Accounts \`${acc.name}\` must be an array.`);
+      }
+
+      const { min = 0, max } = acc.remaining;
+      const len = remaining.length;
+      if (len < min || (max !== undefined && len > max)) {
+        throw new Error(
+          `Expected ${min}..${max ?? ""} accounts for \`${acc.name}\`, got ${len}.`
+        );
+      }
     } else {
       if (!accounts[acc.name]) {
         throw new Error(`Account \`${acc.name}\` not provided.`);
@@ -8,6 +8,7 @@ import { Address } from "./common.js";
 import {
   IdlInstructionAccountItem,
   IdlInstructionAccounts,
+  IdlInstructionRemainingAccounts,
   IdlInstruction,
 } from "../idl.js";
 
@@ -74,6 +75,8 @@ export type Accounts<
 type Account<A extends IdlInstructionAccountItem> =
   A extends IdlInstructionAccounts
     ? Accounts<A["accounts"][number]>
+    : A extends IdlInstructionRemainingAccounts
+    ? Address[]
     : A extends { optional: true }
     ? Address | null
     : A extends { signer: true }
@@ -9,6 +9,7 @@ import {
   IdlInstructionAccounts,
   IdlInstruction,
   isCompositeAccounts,
+  isRemainingAccounts,
 } from "../../idl.js";
 import { IdlError } from "../../error.js";
 import {
@@ -96,6 +97,15 @@ export default class InstructionNamespaceFactory {
           ).flat();
         }
 
+        if (isRemainingAccounts(acc)) {
+          const addresses = (ctx[acc.name] ?? []) as unknown as Address[];
+          return addresses.map((address) => ({
+            pubkey: translateAddress(address),
+            isWritable: Boolean(acc.writable),
+            isSigner: Boolean(acc.signer),
+          }));
+        }
+
         let pubkey: PublicKey;
         try {
           pubkey = translateAddress(ctx[acc.name] as Address);
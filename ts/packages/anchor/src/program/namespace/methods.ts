@@ -12,6 +12,7 @@ import {
   IdlInstructionAccount,
   IdlInstructionAccountItem,
   IdlInstructionAccounts,
+  IdlInstructionRemainingAccounts,
   IdlTypeDef,
 } from "../../idl.js";
 import Provider from "../../provider.js";
@@ -86,6 +87,8 @@ type ResolvedAccount<
   A extends IdlInstructionAccountItem = IdlInstructionAccountItem
 > = A extends IdlInstructionAccounts
   ? ResolvedAccountsRecursive<A["accounts"][number]>
+  : A extends IdlInstructionRemainingAccounts
+  ? Address[] | undefined
   : A extends NonNullable<Pick<IdlInstructionAccount, "address">>
   ? never
   : A extends NonNullable<Pick<IdlInstructionAccount, "pda">>
@@ -117,6 +120,8 @@ type PartialAccount<
   A extends IdlInstructionAccountItem = IdlInstructionAccountItem
 > = A extends IdlInstructionAccounts
   ? PartialAccounts<A["accounts"][number]>
+  : A extends IdlInstructionRemainingAccounts
+  ? Address[]
   : A extends { optional: true }
   ? Address | null
   : Address;
@@ -145,7 +150,9 @@ export function flattenPartialAccounts<A extends IdlInstructionAccountItem>(
         );
       continue;
     }
-    toReturn[accountName] = isPartialAccounts(account)
+    toReturn[accountName] = Array.isArray(account)
+      ? account.map(translateAddress)
+      : isPartialAccounts(account)
       ? flattenPartialAccounts(account, true)
       : translateAddress(account);
   }
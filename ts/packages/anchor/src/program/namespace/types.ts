@@ -3,6 +3,7 @@ import BN from "bn.js";
 import {
   Idl,
   IdlInstructionAccounts as IdlInstructionAccounts,
+  IdlInstructionRemainingAccounts,
   IdlInstructionAccountItem,
   IdlField,
   IdlInstruction,
@@ -112,6 +113,8 @@ type InstructionAccountsAddresses<
 type InstructionAccountsAddress<A extends IdlInstructionAccountItem> =
   A extends IdlInstructionAccounts
     ? InstructionAccountsAddresses<A["accounts"][number]>
+    : A extends IdlInstructionRemainingAccounts
+    ? PublicKey[]
     : PublicKey;
 
 export type MethodsFn<